@@ -0,0 +1,221 @@
+use std::{path::PathBuf, time::Duration};
+
+use clap::{Args, Parser, Subcommand};
+use vrlh_power_manager_core::{
+    config_path, default_socket_path, parse_alias, Aliases, Config, DeviceCommand, DeviceSelector,
+};
+
+const EXIT_CODES: &str = "\
+Exit codes:
+  0  Success
+  1  Bluetooth or internal error
+  2  Invalid arguments
+  3  No lighthouses matched the selection
+  4  At least one lighthouse did not reach the requested state";
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "vrlh",
+    version,
+    about = "Power on and off your virtual reality lighthouses"
+)]
+#[command(after_help = EXIT_CODES)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+    #[command(flatten)]
+    pub global: GlobalArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct GlobalArgs {
+    /// Seconds to scan for lighthouses before acting, instead of the configured duration
+    #[arg(short, long, global = true)]
+    pub duration: Option<u64>,
+    /// Seconds to wait for lighthouses to reach the requested state, instead of the configured timeout
+    #[arg(short, long, global = true)]
+    pub timeout: Option<u64>,
+    /// Configuration file, see the README for where it is looked up otherwise
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+    /// Settings read from the configuration file
+    #[arg(skip)]
+    pub settings: Config,
+    /// Print results as JSON instead of a table
+    #[arg(long, global = true)]
+    pub json: bool,
+    /// Print every status update to stderr while waiting
+    #[arg(short, long, global = true)]
+    pub verbose: bool,
+    /// Alias for a lighthouse name or address, e.g. `left=LHB-1A2B3C4D`
+    #[arg(
+        long = "alias",
+        global = true,
+        env = "VRLH_ALIASES",
+        value_delimiter = ',',
+        value_name = "ALIAS=TARGET",
+        value_parser = parse_alias
+    )]
+    pub aliases: Vec<(String, String)>,
+    /// Send commands through a running daemon instead of using the adapter directly
+    #[arg(
+        long,
+        global = true,
+        env = "VRLH_SOCKET",
+        num_args = 0..=1,
+        default_missing_value = "",
+        value_name = "SOCKET"
+    )]
+    pub daemon: Option<PathBuf>,
+}
+
+impl GlobalArgs {
+    pub fn config_path(&self) -> PathBuf {
+        self.config.clone().unwrap_or_else(|| config_path(None))
+    }
+
+    pub fn duration(&self) -> u64 {
+        self.duration.unwrap_or(self.settings.scan.duration_secs)
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout.unwrap_or(self.settings.timeouts.command_secs))
+    }
+
+    pub fn aliases(&self) -> Aliases {
+        self.aliases.iter().cloned().collect()
+    }
+
+    pub fn daemon_socket(&self) -> Option<PathBuf> {
+        self.daemon
+            .as_ref()
+            .map(|path| match path.as_os_str().is_empty() {
+                true => default_socket_path(),
+                false => path.clone(),
+            })
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// List every lighthouse in range
+    Scan,
+    /// Show the current status of lighthouses
+    Status(Selection),
+    /// Turn lighthouses on
+    On(Selection),
+    /// Turn lighthouses off completely
+    Off(Selection),
+    /// Keep the motor spinning with lasers off
+    Standby(Selection),
+    /// Blink the status light of lighthouses
+    Identify(Selection),
+    /// Print every event from a running daemon until interrupted
+    Watch,
+    /// Let SteamVR power lighthouses on and off through a startup application
+    #[command(name = "steamvr", subcommand)]
+    SteamVr(SteamVrCommand),
+    /// Check automation rules and explain why they fire
+    #[command(subcommand)]
+    Rules(RulesCommand),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum RulesCommand {
+    /// Validate a rules file without running it
+    Check { path: PathBuf },
+    /// Explain what the daemon's rules would do for an input, e.g.
+    /// `{"type":"Status","name":"LHB-1A2B3C4D","addr":"","status":"Stopped"}`
+    DryRun { input: String },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SteamVrCommand {
+    /// Generate a manifest that powers lighthouses on whenever SteamVR starts and register it
+    Register(RegisterArgs),
+    /// Remove the generated manifest from SteamVR
+    Unregister(ManifestArgs),
+    /// Wait for SteamVR's processes to exit, then power lighthouses off
+    Companion(CompanionArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct ManifestArgs {
+    /// Where the generated manifest is written
+    #[arg(long)]
+    pub manifest: Option<PathBuf>,
+    /// Steam `config` directory containing SteamVR's `appconfig.json`
+    #[arg(long)]
+    pub steam_config: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct RegisterArgs {
+    #[command(flatten)]
+    pub paths: ManifestArgs,
+    /// Lighthouse names, addresses or aliases, every lighthouse in range if none are given
+    pub targets: Vec<DeviceSelector>,
+    /// Also start a companion that powers lighthouses off once SteamVR exits
+    ///
+    /// The companion notices the exit by polling running processes, not through SteamVR
+    #[arg(long)]
+    pub companion: bool,
+    #[command(flatten)]
+    pub shutdown: ShutdownArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct CompanionArgs {
+    /// Lighthouse names, addresses or aliases, every lighthouse in range if none are given
+    pub targets: Vec<DeviceSelector>,
+    #[command(flatten)]
+    pub shutdown: ShutdownArgs,
+    /// Keep running attached to the launching process instead of detaching from it
+    #[arg(long)]
+    pub foreground: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ShutdownArgs {
+    /// Command sent once SteamVR exits, instead of the configured off command
+    #[arg(long)]
+    pub off_command: Option<DeviceCommand>,
+    /// Seconds to wait after SteamVR exits, in case it is started again
+    #[arg(long, default_value_t = 30)]
+    pub grace: u64,
+}
+
+#[derive(Debug, Args)]
+pub struct Selection {
+    /// Lighthouse names, addresses or aliases
+    #[arg(required_unless_present = "all")]
+    pub targets: Vec<DeviceSelector>,
+    /// Select every lighthouse in range
+    #[arg(short, long, conflicts_with = "targets")]
+    pub all: bool,
+}
+
+impl Selection {
+    /// Selects the targets, or every lighthouse if there are none
+    pub fn targets_or_all(targets: &[DeviceSelector]) -> Self {
+        Self {
+            targets: targets.to_vec(),
+            all: targets.is_empty(),
+        }
+    }
+
+    pub fn selectors(&self) -> Vec<DeviceSelector> {
+        match self.all {
+            true => vec![DeviceSelector::all()],
+            false => self.targets.clone(),
+        }
+    }
+
+    /// Selectors with aliases replaced, for daemons that do not know about local aliases
+    pub fn resolved_selectors(&self, aliases: &Aliases) -> Vec<DeviceSelector> {
+        self.selectors()
+            .iter()
+            .map(|selector| selector.resolve(aliases))
+            .collect()
+    }
+}
@@ -0,0 +1,41 @@
+use vrlh_power_manager_core::{DeviceLocalStatus, IdentifyParams, METHOD_IDENTIFY};
+
+use crate::{
+    cli::{GlobalArgs, Selection},
+    output::print_devices,
+};
+
+use super::{connect, drain, scan_devices, snapshot};
+
+pub async fn identify(global: &GlobalArgs, selection: &Selection) -> crate::Result<()> {
+    let (infos, timed_out) = if let Some(mut client) = connect(global).await? {
+        let params = IdentifyParams {
+            targets: selection.resolved_selectors(&global.aliases()),
+        };
+        let infos = client
+            .call(METHOD_IDENTIFY, params)
+            .await
+            .map_err(crate::Error::from_daemon)?;
+        (infos, false)
+    } else {
+        let (devices, _) = scan_devices(global).await?;
+        let selected = devices.select(&selection.selectors(), &global.aliases());
+        if selected.is_empty() {
+            return Err(crate::Error::NoDevices);
+        }
+        let rx = devices.start_identify(selected.clone());
+        let (mut warnings, timed_out) = drain(global, rx, Some(global.timeout())).await;
+        (snapshot(&selected, &mut warnings), timed_out)
+    };
+    print_devices(global, &infos)?;
+
+    let incomplete = infos
+        .into_iter()
+        .filter(|info| timed_out || !matches!(info.local, Some(DeviceLocalStatus::Disconnected)))
+        .map(|info| info.name)
+        .collect::<Vec<_>>();
+    match incomplete.is_empty() {
+        true => Ok(()),
+        false => Err(crate::Error::Incomplete(incomplete)),
+    }
+}
@@ -0,0 +1,83 @@
+mod identify;
+mod power;
+mod rules;
+mod scan;
+mod status;
+mod steamvr;
+mod watch;
+
+use std::{collections::HashMap, time::Duration};
+
+use tokio::{sync::mpsc::Receiver, time::timeout};
+use vrlh_power_manager_core::{Device, DeviceInfo, DeviceList, DeviceWarning, RpcClient};
+
+pub use identify::*;
+pub use power::*;
+pub use rules::*;
+pub use scan::*;
+pub use status::*;
+pub use steamvr::*;
+pub use watch::*;
+
+use crate::{cli::GlobalArgs, output::print_progress};
+
+/// Warnings collected while draining updates, keyed by device address
+type Warnings = HashMap<String, Vec<DeviceWarning>>;
+
+/// Connects to the daemon if one was requested
+async fn connect(global: &GlobalArgs) -> crate::Result<Option<RpcClient>> {
+    let Some(socket) = global.daemon_socket() else {
+        return Ok(None);
+    };
+    let client = RpcClient::connect(&socket).await.map_err(|err| {
+        crate::Error::VrlhCli(format!(
+            "Could not reach daemon at '{}': {err}",
+            socket.display()
+        ))
+    })?;
+    Ok(Some(client))
+}
+
+async fn scan_devices(global: &GlobalArgs) -> crate::Result<(DeviceList, Warnings)> {
+    let devices = DeviceList::init().await?;
+    devices.set_config(global.settings.clone());
+    let rx = devices.start_scan(global.duration())?;
+    let (warnings, _) = drain(global, rx, None).await;
+    Ok((devices, warnings))
+}
+
+/// Receives updates until the sender closes or the timeout elapses, returning whether it timed out
+async fn drain(
+    global: &GlobalArgs,
+    mut rx: Receiver<DeviceInfo>,
+    limit: Option<Duration>,
+) -> (Warnings, bool) {
+    let mut warnings = Warnings::new();
+    let future = async {
+        while let Some(info) = rx.recv().await {
+            print_progress(global, &info);
+            warnings.entry(info.addr).or_default().extend(info.warnings);
+        }
+    };
+    let timed_out = if let Some(limit) = limit {
+        timeout(limit, future).await.is_err()
+    } else {
+        future.await;
+        false
+    };
+    (warnings, timed_out)
+}
+
+fn snapshot(devices: &[Device], warnings: &mut Warnings) -> Vec<DeviceInfo> {
+    let mut infos = devices
+        .iter()
+        .map(|device| {
+            let (local, remote) = device.get_last_statuses();
+            let mut info = DeviceInfo::from_device_statuses(device, local, remote);
+            info.warnings = warnings.remove(&info.addr).unwrap_or_default();
+            info
+        })
+        .collect::<Vec<_>>();
+    infos.sort_by(|a, b| a.name.cmp(&b.name));
+    infos
+}
@@ -0,0 +1,46 @@
+use vrlh_power_manager_core::{DeviceCommand, PowerParams, METHOD_POWER};
+
+use crate::{
+    cli::{GlobalArgs, Selection},
+    output::print_devices,
+};
+
+use super::{connect, drain, scan_devices, snapshot};
+
+pub async fn power(
+    global: &GlobalArgs,
+    selection: &Selection,
+    command: DeviceCommand,
+) -> crate::Result<()> {
+    let infos = if let Some(mut client) = connect(global).await? {
+        let params = PowerParams {
+            command: command.clone(),
+            targets: selection.resolved_selectors(&global.aliases()),
+        };
+        client
+            .call(METHOD_POWER, params)
+            .await
+            .map_err(crate::Error::from_daemon)?
+    } else {
+        let (devices, _) = scan_devices(global).await?;
+        let selected = devices.select(&selection.selectors(), &global.aliases());
+        if selected.is_empty() {
+            return Err(crate::Error::NoDevices);
+        }
+        let rx = devices.start_power(selected.clone(), &command);
+        let (mut warnings, _) = drain(global, rx, Some(global.timeout())).await;
+        snapshot(&selected, &mut warnings)
+    };
+    print_devices(global, &infos)?;
+
+    let target = command.target();
+    let incomplete = infos
+        .into_iter()
+        .filter(|info| info.remote.as_ref() != Some(&target))
+        .map(|info| info.name)
+        .collect::<Vec<_>>();
+    match incomplete.is_empty() {
+        true => Ok(()),
+        false => Err(crate::Error::Incomplete(incomplete)),
+    }
+}
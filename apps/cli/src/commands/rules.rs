@@ -0,0 +1,47 @@
+use vrlh_power_manager_core::{
+    parse_rules, RuleEvaluation, RuleInput, RulesDryRunParams, METHOD_RULES_DRY_RUN,
+};
+
+use crate::cli::{GlobalArgs, RulesCommand};
+
+use super::connect;
+
+pub async fn rules(global: &GlobalArgs, command: &RulesCommand) -> crate::Result<()> {
+    match command {
+        RulesCommand::Check { path } => {
+            let rules = parse_rules(&std::fs::read_to_string(path)?)?;
+            if global.json {
+                println!("{}", serde_json::to_string(&rules)?);
+                return Ok(());
+            }
+            let enabled = rules.rules.iter().filter(|rule| rule.enabled).count();
+            println!("{} rules are valid, {enabled} enabled", rules.rules.len());
+        }
+        RulesCommand::DryRun { input } => {
+            let input = serde_json::from_str::<RuleInput>(input)?;
+            let Some(mut client) = connect(global).await? else {
+                let msg = "Dry runs require a running daemon with rules, pass --daemon".into();
+                return Err(crate::Error::VrlhCli(msg));
+            };
+            let evaluations = client
+                .call::<_, Vec<RuleEvaluation>>(METHOD_RULES_DRY_RUN, RulesDryRunParams { input })
+                .await
+                .map_err(crate::Error::from_daemon)?;
+            if global.json {
+                println!("{}", serde_json::to_string(&evaluations)?);
+                return Ok(());
+            }
+            for evaluation in evaluations {
+                let outcome = match evaluation.fired {
+                    true => "would fire",
+                    false => "would not fire",
+                };
+                println!("{} {outcome}", evaluation.rule);
+                for line in &evaluation.explanation {
+                    println!("  {line}");
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,21 @@
+use vrlh_power_manager_core::{ScanParams, METHOD_SCAN};
+
+use crate::{cli::GlobalArgs, output::print_devices};
+
+use super::{connect, scan_devices, snapshot};
+
+pub async fn scan(global: &GlobalArgs) -> crate::Result<()> {
+    let infos = if let Some(mut client) = connect(global).await? {
+        let params = ScanParams {
+            duration: global.duration,
+        };
+        client
+            .call(METHOD_SCAN, params)
+            .await
+            .map_err(crate::Error::from_daemon)?
+    } else {
+        let (devices, mut warnings) = scan_devices(global).await?;
+        snapshot(&devices.get_devices(), &mut warnings)
+    };
+    print_devices(global, &infos)
+}
@@ -0,0 +1,29 @@
+use vrlh_power_manager_core::{StatusParams, METHOD_STATUS};
+
+use crate::{
+    cli::{GlobalArgs, Selection},
+    output::print_devices,
+};
+
+use super::{connect, scan_devices, snapshot};
+
+pub async fn status(global: &GlobalArgs, selection: &Selection) -> crate::Result<()> {
+    let infos = if let Some(mut client) = connect(global).await? {
+        // The daemon already tracks statuses so there is no need to scan first
+        let params = StatusParams {
+            targets: selection.resolved_selectors(&global.aliases()),
+        };
+        client
+            .call(METHOD_STATUS, params)
+            .await
+            .map_err(crate::Error::from_daemon)?
+    } else {
+        let (devices, mut warnings) = scan_devices(global).await?;
+        let selected = devices.select(&selection.selectors(), &global.aliases());
+        if selected.is_empty() {
+            return Err(crate::Error::NoDevices);
+        }
+        snapshot(&selected, &mut warnings)
+    };
+    print_devices(global, &infos)
+}
@@ -0,0 +1,166 @@
+use std::{env, path::PathBuf, time::Duration};
+
+use tokio::time::sleep;
+use vrlh_power_manager_core::{
+    default_manifest_path, default_steam_config_dir, register_manifest, steamvr_watcher,
+    unregister_manifest, write_manifest, DeviceSelector, ManifestApp,
+};
+
+use crate::cli::{
+    CompanionArgs, GlobalArgs, ManifestArgs, RegisterArgs, Selection, ShutdownArgs, SteamVrCommand,
+};
+
+use super::power;
+
+pub async fn steamvr(global: &GlobalArgs, command: &SteamVrCommand) -> crate::Result<()> {
+    match command {
+        SteamVrCommand::Register(args) => register(global, args),
+        SteamVrCommand::Unregister(args) => unregister(args),
+        SteamVrCommand::Companion(args) => companion(global, args).await,
+    }
+}
+
+fn register(global: &GlobalArgs, args: &RegisterArgs) -> crate::Result<()> {
+    let (manifest, steam_config) = paths(&args.paths)?;
+    let mut apps = vec![ManifestApp {
+        key: "poweron".into(),
+        name: "Lighthouse Power On".into(),
+        description: "Powers on lighthouses when SteamVR starts".into(),
+        arguments: [
+            global_arguments(global),
+            vec!["on".into()],
+            targets(&args.targets),
+        ]
+        .concat(),
+    }];
+    if args.companion {
+        let command = vec!["steamvr".into(), "companion".into()];
+        apps.push(ManifestApp {
+            key: "companion".into(),
+            name: "Lighthouse Power Off".into(),
+            description: "Powers off lighthouses after SteamVR exits".into(),
+            arguments: [
+                global_arguments(global),
+                command,
+                args.targets.iter().map(ToString::to_string).collect(),
+                shutdown_arguments(&args.shutdown),
+            ]
+            .concat(),
+        });
+    }
+
+    write_manifest(&manifest, &env::current_exe()?, &apps)?;
+    register_manifest(&steam_config, &manifest)?;
+    println!("Registered '{}' with SteamVR", manifest.display());
+    println!("Enable it under Settings > Startup / Shutdown > Choose Startup Overlay Apps");
+    Ok(())
+}
+
+fn unregister(args: &ManifestArgs) -> crate::Result<()> {
+    let (manifest, steam_config) = paths(args)?;
+    match unregister_manifest(&steam_config, &manifest)? {
+        true => println!("Unregistered '{}' from SteamVR", manifest.display()),
+        false => println!("'{}' was not registered with SteamVR", manifest.display()),
+    }
+    Ok(())
+}
+
+/// SteamVR may end the processes it launched when it exits, so the companion
+/// restarts itself in a new process group before waiting
+async fn companion(global: &GlobalArgs, args: &CompanionArgs) -> crate::Result<()> {
+    #[cfg(unix)]
+    if !args.foreground {
+        use std::os::unix::process::CommandExt;
+
+        std::process::Command::new(env::current_exe()?)
+            .args(env::args_os().skip(1))
+            .arg("--foreground")
+            .process_group(0)
+            .stdin(std::process::Stdio::null())
+            .spawn()?;
+        return Ok(());
+    }
+
+    let mut running = steamvr_watcher().watch();
+    let grace = Duration::from_secs(args.shutdown.grace);
+    let closed = || crate::Error::VrlhCli("SteamVR watcher stopped unexpectedly!".into());
+    running
+        .wait_for(|running| *running)
+        .await
+        .map_err(|_| closed())?;
+    loop {
+        running
+            .wait_for(|running| !*running)
+            .await
+            .map_err(|_| closed())?;
+        tokio::select! {
+            () = sleep(grace) => break,
+            restarted = running.wait_for(|running| *running) => {
+                restarted.map_err(|_| closed())?;
+            }
+        }
+    }
+
+    let selection = Selection::targets_or_all(&args.targets);
+    let off_command = args
+        .shutdown
+        .off_command
+        .clone()
+        .unwrap_or_else(|| global.settings.power.off_command.clone());
+    power(global, &selection, off_command).await
+}
+
+fn paths(args: &ManifestArgs) -> crate::Result<(PathBuf, PathBuf)> {
+    let manifest = args
+        .manifest
+        .clone()
+        .or_else(default_manifest_path)
+        .ok_or_else(|| crate::Error::VrlhCli("Could not find a data directory!".into()))?;
+    let steam_config = args
+        .steam_config
+        .clone()
+        .or_else(default_steam_config_dir)
+        .ok_or_else(|| {
+            crate::Error::VrlhCli("Could not find Steam, pass --steam-config instead!".into())
+        })?;
+    Ok((manifest, steam_config))
+}
+
+/// Options SteamVR's environment would otherwise lack, such as aliases set through `VRLH_ALIASES`
+fn global_arguments(global: &GlobalArgs) -> Vec<String> {
+    let mut arguments = Vec::new();
+    if let Some(duration) = global.duration {
+        arguments.push(format!("--duration={duration}"));
+    }
+    if let Some(timeout) = global.timeout {
+        arguments.push(format!("--timeout={timeout}"));
+    }
+    if let Some(config) = &global.config {
+        arguments.push(format!("--config={}", config.display()));
+    }
+    arguments.extend(
+        global
+            .aliases
+            .iter()
+            .map(|(alias, target)| format!("--alias={alias}={target}")),
+    );
+    if let Some(socket) = global.daemon_socket() {
+        arguments.push(format!("--daemon={}", socket.display()));
+    }
+    arguments
+}
+
+fn targets(targets: &[DeviceSelector]) -> Vec<String> {
+    match targets.is_empty() {
+        true => vec!["--all".into()],
+        false => targets.iter().map(ToString::to_string).collect(),
+    }
+}
+
+fn shutdown_arguments(shutdown: &ShutdownArgs) -> Vec<String> {
+    let mut arguments = vec![format!("--grace={}", shutdown.grace)];
+    if let Some(off_command) = &shutdown.off_command {
+        arguments.push(format!("--off-command={off_command}"));
+    }
+    arguments
+}
@@ -0,0 +1,31 @@
+use serde_json::Value;
+use vrlh_power_manager_core::{DeviceListEvent, METHOD_SUBSCRIBE};
+
+use crate::{cli::GlobalArgs, output::print_update};
+
+use super::connect;
+
+pub async fn watch(global: &GlobalArgs) -> crate::Result<()> {
+    let Some(mut client) = connect(global).await? else {
+        let msg = "Watching requires a running daemon, pass --daemon".into();
+        return Err(crate::Error::VrlhCli(msg));
+    };
+    client
+        .call::<_, Value>(METHOD_SUBSCRIBE, Value::Null)
+        .await
+        .map_err(crate::Error::from_daemon)?;
+
+    while let Some(event) = client.next_event().await? {
+        if global.json {
+            println!("{}", serde_json::to_string(&event)?);
+            continue;
+        }
+        match event {
+            DeviceListEvent::Device(info) => print_update(&info),
+            DeviceListEvent::Status(message) => eprintln!("{message}"),
+            DeviceListEvent::ScanStarted { duration } => eprintln!("Scanning for {duration}s..."),
+            DeviceListEvent::ScanFinished => eprintln!("Done scanning for devices!"),
+        }
+    }
+    Ok(())
+}
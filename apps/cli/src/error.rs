@@ -0,0 +1,40 @@
+use std::process::ExitCode;
+
+use thiserror::Error;
+use vrlh_power_manager_core::ERROR_NO_DEVICES;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("{}", .0)]
+    VrlhCli(String),
+    #[error("{}", .0)]
+    VrlhCore(#[from] vrlh_power_manager_core::Error),
+    #[error("{}", .0)]
+    Io(#[from] std::io::Error),
+    #[error("{}", .0)]
+    Json(#[from] serde_json::Error),
+    #[error("No lighthouses matched the given selection!")]
+    NoDevices,
+    #[error("Command did not complete for: {}", .0.join(", "))]
+    Incomplete(Vec<String>),
+}
+
+impl Error {
+    /// Maps errors reported by a daemon back to the matching local error
+    pub fn from_daemon(err: vrlh_power_manager_core::Error) -> Self {
+        match err {
+            vrlh_power_manager_core::Error::Rpc(ERROR_NO_DEVICES, _) => Self::NoDevices,
+            err => Self::VrlhCore(err),
+        }
+    }
+
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            Self::VrlhCli(_) | Self::VrlhCore(_) | Self::Io(_) | Self::Json(_) => ExitCode::from(1),
+            Self::NoDevices => ExitCode::from(3),
+            Self::Incomplete(_) => ExitCode::from(4),
+        }
+    }
+}
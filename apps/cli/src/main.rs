@@ -0,0 +1,52 @@
+mod cli;
+mod commands;
+mod error;
+mod output;
+
+use std::process::ExitCode;
+
+use clap::Parser;
+use vrlh_power_manager_core::{Config, DeviceCommand};
+
+pub use error::*;
+
+use crate::cli::{Cli, Command};
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let mut cli = Cli::parse();
+    let result = match Config::load(&cli.global.config_path()) {
+        Ok(settings) => {
+            cli.global.settings = settings;
+            run(&cli).await
+        }
+        Err(err) => Err(err.into()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{err}");
+            err.exit_code()
+        }
+    }
+}
+
+async fn run(cli: &Cli) -> Result<()> {
+    match &cli.command {
+        Command::Scan => commands::scan(&cli.global).await,
+        Command::Status(selection) => commands::status(&cli.global, selection).await,
+        Command::On(selection) => {
+            commands::power(&cli.global, selection, DeviceCommand::Activate).await
+        }
+        Command::Off(selection) => {
+            commands::power(&cli.global, selection, DeviceCommand::Sleep).await
+        }
+        Command::Standby(selection) => {
+            commands::power(&cli.global, selection, DeviceCommand::Standby).await
+        }
+        Command::Identify(selection) => commands::identify(&cli.global, selection).await,
+        Command::Watch => commands::watch(&cli.global).await,
+        Command::SteamVr(command) => commands::steamvr(&cli.global, command).await,
+        Command::Rules(command) => commands::rules(&cli.global, command).await,
+    }
+}
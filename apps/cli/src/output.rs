@@ -0,0 +1,52 @@
+use vrlh_power_manager_core::DeviceInfo;
+
+use crate::cli::GlobalArgs;
+
+pub fn print_devices(global: &GlobalArgs, devices: &[DeviceInfo]) -> crate::Result<()> {
+    if global.json {
+        println!("{}", serde_json::to_string_pretty(devices)?);
+        return Ok(());
+    }
+    if devices.is_empty() {
+        println!("No lighthouses found!");
+        return Ok(());
+    }
+    println!("{:<14} {:<17} {:<12} LOCAL", "NAME", "ADDRESS", "REMOTE");
+    for info in devices {
+        println!(
+            "{:<14} {:<17} {:<12} {}",
+            info.name,
+            info.addr,
+            info.remote
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_default(),
+            info.local
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_default(),
+        );
+        for warning in &info.warnings {
+            println!("  warning: {warning}");
+        }
+    }
+    Ok(())
+}
+
+pub fn print_progress(global: &GlobalArgs, info: &DeviceInfo) {
+    if global.verbose {
+        print_update(info);
+    }
+}
+
+pub fn print_update(info: &DeviceInfo) {
+    if let Some(local) = &info.local {
+        eprintln!("{}: {local}", info.name);
+    }
+    if let Some(remote) = &info.remote {
+        eprintln!("{}: {remote}", info.name);
+    }
+    for warning in &info.warnings {
+        eprintln!("{}: warning: {warning}", info.name);
+    }
+}
@@ -0,0 +1,72 @@
+use std::{net::SocketAddr, path::PathBuf, process::ExitCode};
+
+use clap::Parser;
+use vrlh_power_manager_core::{parse_alias, Aliases, DeviceList};
+use vrlh_power_manager_daemon::{http, watch_config, Daemon};
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "vrlh-http",
+    version,
+    about = "HTTP API for your virtual reality lighthouses, see /openapi.json"
+)]
+struct Cli {
+    /// Address to listen on, use 0.0.0.0 to allow other machines on the network
+    #[arg(
+        short,
+        long,
+        env = "VRLH_HTTP_LISTEN",
+        default_value = "127.0.0.1:7373"
+    )]
+    listen: SocketAddr,
+    /// Token clients must send as `Authorization: Bearer <token>`
+    #[arg(long, env = "VRLH_HTTP_TOKEN")]
+    token: Option<String>,
+    /// Configuration file, see the README for where it is looked up otherwise
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+    /// Seconds to scan for lighthouses on startup instead of the configured duration, 0 to skip
+    #[arg(short, long)]
+    duration: Option<u64>,
+    /// Alias for a lighthouse name or address, e.g. `left=LHB-1A2B3C4D`
+    #[arg(
+        long = "alias",
+        env = "VRLH_ALIASES",
+        value_delimiter = ',',
+        value_name = "ALIAS=TARGET",
+        value_parser = parse_alias
+    )]
+    aliases: Vec<(String, String)>,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> vrlh_power_manager_daemon::Result<()> {
+    let aliases = cli.aliases.into_iter().collect::<Aliases>();
+    let daemon = Daemon::new(DeviceList::init().await?, aliases);
+    daemon.follow_config(watch_config(cli.config.as_deref())?);
+
+    if cli.duration != Some(0) {
+        let (daemon, duration) = (daemon.clone(), cli.duration);
+        tokio::spawn(async move { daemon.scan(duration).await });
+    }
+
+    eprintln!("Listening on 'http://{}'", cli.listen);
+    let result = tokio::select! {
+        result = http::serve(daemon.clone(), cli.listen, cli.token) => result,
+        _ = tokio::signal::ctrl_c() => Ok(()),
+    };
+
+    daemon.shutdown().await;
+    result
+}
@@ -0,0 +1,369 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use futures::StreamExt;
+use vrlh_power_manager_core::{
+    DeviceCommand, DeviceInfo, DeviceRemoteStatus, DeviceSelector, SuspendConfig,
+};
+use zbus::{proxy, zvariant::OwnedFd};
+
+use super::{to_daemon_error, Bus};
+use crate::Daemon;
+
+#[proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1"
+)]
+trait Logind {
+    fn inhibit(&self, what: &str, who: &str, why: &str, mode: &str) -> zbus::Result<OwnedFd>;
+
+    #[zbus(signal)]
+    fn prepare_for_sleep(&self, start: bool) -> zbus::Result<()>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.login1.Session",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1/session/auto"
+)]
+pub trait LogindSession {
+    #[zbus(property)]
+    fn idle_hint(&self) -> zbus::Result<bool>;
+
+    /// Microseconds since the unix epoch
+    #[zbus(property)]
+    fn idle_since_hint(&self) -> zbus::Result<u64>;
+}
+
+/// Session of the daemon itself, which needs to run inside the user's login session
+pub async fn logind_session(bus: &Bus) -> crate::Result<LogindSessionProxy<'static>> {
+    let connection = bus
+        .builder()?
+        .build()
+        .await
+        .map_err(|err| to_daemon_error(&err))?;
+    LogindSessionProxy::new(&connection)
+        .await
+        .map_err(|err| to_daemon_error(&err))
+}
+
+/// How long the session has been idle, zero while it is not
+pub async fn logind_idle_time(session: &LogindSessionProxy<'_>) -> crate::Result<Duration> {
+    if !session
+        .idle_hint()
+        .await
+        .map_err(|err| to_daemon_error(&err))?
+    {
+        return Ok(Duration::ZERO);
+    }
+    let since = session
+        .idle_since_hint()
+        .await
+        .map_err(|err| to_daemon_error(&err))?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    Ok(now.saturating_sub(Duration::from_micros(since)))
+}
+
+/// Sends the configured command before the system suspends, holding a delay inhibitor
+/// so logind waits for it, until the daemon shuts down
+///
+/// Runs against the system bus unless another one stands in for logind
+pub async fn run_suspend(daemon: Daemon, bus: Bus, config: SuspendConfig) -> crate::Result<()> {
+    let connection = bus
+        .builder()?
+        .build()
+        .await
+        .map_err(|err| to_daemon_error(&err))?;
+    let logind = LogindProxy::new(&connection)
+        .await
+        .map_err(|err| to_daemon_error(&err))?;
+    follow_sleep(&daemon, &logind, &config).await
+}
+
+/// The part of the daemon suspend handling acts on, so it can be followed without bluetooth
+trait Lighthouses {
+    fn status(&self, targets: &[DeviceSelector]) -> crate::Result<Vec<DeviceInfo>>;
+
+    async fn power(
+        &self,
+        command: &DeviceCommand,
+        targets: &[DeviceSelector],
+    ) -> crate::Result<Vec<DeviceInfo>>;
+
+    fn publish_status(&self, message: String);
+}
+
+impl Lighthouses for Daemon {
+    fn status(&self, targets: &[DeviceSelector]) -> crate::Result<Vec<DeviceInfo>> {
+        Daemon::status(self, targets)
+    }
+
+    async fn power(
+        &self,
+        command: &DeviceCommand,
+        targets: &[DeviceSelector],
+    ) -> crate::Result<Vec<DeviceInfo>> {
+        Daemon::power(self, command, targets).await
+    }
+
+    fn publish_status(&self, message: String) {
+        Daemon::publish_status(self, message);
+    }
+}
+
+async fn follow_sleep(
+    lighthouses: &impl Lighthouses,
+    logind: &LogindProxy<'_>,
+    config: &SuspendConfig,
+) -> crate::Result<()> {
+    let mut signals = logind
+        .receive_prepare_for_sleep()
+        .await
+        .map_err(|err| to_daemon_error(&err))?;
+
+    let mut inhibitor = inhibit(logind).await;
+    let mut previous = Vec::new();
+    while let Some(signal) = signals.next().await {
+        let Ok(args) = signal.args() else {
+            continue;
+        };
+        if args.start {
+            previous = restorable(lighthouses, config);
+            lighthouses.publish_status(format!(
+                r#"System is suspending, sending "{}" to lighthouses"#,
+                config.command
+            ));
+            let _ = lighthouses.power(&config.command, &config.targets).await;
+            // Dropping the descriptor releases the inhibitor and lets the suspend continue
+            inhibitor = None;
+        } else {
+            drop(inhibitor);
+            inhibitor = inhibit(logind).await;
+            if config.restore {
+                restore(lighthouses, std::mem::take(&mut previous)).await;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Suspending still works without an inhibitor, lighthouses just may not be reached in time
+async fn inhibit(logind: &LogindProxy<'_>) -> Option<OwnedFd> {
+    let why = "Power off lighthouses before suspending";
+    match logind
+        .inhibit("sleep", "VRLH Power Manager", why, "delay")
+        .await
+    {
+        Ok(fd) => Some(fd),
+        Err(err) => {
+            eprintln!("Could not take a logind sleep inhibitor: {err}");
+            None
+        }
+    }
+}
+
+/// Commands that bring the selected lighthouses back into their current state
+fn restorable(
+    lighthouses: &impl Lighthouses,
+    config: &SuspendConfig,
+) -> Vec<(DeviceCommand, String)> {
+    let Ok(infos) = lighthouses.status(&config.targets) else {
+        return Vec::new();
+    };
+    infos
+        .into_iter()
+        .filter_map(|info| match info.remote? {
+            DeviceRemoteStatus::Active => Some((DeviceCommand::Activate, info.addr)),
+            DeviceRemoteStatus::Standby => Some((DeviceCommand::Standby, info.addr)),
+            _ => None,
+        })
+        .collect()
+}
+
+async fn restore(lighthouses: &impl Lighthouses, previous: Vec<(DeviceCommand, String)>) {
+    if previous.is_empty() {
+        return;
+    }
+    lighthouses.publish_status("System resumed, restoring lighthouses".into());
+    for command in [DeviceCommand::Activate, DeviceCommand::Standby] {
+        let targets = previous
+            .iter()
+            .filter(|(previous, _)| *previous == command)
+            .map(|(_, addr)| DeviceSelector::new(addr.as_str()))
+            .collect::<Vec<_>>();
+        if !targets.is_empty() {
+            let _ = lighthouses.power(&command, &targets).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{pipe, PipeReader, Read},
+        sync::{Arc, Mutex},
+    };
+
+    use tokio::{
+        task::spawn_blocking,
+        time::{sleep, timeout},
+    };
+    use zbus::{interface, object_server::SignalEmitter};
+
+    use super::*;
+    use crate::dbus::tests::{device_info, PrivateBus};
+
+    const LOGIND_PATH: &str = "/org/freedesktop/login1";
+
+    /// Hands out pipes as inhibitors, keeping the read ends to see when they are released
+    #[derive(Clone, Default)]
+    struct StandInLogind {
+        inhibitors: Arc<Mutex<Vec<PipeReader>>>,
+    }
+
+    #[interface(name = "org.freedesktop.login1.Manager")]
+    impl StandInLogind {
+        fn inhibit(&self, what: &str, who: &str, why: &str, mode: &str) -> OwnedFd {
+            assert_eq!((what, who, mode), ("sleep", "VRLH Power Manager", "delay"));
+            assert!(!why.is_empty());
+            let (reader, writer) = pipe().unwrap();
+            self.inhibitors.lock().unwrap().push(reader);
+            std::os::fd::OwnedFd::from(writer).into()
+        }
+
+        #[zbus(signal)]
+        async fn prepare_for_sleep(emitter: &SignalEmitter<'_>, start: bool) -> zbus::Result<()>;
+    }
+
+    impl StandInLogind {
+        fn inhibitor_count(&self) -> usize {
+            self.inhibitors.lock().unwrap().len()
+        }
+
+        /// Resolves once every copy of the first inhibitor is closed
+        async fn released(&self) {
+            let mut reader = self.inhibitors.lock().unwrap().remove(0);
+            spawn_blocking(move || reader.read_to_end(&mut Vec::new()))
+                .await
+                .unwrap()
+                .unwrap();
+        }
+    }
+
+    #[derive(Default)]
+    struct FakeLighthouses {
+        infos: Vec<DeviceInfo>,
+        commands: Mutex<Vec<(DeviceCommand, Vec<DeviceSelector>)>>,
+    }
+
+    impl FakeLighthouses {
+        fn commands(&self) -> Vec<(DeviceCommand, Vec<DeviceSelector>)> {
+            self.commands.lock().unwrap().clone()
+        }
+    }
+
+    impl Lighthouses for FakeLighthouses {
+        fn status(&self, _targets: &[DeviceSelector]) -> crate::Result<Vec<DeviceInfo>> {
+            Ok(self.infos.clone())
+        }
+
+        async fn power(
+            &self,
+            command: &DeviceCommand,
+            targets: &[DeviceSelector],
+        ) -> crate::Result<Vec<DeviceInfo>> {
+            self.commands
+                .lock()
+                .unwrap()
+                .push((command.clone(), targets.to_vec()));
+            Ok(Vec::new())
+        }
+
+        fn publish_status(&self, _message: String) {}
+    }
+
+    async fn wait_until(condition: impl Fn() -> bool) {
+        timeout(Duration::from_secs(5), async {
+            while !condition() {
+                sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("Condition was never met");
+    }
+
+    #[tokio::test]
+    async fn powers_off_before_suspending_and_restores_on_resume() {
+        let Some(bus) = PrivateBus::start() else {
+            eprintln!("Skipping, dbus-daemon is not installed");
+            return;
+        };
+        let logind = StandInLogind::default();
+        let server = bus
+            .bus()
+            .builder()
+            .unwrap()
+            .name("org.freedesktop.login1")
+            .unwrap()
+            .serve_at(LOGIND_PATH, logind.clone())
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+        let emitter = SignalEmitter::new(&server, LOGIND_PATH).unwrap();
+
+        let lighthouses = Arc::new(FakeLighthouses {
+            infos: vec![
+                device_info("AA:AA:AA:AA:AA:AA", "LHB-A", &DeviceRemoteStatus::Active),
+                device_info("BB:BB:BB:BB:BB:BB", "LHB-B", &DeviceRemoteStatus::Standby),
+                device_info("CC:CC:CC:CC:CC:CC", "LHB-C", &DeviceRemoteStatus::Stopped),
+            ],
+            ..FakeLighthouses::default()
+        });
+        let config = SuspendConfig {
+            restore: true,
+            ..SuspendConfig::default()
+        };
+        let client = bus.connect().await;
+        let following = lighthouses.clone();
+        tokio::spawn(async move {
+            let logind = LogindProxy::new(&client).await.unwrap();
+            follow_sleep(following.as_ref(), &logind, &config).await
+        });
+        // Signals are subscribed to before the first inhibitor is taken
+        wait_until(|| logind.inhibitor_count() == 1).await;
+
+        StandInLogind::prepare_for_sleep(&emitter, true)
+            .await
+            .unwrap();
+        timeout(Duration::from_secs(5), logind.released())
+            .await
+            .expect("Inhibitor was never released");
+        assert_eq!(
+            lighthouses.commands(),
+            [(DeviceCommand::Sleep, vec![DeviceSelector::all()])]
+        );
+
+        StandInLogind::prepare_for_sleep(&emitter, false)
+            .await
+            .unwrap();
+        wait_until(|| lighthouses.commands().len() == 3).await;
+        assert_eq!(
+            lighthouses.commands()[1..],
+            [
+                (
+                    DeviceCommand::Activate,
+                    vec![DeviceSelector::new("AA:AA:AA:AA:AA:AA")]
+                ),
+                (
+                    DeviceCommand::Standby,
+                    vec![DeviceSelector::new("BB:BB:BB:BB:BB:BB")]
+                ),
+            ]
+        );
+        // A new inhibitor covers the next suspend
+        assert_eq!(logind.inhibitor_count(), 1);
+    }
+}
@@ -0,0 +1,442 @@
+mod logind;
+mod notify;
+
+use std::{convert::Infallible, str::FromStr};
+
+use tokio::sync::broadcast::error::RecvError;
+use vrlh_power_manager_core::{
+    DeviceCommand, DeviceInfo, DeviceListEvent, DeviceLocalStatus, DeviceSelector,
+};
+use zbus::{connection, fdo, interface, object_server::SignalEmitter, Connection};
+
+use crate::{address_slug, Daemon};
+
+pub use logind::*;
+pub use notify::*;
+
+pub const BUS_NAME: &str = "dev.reyma.VrlhPowerManager";
+pub const MANAGER_PATH: &str = "/dev/reyma/VrlhPowerManager";
+
+/// `(name, address, local, remote)` with empty strings for unknown statuses
+pub type DeviceTuple = (String, String, String, String);
+
+/// Which bus to claim the service name on
+#[derive(Clone, Debug, Default)]
+pub enum Bus {
+    #[default]
+    Session,
+    System,
+    /// A private bus, such as one started with `dbus-daemon --print-address`
+    Address(String),
+}
+
+impl Bus {
+    pub(crate) fn builder(&self) -> crate::Result<connection::Builder<'static>> {
+        match self {
+            Self::Session => connection::Builder::session(),
+            Self::System => connection::Builder::system(),
+            Self::Address(address) => connection::Builder::address(address.as_str()),
+        }
+        .map_err(|err| to_daemon_error(&err))
+    }
+}
+
+impl FromStr for Bus {
+    type Err = Infallible;
+
+    /// `session` and `system` select the well known buses, anything else is an address
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "session" => Self::Session,
+            "system" => Self::System,
+            address => Self::Address(address.into()),
+        })
+    }
+}
+
+/// Serves the device list on the bus until the daemon shuts down
+pub async fn run(daemon: Daemon, bus: Bus) -> crate::Result<()> {
+    let connection = bus
+        .builder()?
+        .name(BUS_NAME)
+        .map_err(|err| to_daemon_error(&err))?
+        .serve_at(
+            MANAGER_PATH,
+            Manager {
+                daemon: daemon.clone(),
+            },
+        )
+        .map_err(|err| to_daemon_error(&err))?
+        .build()
+        .await
+        .map_err(|err| to_daemon_error(&err))?;
+
+    let mut events = daemon.devices().subscribe();
+    for info in daemon.list() {
+        update_device(&connection, &daemon, &info).await;
+    }
+    loop {
+        match events.recv().await {
+            Ok(DeviceListEvent::Device(info)) => {
+                if matches!(info.local, Some(DeviceLocalStatus::Ignored)) {
+                    continue;
+                }
+                update_device(&connection, &daemon, &info).await;
+                if let Ok(emitter) = SignalEmitter::new(&connection, MANAGER_PATH) {
+                    let (name, addr, local, remote) = to_tuple(&info);
+                    let _ = Manager::device_changed(&emitter, &name, &addr, &local, &remote).await;
+                }
+            }
+            Ok(_) | Err(RecvError::Lagged(_)) => {}
+            Err(RecvError::Closed) => return Ok(()),
+        }
+    }
+}
+
+struct Manager {
+    daemon: Daemon,
+}
+
+#[interface(name = "dev.reyma.VrlhPowerManager1")]
+impl Manager {
+    /// Scans for the given seconds, 0 for the configured duration
+    async fn scan(&self, duration: u64) -> fdo::Result<Vec<DeviceTuple>> {
+        let duration = (duration > 0).then_some(duration);
+        Ok(to_tuples(&self.daemon.scan(duration).await?))
+    }
+
+    /// Lighthouses matching any of the targets, an empty list selects all of them
+    fn status(&self, targets: Vec<String>) -> fdo::Result<Vec<DeviceTuple>> {
+        Ok(to_tuples(&self.daemon.status(&selectors(targets))?))
+    }
+
+    async fn power(&self, command: &str, targets: Vec<String>) -> fdo::Result<Vec<DeviceTuple>> {
+        let command = command
+            .parse::<DeviceCommand>()
+            .map_err(|err| fdo::Error::InvalidArgs(err.to_string()))?;
+        let infos = self.daemon.power(&command, &selectors(targets)).await?;
+        Ok(to_tuples(&infos))
+    }
+
+    async fn identify(&self, targets: Vec<String>) -> fdo::Result<Vec<DeviceTuple>> {
+        Ok(to_tuples(&self.daemon.identify(&selectors(targets)).await?))
+    }
+
+    /// Fires for every update, with empty strings for statuses the update does not carry
+    #[zbus(signal)]
+    async fn device_changed(
+        emitter: &SignalEmitter<'_>,
+        name: &str,
+        addr: &str,
+        local: &str,
+        remote: &str,
+    ) -> zbus::Result<()>;
+}
+
+/// Exported at `MANAGER_PATH/devices/<address slug>` once a lighthouse is first seen
+struct DeviceObject {
+    daemon: Daemon,
+    name: String,
+    addr: String,
+    local: String,
+    remote: String,
+}
+
+#[interface(name = "dev.reyma.VrlhPowerManager1.Device")]
+impl DeviceObject {
+    #[zbus(property)]
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[zbus(property)]
+    fn address(&self) -> &str {
+        &self.addr
+    }
+
+    #[zbus(property)]
+    fn local(&self) -> &str {
+        &self.local
+    }
+
+    #[zbus(property)]
+    fn remote(&self) -> &str {
+        &self.remote
+    }
+
+    async fn power(&self, command: &str) -> fdo::Result<()> {
+        let command = command
+            .parse::<DeviceCommand>()
+            .map_err(|err| fdo::Error::InvalidArgs(err.to_string()))?;
+        self.daemon
+            .power(&command, &[DeviceSelector::new(self.addr.as_str())])
+            .await?;
+        Ok(())
+    }
+
+    async fn identify(&self) -> fdo::Result<()> {
+        self.daemon
+            .identify(&[DeviceSelector::new(self.addr.as_str())])
+            .await?;
+        Ok(())
+    }
+}
+
+async fn update_device(connection: &Connection, daemon: &Daemon, info: &DeviceInfo) {
+    let path = device_path(&info.addr);
+    let server = connection.object_server();
+    let Ok(iface) = server.interface::<_, DeviceObject>(path.as_str()).await else {
+        let (name, addr, local, remote) = to_tuple(info);
+        let object = DeviceObject {
+            daemon: daemon.clone(),
+            name,
+            addr,
+            local,
+            remote,
+        };
+        let _ = server.at(path.as_str(), object).await;
+        return;
+    };
+
+    let mut object = iface.get_mut().await;
+    let emitter = iface.signal_emitter();
+    if let Some(local) = &info.local {
+        object.local = local.to_string();
+        let _ = object.local_changed(emitter).await;
+    }
+    if let Some(remote) = &info.remote {
+        object.remote = remote.to_string();
+        let _ = object.remote_changed(emitter).await;
+    }
+}
+
+fn device_path(addr: &str) -> String {
+    format!("{MANAGER_PATH}/devices/{}", address_slug(addr))
+}
+
+fn selectors(targets: Vec<String>) -> Vec<DeviceSelector> {
+    match targets.is_empty() {
+        true => vec![DeviceSelector::all()],
+        false => targets.into_iter().map(DeviceSelector::new).collect(),
+    }
+}
+
+fn to_tuple(info: &DeviceInfo) -> DeviceTuple {
+    (
+        info.name.clone(),
+        info.addr.clone(),
+        info.local
+            .as_ref()
+            .map(ToString::to_string)
+            .unwrap_or_default(),
+        info.remote
+            .as_ref()
+            .map(ToString::to_string)
+            .unwrap_or_default(),
+    )
+}
+
+fn to_tuples(infos: &[DeviceInfo]) -> Vec<DeviceTuple> {
+    infos.iter().map(to_tuple).collect()
+}
+
+pub(crate) fn to_daemon_error(err: &zbus::Error) -> crate::Error {
+    crate::Error::VrlhDaemon(format!("D-Bus error: {err}"))
+}
+
+impl From<crate::Error> for fdo::Error {
+    fn from(err: crate::Error) -> Self {
+        match err {
+            crate::Error::NoDevices | crate::Error::InvalidParams(_) => {
+                Self::InvalidArgs(err.to_string())
+            }
+            err => Self::Failed(err.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader},
+        process::{Child, Command, Stdio},
+        time::Duration,
+    };
+
+    use serde_json::json;
+    use tokio::time::{sleep, timeout};
+    use vrlh_power_manager_core::{Aliases, DeviceList, DeviceRemoteStatus};
+    use zbus::{fdo::IntrospectableProxy, proxy::CacheProperties, Proxy};
+
+    use super::*;
+
+    /// A `dbus-daemon` of its own, killed once dropped
+    pub(super) struct PrivateBus {
+        child: Child,
+        address: String,
+    }
+
+    impl PrivateBus {
+        /// Nothing without `dbus-daemon` installed
+        pub(super) fn start() -> Option<Self> {
+            let mut child = Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address"])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .ok()?;
+            let mut address = String::new();
+            BufReader::new(child.stdout.take()?)
+                .read_line(&mut address)
+                .ok()?;
+            let address = address.trim().to_string();
+            Some(Self { child, address })
+        }
+
+        pub(super) fn bus(&self) -> Bus {
+            Bus::Address(self.address.clone())
+        }
+
+        pub(super) async fn connect(&self) -> Connection {
+            self.bus().builder().unwrap().build().await.unwrap()
+        }
+    }
+
+    impl Drop for PrivateBus {
+        fn drop(&mut self) {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+
+    pub(super) fn device_info(addr: &str, name: &str, remote: &DeviceRemoteStatus) -> DeviceInfo {
+        let object_path = format!("/org/bluez/hci0/dev_{}", addr.replace(':', "_"));
+        serde_json::from_value(json!({
+            "id": { "object_path": object_path },
+            "addr": addr,
+            "name": name,
+            "local": null,
+            "remote": remote,
+            "warnings": [],
+        }))
+        .unwrap()
+    }
+
+    async fn proxy<'a>(connection: &Connection, path: &'a str, interface: &'a str) -> Proxy<'a> {
+        zbus::proxy::Builder::new(connection)
+            .destination(BUS_NAME)
+            .unwrap()
+            .path(path)
+            .unwrap()
+            .interface(interface)
+            .unwrap()
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await
+            .unwrap()
+    }
+
+    async fn wait_for_property(device: &Proxy<'_>, property: &str, expected: &str) {
+        timeout(Duration::from_secs(5), async {
+            while device
+                .get_property::<String>(property)
+                .await
+                .ok()
+                .as_deref()
+                != Some(expected)
+            {
+                sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .unwrap_or_else(|_| panic!("{property} never became {expected:?}"));
+    }
+
+    fn method_error(err: zbus::Error) -> (String, String) {
+        match err {
+            zbus::Error::MethodError(name, message, _) => {
+                (name.to_string(), message.unwrap_or_default())
+            }
+            err => panic!("Expected a method error, got {err}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn serves_devices_on_a_private_bus() {
+        let Some(bus) = PrivateBus::start() else {
+            eprintln!("Skipping, dbus-daemon is not installed");
+            return;
+        };
+        let daemon = Daemon::new(DeviceList::detached(), Aliases::default());
+        tokio::spawn(run(daemon.clone(), bus.bus()));
+        let client = bus.connect().await;
+
+        let info = device_info(
+            "AA:BB:CC:DD:EE:FF",
+            "LHB-1234ABCD",
+            &DeviceRemoteStatus::Standby,
+        );
+        let path = device_path(&info.addr);
+        let device = proxy(&client, &path, "dev.reyma.VrlhPowerManager1.Device").await;
+        // The service only follows the list once it owns its name
+        timeout(Duration::from_secs(5), async {
+            while device.get_property::<String>("Name").await.is_err() {
+                daemon
+                    .devices()
+                    .publish(DeviceListEvent::Device(info.clone()));
+                sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("Device was never exported");
+
+        let devices = format!("{MANAGER_PATH}/devices");
+        let xml = IntrospectableProxy::builder(&client)
+            .destination(BUS_NAME)
+            .unwrap()
+            .path(devices.as_str())
+            .unwrap()
+            .build()
+            .await
+            .unwrap()
+            .introspect()
+            .await
+            .unwrap();
+        assert!(xml.contains(r#"<node name="aabbccddeeff">"#), "{xml}");
+
+        wait_for_property(&device, "Name", "LHB-1234ABCD").await;
+        wait_for_property(&device, "Address", "AA:BB:CC:DD:EE:FF").await;
+        wait_for_property(&device, "Local", "").await;
+        wait_for_property(&device, "Remote", "STANDBY").await;
+
+        let update = device_info(&info.addr, &info.name, &DeviceRemoteStatus::Active);
+        daemon.devices().publish(DeviceListEvent::Device(update));
+        wait_for_property(&device, "Remote", "ACTIVE").await;
+
+        // The list never found the lighthouse, so the selection comes back empty
+        let err = device.call_method("Power", &("Sleep",)).await.unwrap_err();
+        assert_eq!(
+            method_error(err),
+            (
+                "org.freedesktop.DBus.Error.InvalidArgs".into(),
+                crate::Error::NoDevices.to_string()
+            )
+        );
+
+        let manager = proxy(&client, MANAGER_PATH, "dev.reyma.VrlhPowerManager1").await;
+        let targets = vec!["LHB-1234ABCD"];
+        let err = manager
+            .call_method("Power", &("Explode", targets))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            method_error(err).0,
+            "org.freedesktop.DBus.Error.InvalidArgs"
+        );
+        let err = manager
+            .call_method("Status", &(Vec::<String>::new(),))
+            .await
+            .unwrap_err();
+        assert_eq!(method_error(err).1, crate::Error::NoDevices.to_string());
+    }
+}
@@ -0,0 +1,87 @@
+use std::{collections::HashMap, time::Duration};
+
+use futures::StreamExt;
+use tokio::time::sleep;
+use zbus::{proxy, zvariant::Value, Connection};
+
+const CANCEL_ACTION: &str = "cancel";
+
+#[proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+
+    fn close_notification(&self, id: u32) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn action_invoked(&self, id: u32, action_key: String) -> zbus::Result<()>;
+}
+
+/// Shows a desktop notification with a cancel button for the given time,
+/// returning whether it was pressed
+///
+/// Without a notification server this only waits
+pub async fn notify_cancellable(summary: &str, body: &str, duration: Duration) -> bool {
+    let Ok((notifications, id)) = show(summary, body, duration).await else {
+        sleep(duration).await;
+        return false;
+    };
+    let Ok(mut actions) = notifications.receive_action_invoked().await else {
+        sleep(duration).await;
+        return false;
+    };
+
+    let pressed = async {
+        while let Some(signal) = actions.next().await {
+            if let Ok(args) = signal.args() {
+                if args.id == id && args.action_key == CANCEL_ACTION {
+                    return true;
+                }
+            }
+        }
+        false
+    };
+    let cancelled = tokio::select! {
+        pressed = pressed => pressed,
+        () = sleep(duration) => false,
+    };
+    let _ = notifications.close_notification(id).await;
+    cancelled
+}
+
+async fn show(
+    summary: &str,
+    body: &str,
+    duration: Duration,
+) -> zbus::Result<(NotificationsProxy<'static>, u32)> {
+    let connection = Connection::session().await?;
+    let notifications = NotificationsProxy::new(&connection).await?;
+    let timeout = i32::try_from(duration.as_millis()).unwrap_or(i32::MAX);
+    let id = notifications
+        .notify(
+            "VRLH Power Manager",
+            0,
+            "",
+            summary,
+            body,
+            &[CANCEL_ACTION, "Cancel"],
+            HashMap::new(),
+            timeout,
+        )
+        .await?;
+    Ok((notifications, id))
+}
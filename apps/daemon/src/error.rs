@@ -0,0 +1,29 @@
+use thiserror::Error;
+use vrlh_power_manager_core::{ERROR_INTERNAL, ERROR_INVALID_PARAMS, ERROR_NO_DEVICES};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("{}", .0)]
+    VrlhDaemon(String),
+    #[error("{}", .0)]
+    VrlhCore(#[from] vrlh_power_manager_core::Error),
+    #[error("{}", .0)]
+    Io(#[from] std::io::Error),
+    #[error("Invalid parameters: {}", .0)]
+    InvalidParams(#[from] serde_json::Error),
+    #[error("No lighthouses matched the given selection!")]
+    NoDevices,
+}
+
+impl Error {
+    /// JSON-RPC error code reported to clients
+    pub fn code(&self) -> i64 {
+        match self {
+            Self::InvalidParams(_) => ERROR_INVALID_PARAMS,
+            Self::NoDevices => ERROR_NO_DEVICES,
+            Self::VrlhDaemon(_) | Self::VrlhCore(_) | Self::Io(_) => ERROR_INTERNAL,
+        }
+    }
+}
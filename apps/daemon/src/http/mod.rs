@@ -0,0 +1,225 @@
+mod routes;
+mod ws;
+
+use std::{borrow::Cow, net::SocketAddr, sync::Arc};
+
+use axum::{
+    extract::{Request, State},
+    http::{header::AUTHORIZATION, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use serde_json::json;
+use subtle::ConstantTimeEq;
+use tokio::net::TcpListener;
+use utoipa::OpenApi;
+
+use crate::Daemon;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "VRLH Power Manager",
+        description = "Control virtual reality lighthouses"
+    ),
+    paths(
+        routes::list_devices,
+        routes::get_device,
+        routes::power_device,
+        routes::identify_device,
+        routes::power_devices,
+        routes::scan,
+    ),
+    components(schemas(routes::CommandBody))
+)]
+pub struct ApiDoc;
+
+#[derive(Clone)]
+struct HttpState {
+    daemon: Daemon,
+    token: Option<Arc<str>>,
+}
+
+/// Routes backed by the daemon, requiring `Authorization: Bearer <token>` if a token is given
+pub fn router(daemon: Daemon, token: Option<String>) -> Router {
+    let state = HttpState {
+        daemon,
+        token: token.map(Into::into),
+    };
+    Router::new()
+        .route("/devices", get(routes::list_devices))
+        .route("/devices/{selector}", get(routes::get_device))
+        .route("/devices/{selector}/power", post(routes::power_device))
+        .route(
+            "/devices/{selector}/identify",
+            post(routes::identify_device),
+        )
+        .route("/power", post(routes::power_devices))
+        .route("/scan", post(routes::scan))
+        .route("/events", get(ws::events))
+        .route_layer(middleware::from_fn_with_state(state.clone(), authorize))
+        // The document stays public so tools can discover the API before authenticating
+        .route("/openapi.json", get(|| async { Json(ApiDoc::openapi()) }))
+        .with_state(state.daemon)
+}
+
+pub async fn serve(daemon: Daemon, addr: SocketAddr, token: Option<String>) -> crate::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    axum::serve(listener, router(daemon, token)).await?;
+    Ok(())
+}
+
+async fn authorize(State(state): State<HttpState>, request: Request, next: Next) -> Response {
+    let Some(token) = &state.token else {
+        return next.run(request).await;
+    };
+    let header = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    // Browsers cannot set headers on WebSocket connections so a query parameter is accepted too
+    let query = request.uri().query().and_then(|query| {
+        form_urlencoded::parse(query.as_bytes())
+            .find_map(|(key, value)| (key == "token").then_some(value))
+    });
+    // Comparing in constant time keeps response timings from revealing how much of a guess matched
+    let authorized = header
+        .map(Cow::Borrowed)
+        .or(query)
+        .is_some_and(|value| value.as_bytes().ct_eq(token.as_bytes()).into());
+    match authorized {
+        true => next.run(request).await,
+        false => (
+            StatusCode::UNAUTHORIZED,
+            error_body("Missing or invalid token!"),
+        )
+            .into_response(),
+    }
+}
+
+fn error_body(message: impl Into<String>) -> Json<serde_json::Value> {
+    Json(json!({ "error": message.into() }))
+}
+
+impl IntoResponse for crate::Error {
+    fn into_response(self) -> Response {
+        let status = match self {
+            Self::NoDevices => StatusCode::NOT_FOUND,
+            Self::InvalidParams(_) => StatusCode::BAD_REQUEST,
+            Self::VrlhDaemon(_) | Self::VrlhCore(_) | Self::Io(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        };
+        (status, error_body(self.to_string())).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use vrlh_power_manager_core::{Aliases, DeviceList};
+
+    use super::*;
+
+    const UPGRADE: &[&str] = &[
+        "Connection: Upgrade",
+        "Upgrade: websocket",
+        "Sec-WebSocket-Version: 13",
+        "Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==",
+    ];
+
+    async fn serve_detached(token: Option<&str>) -> SocketAddr {
+        let daemon = Daemon::new(DeviceList::detached(), Aliases::default());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = router(daemon, token.map(Into::into));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        addr
+    }
+
+    /// Status line of a `GET` with the given extra header lines
+    async fn status(addr: SocketAddr, path: &str, headers: &[&str]) -> String {
+        let mut stream = BufReader::new(tokio::net::TcpStream::connect(addr).await.unwrap());
+        let request = [format!("GET {path} HTTP/1.1"), format!("Host: {addr}")]
+            .into_iter()
+            .chain(headers.iter().map(ToString::to_string))
+            .map(|line| line + "\r\n")
+            .collect::<String>()
+            + "\r\n";
+        stream
+            .get_mut()
+            .write_all(request.as_bytes())
+            .await
+            .unwrap();
+        let mut line = String::new();
+        stream.read_line(&mut line).await.unwrap();
+        line.trim_end().to_string()
+    }
+
+    #[tokio::test]
+    async fn requires_the_token() {
+        let addr = serve_detached(Some("s3cret")).await;
+
+        let ok = "HTTP/1.1 200 OK";
+        let unauthorized = "HTTP/1.1 401 Unauthorized";
+        assert_eq!(
+            status(addr, "/devices", &["Authorization: Bearer s3cret"]).await,
+            ok
+        );
+        assert_eq!(status(addr, "/devices?token=s3cret", &[]).await, ok);
+        assert_eq!(status(addr, "/devices", &[]).await, unauthorized);
+        assert_eq!(
+            status(addr, "/devices", &["Authorization: Bearer s3crex"]).await,
+            unauthorized
+        );
+        assert_eq!(
+            status(addr, "/devices", &["Authorization: Bearer s3cret2"]).await,
+            unauthorized
+        );
+        assert_eq!(
+            status(addr, "/devices", &["Authorization: Basic s3cret"]).await,
+            unauthorized
+        );
+        assert_eq!(status(addr, "/devices?token=", &[]).await, unauthorized);
+    }
+
+    #[tokio::test]
+    async fn decodes_the_query_token() {
+        let addr = serve_detached(Some("a+b/c=d&e")).await;
+        assert_eq!(
+            status(addr, "/devices?token=a%2Bb%2Fc%3Dd%26e", &[]).await,
+            "HTTP/1.1 200 OK"
+        );
+        assert_eq!(
+            status(addr, "/devices?token=a+b/c=d&e", &[]).await,
+            "HTTP/1.1 401 Unauthorized"
+        );
+    }
+
+    #[tokio::test]
+    async fn refuses_cross_origin_websockets() {
+        let addr = serve_detached(None).await;
+        let switching = "HTTP/1.1 101 Switching Protocols";
+        assert_eq!(status(addr, "/events", UPGRADE).await, switching);
+
+        let same = format!("Origin: http://{addr}");
+        let headers = [UPGRADE, &[same.as_str()]].concat();
+        assert_eq!(status(addr, "/events", &headers).await, switching);
+
+        for origin in [
+            "Origin: https://example.com",
+            "Origin: null",
+            "Origin: http://127.0.0.1",
+        ] {
+            let headers = [UPGRADE, &[origin]].concat();
+            assert_eq!(
+                status(addr, "/events", &headers).await,
+                "HTTP/1.1 403 Forbidden",
+                "{origin}"
+            );
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use vrlh_power_manager_core::{DeviceCommand, DeviceInfo, DeviceSelector, PowerParams};
+
+use crate::Daemon;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CommandBody {
+    pub command: DeviceCommand,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ScanQuery {
+    /// Seconds to scan for lighthouses, the configured duration when missing
+    #[serde(default)]
+    pub duration: Option<u64>,
+}
+
+/// Every lighthouse found so far
+#[utoipa::path(
+    get,
+    path = "/devices",
+    responses((status = 200, body = Vec<DeviceInfo>))
+)]
+pub async fn list_devices(State(daemon): State<Daemon>) -> crate::Result<Json<Vec<DeviceInfo>>> {
+    Ok(Json(daemon.list()))
+}
+
+/// A single lighthouse by name, address or alias
+#[utoipa::path(
+    get,
+    path = "/devices/{selector}",
+    params(("selector" = String, Path, description = "Name, address or alias")),
+    responses(
+        (status = 200, body = DeviceInfo),
+        (status = 404, description = "No lighthouse matched"),
+    )
+)]
+pub async fn get_device(
+    State(daemon): State<Daemon>,
+    Path(selector): Path<DeviceSelector>,
+) -> crate::Result<Json<DeviceInfo>> {
+    daemon
+        .status(&[selector])?
+        .into_iter()
+        .next()
+        .map(Json)
+        .ok_or(crate::Error::NoDevices)
+}
+
+/// Sends a power command to a single lighthouse and waits for it to finish
+#[utoipa::path(
+    post,
+    path = "/devices/{selector}/power",
+    params(("selector" = String, Path, description = "Name, address or alias")),
+    request_body = CommandBody,
+    responses(
+        (status = 200, body = Vec<DeviceInfo>),
+        (status = 404, description = "No lighthouse matched"),
+    )
+)]
+pub async fn power_device(
+    State(daemon): State<Daemon>,
+    Path(selector): Path<DeviceSelector>,
+    Json(body): Json<CommandBody>,
+) -> crate::Result<Json<Vec<DeviceInfo>>> {
+    Ok(Json(daemon.power(&body.command, &[selector]).await?))
+}
+
+/// Blinks the status light of a single lighthouse
+#[utoipa::path(
+    post,
+    path = "/devices/{selector}/identify",
+    params(("selector" = String, Path, description = "Name, address or alias")),
+    responses(
+        (status = 200, body = Vec<DeviceInfo>),
+        (status = 404, description = "No lighthouse matched"),
+    )
+)]
+pub async fn identify_device(
+    State(daemon): State<Daemon>,
+    Path(selector): Path<DeviceSelector>,
+) -> crate::Result<Json<Vec<DeviceInfo>>> {
+    Ok(Json(daemon.identify(&[selector]).await?))
+}
+
+/// Sends a power command to every matching lighthouse at once, use `*` to target all
+#[utoipa::path(
+    post,
+    path = "/power",
+    request_body = PowerParams,
+    responses(
+        (status = 200, body = Vec<DeviceInfo>),
+        (status = 404, description = "No lighthouse matched"),
+    )
+)]
+pub async fn power_devices(
+    State(daemon): State<Daemon>,
+    Json(params): Json<PowerParams>,
+) -> crate::Result<Json<Vec<DeviceInfo>>> {
+    Ok(Json(daemon.power(&params.command, &params.targets).await?))
+}
+
+/// Scans for lighthouses and returns every one found so far
+#[utoipa::path(
+    post,
+    path = "/scan",
+    params(ScanQuery),
+    responses((status = 200, body = Vec<DeviceInfo>))
+)]
+pub async fn scan(
+    State(daemon): State<Daemon>,
+    Query(query): Query<ScanQuery>,
+) -> crate::Result<Json<Vec<DeviceInfo>>> {
+    Ok(Json(daemon.scan(query.duration).await?))
+}
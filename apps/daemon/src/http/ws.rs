@@ -0,0 +1,175 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket},
+        State, WebSocketUpgrade,
+    },
+    http::{
+        header::{HOST, ORIGIN},
+        HeaderMap, StatusCode,
+    },
+    response::{IntoResponse, Response},
+};
+use futures::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::{broadcast::error::RecvError, mpsc};
+use vrlh_power_manager_core::{DeviceCommand, DeviceInfo, DeviceListEvent, DeviceSelector};
+
+use crate::Daemon;
+
+/// Commands accepted from clients, the optional `id` is echoed back in the reply
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+enum ClientMessage {
+    Power {
+        id: Option<Value>,
+        command: DeviceCommand,
+        targets: Vec<DeviceSelector>,
+    },
+    Identify {
+        id: Option<Value>,
+        targets: Vec<DeviceSelector>,
+    },
+    Scan {
+        id: Option<Value>,
+        /// Seconds to scan, the configured duration when missing
+        #[serde(default)]
+        duration: Option<u64>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type")]
+enum Reply {
+    Result {
+        id: Option<Value>,
+        devices: Vec<DeviceInfo>,
+    },
+    Error {
+        id: Option<Value>,
+        message: String,
+    },
+}
+
+/// Streams every `DeviceListEvent` as JSON and accepts commands on the same connection
+///
+/// Browsers let any page connect to localhost, so upgrades from other origins are refused
+pub async fn events(
+    ws: WebSocketUpgrade,
+    headers: HeaderMap,
+    State(daemon): State<Daemon>,
+) -> Response {
+    if !same_origin(&headers) {
+        return (
+            StatusCode::FORBIDDEN,
+            super::error_body("Cross-origin WebSocket connections are not allowed!"),
+        )
+            .into_response();
+    }
+    ws.on_upgrade(move |socket| handle_socket(socket, daemon))
+}
+
+/// Clients other than browsers send no `Origin`, browsers must send the host they connect to
+fn same_origin(headers: &HeaderMap) -> bool {
+    let Some(origin) = headers.get(ORIGIN) else {
+        return true;
+    };
+    let authority = origin
+        .to_str()
+        .ok()
+        .and_then(|origin| origin.split_once("://"))
+        .map(|(_, authority)| authority);
+    let host = headers.get(HOST).and_then(|host| host.to_str().ok());
+    match (authority, host) {
+        (Some(authority), Some(host)) => authority.eq_ignore_ascii_case(host),
+        _ => false,
+    }
+}
+
+async fn handle_socket(socket: WebSocket, daemon: Daemon) {
+    let (mut sink, mut stream) = socket.split();
+    let (tx, mut rx) = mpsc::channel::<String>(32);
+
+    // Subscribe before sending the snapshot so no update falls in between
+    let mut events = daemon.devices().subscribe();
+    let snapshot = daemon.list().into_iter().map(DeviceListEvent::Device);
+    let writer = tokio::spawn(async move {
+        for event in snapshot {
+            if !send_json(&mut sink, &event).await {
+                return;
+            }
+        }
+        loop {
+            let sent = tokio::select! {
+                line = rx.recv() => match line {
+                    Some(line) => sink.send(Message::text(line)).await.is_ok(),
+                    None => false,
+                },
+                event = events.recv() => match event {
+                    Ok(event) => send_json(&mut sink, &event).await,
+                    // Slow clients miss events rather than holding up everyone else
+                    Err(RecvError::Lagged(_)) => true,
+                    Err(RecvError::Closed) => false,
+                },
+            };
+            if !sent {
+                break;
+            }
+        }
+    });
+
+    while let Some(Ok(message)) = stream.next().await {
+        let text = match message {
+            Message::Text(text) => text,
+            Message::Close(_) => break,
+            _ => continue,
+        };
+        let tx = tx.clone();
+        let daemon = daemon.clone();
+        tokio::spawn(async move {
+            let reply = match serde_json::from_str::<ClientMessage>(&text) {
+                Ok(message) => run(&daemon, message).await,
+                Err(err) => Reply::Error {
+                    id: None,
+                    message: err.to_string(),
+                },
+            };
+            if let Ok(line) = serde_json::to_string(&reply) {
+                let _ = tx.send(line).await;
+            }
+        });
+    }
+
+    drop(tx);
+    writer.abort();
+}
+
+async fn run(daemon: &Daemon, message: ClientMessage) -> Reply {
+    let (id, result) = match message {
+        ClientMessage::Power {
+            id,
+            command,
+            targets,
+        } => (id, daemon.power(&command, &targets).await),
+        ClientMessage::Identify { id, targets } => (id, daemon.identify(&targets).await),
+        ClientMessage::Scan { id, duration } => (id, daemon.scan(duration).await),
+    };
+    match result {
+        Ok(devices) => Reply::Result { id, devices },
+        Err(err) => Reply::Error {
+            id,
+            message: err.to_string(),
+        },
+    }
+}
+
+async fn send_json<S, T>(sink: &mut S, value: &T) -> bool
+where
+    S: SinkExt<Message> + Unpin,
+    T: Serialize,
+{
+    match serde_json::to_string(value) {
+        Ok(line) => sink.send(Message::text(line)).await.is_ok(),
+        Err(_) => true,
+    }
+}
@@ -0,0 +1,167 @@
+use std::time::Duration;
+
+use tokio::{
+    task::spawn_blocking,
+    time::{interval, sleep},
+};
+use vrlh_power_manager_core::{
+    command_idle_time, DeviceRemoteStatus, DeviceSelector, IdleConfig, IdleSource, OpenXrRuntime,
+    ProcessWatcher,
+};
+
+use crate::Daemon;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+enum IdleMonitor {
+    #[cfg(all(feature = "dbus", target_os = "linux"))]
+    Logind(crate::dbus::LogindSessionProxy<'static>),
+    Command {
+        command: std::path::PathBuf,
+        args: Vec<String>,
+    },
+}
+
+impl IdleMonitor {
+    #[cfg_attr(
+        not(all(feature = "dbus", target_os = "linux")),
+        allow(clippy::unused_async)
+    )]
+    async fn new(source: &IdleSource, logind_bus: &LogindBus) -> crate::Result<Self> {
+        match source {
+            #[cfg(all(feature = "dbus", target_os = "linux"))]
+            IdleSource::Logind => Ok(Self::Logind(crate::dbus::logind_session(logind_bus).await?)),
+            #[cfg(not(all(feature = "dbus", target_os = "linux")))]
+            IdleSource::Logind => {
+                let () = logind_bus;
+                Err(crate::Error::VrlhDaemon(
+                    "The logind idle source needs a daemon built with the dbus feature!".into(),
+                ))
+            }
+            IdleSource::Command { command, args } => Ok(Self::Command {
+                command: command.clone(),
+                args: args.clone(),
+            }),
+        }
+    }
+
+    async fn idle_time(&self) -> crate::Result<Duration> {
+        match self {
+            #[cfg(all(feature = "dbus", target_os = "linux"))]
+            Self::Logind(session) => crate::dbus::logind_idle_time(session).await,
+            Self::Command { command, args } => Ok(command_idle_time(command, args).await?),
+        }
+    }
+}
+
+#[cfg(all(feature = "dbus", target_os = "linux"))]
+pub type LogindBus = crate::dbus::Bus;
+/// Placeholder so callers need no feature checks, logind is only reachable over D-Bus
+#[cfg(not(all(feature = "dbus", target_os = "linux")))]
+pub type LogindBus = ();
+
+/// Powers down active lighthouses once the user has been idle long enough while no VR
+/// session is running, after a warning that can be cancelled
+pub async fn run_idle(
+    daemon: Daemon,
+    config: IdleConfig,
+    logind_bus: LogindBus,
+) -> crate::Result<()> {
+    let monitor = IdleMonitor::new(&config.source, &logind_bus).await?;
+    // Absurdly long limits never trigger rather than overflowing
+    let limit = Duration::from_secs(config.minutes.saturating_mul(60));
+    // Playing VR leaves the desktop idle, so runtimes count even without a presence watcher
+    let runtimes = ProcessWatcher::new(
+        OpenXrRuntime::ALL
+            .iter()
+            .flat_map(|runtime| runtime.processes())
+            .copied(),
+    );
+    let mut ticks = interval(POLL_INTERVAL);
+    // Only one automatic action per idle period, activity arms it again
+    let mut armed = true;
+    loop {
+        ticks.tick().await;
+        let idle = match monitor.idle_time().await {
+            Ok(idle) => idle,
+            Err(err) => {
+                eprintln!("Could not read idle time: {err}");
+                continue;
+            }
+        };
+        if idle < limit {
+            armed = true;
+            continue;
+        }
+        if !armed || daemon.session_active() || runtime_running(&runtimes).await {
+            continue;
+        }
+        let targets = active_targets(&daemon, &config);
+        if targets.is_empty() {
+            continue;
+        }
+        armed = false;
+
+        if !warn(&daemon, &config, targets.len()).await {
+            daemon.publish_status("Automatic power down cancelled");
+            continue;
+        }
+        if monitor.idle_time().await.is_ok_and(|idle| idle < limit) {
+            daemon.publish_status("Automatic power down cancelled, activity resumed");
+            continue;
+        }
+        if daemon.session_active() || runtime_running(&runtimes).await {
+            daemon.publish_status("Automatic power down cancelled, a VR session started");
+            continue;
+        }
+        daemon.publish_status(format!(
+            r#"Idle for {} minutes, sending "{}" to {} lighthouses"#,
+            config.minutes,
+            config.command,
+            targets.len()
+        ));
+        let _ = daemon.power(&config.command, &targets).await;
+    }
+}
+
+async fn runtime_running(runtimes: &ProcessWatcher) -> bool {
+    let runtimes = runtimes.clone();
+    spawn_blocking(move || runtimes.is_running())
+        .await
+        .unwrap_or_default()
+}
+
+fn active_targets(daemon: &Daemon, config: &IdleConfig) -> Vec<DeviceSelector> {
+    daemon
+        .status(&config.targets)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|info| matches!(info.remote, Some(DeviceRemoteStatus::Active)))
+        .map(|info| DeviceSelector::new(info.addr))
+        .collect()
+}
+
+/// Returns whether the command should still be sent
+async fn warn(daemon: &Daemon, config: &IdleConfig, count: usize) -> bool {
+    let duration = Duration::from_secs(config.warning_secs);
+    let message = format!(
+        r#"Sending "{}" to {count} idle lighthouses in {}s"#,
+        config.command, config.warning_secs
+    );
+    daemon.publish_status(message.clone());
+
+    #[cfg(all(feature = "dbus", target_os = "linux"))]
+    let notification = crate::dbus::notify_cancellable("Lighthouses idle", &message, duration);
+    #[cfg(not(all(feature = "dbus", target_os = "linux")))]
+    let notification = async {
+        sleep(duration).await;
+        false
+    };
+
+    tokio::select! {
+        cancelled = notification => !cancelled,
+        () = daemon.idle_cancelled() => false,
+        // Notification servers may drop the notification without reporting it
+        () = sleep(duration + Duration::from_secs(5)) => true,
+    }
+}
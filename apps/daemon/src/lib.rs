@@ -0,0 +1,329 @@
+#[cfg(all(feature = "dbus", target_os = "linux"))]
+pub mod dbus;
+mod error;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod idle;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+#[cfg(feature = "osc")]
+pub mod osc;
+pub mod rpc;
+
+use std::{
+    path::Path,
+    sync::{Arc, Mutex as StdMutex},
+    time::Duration,
+};
+
+use futures::future::join_all;
+use tokio::{
+    sync::{mpsc::Receiver, watch, Mutex, Notify},
+    time::timeout,
+};
+use vrlh_power_manager_core::{
+    config_path, Aliases, Config, ConfigWatcher, Device, DeviceCommand, DeviceInfo, DeviceList,
+    DeviceListEvent, DeviceSelector, HookRun, Hooks, LoadedConfig, PowerPolicy, RuleEngine,
+    RuleEvaluation, RuleInput, Scheduler,
+};
+
+pub use error::*;
+
+/// Owns the bluetooth adapter and device list, shared by every frontend the daemon serves
+///
+/// Can be cloned and will retain references to the same devices
+#[derive(Clone, Debug)]
+pub struct Daemon {
+    devices: DeviceList,
+    aliases: Arc<Aliases>,
+    scan_lock: Arc<Mutex<()>>,
+    sessions: Arc<StdMutex<Vec<watch::Receiver<bool>>>>,
+    idle_cancel: Arc<Notify>,
+    hooks: Option<Hooks>,
+    rules: Option<RuleEngine>,
+    #[cfg(feature = "webhooks")]
+    webhooks: Option<vrlh_power_manager_core::Webhooks>,
+    #[cfg(feature = "scripting")]
+    scripts: Option<vrlh_power_manager_core::Scripts>,
+}
+
+impl Daemon {
+    pub fn new(devices: DeviceList, aliases: Aliases) -> Self {
+        Self {
+            devices,
+            aliases: Arc::new(aliases),
+            scan_lock: Arc::new(Mutex::new(())),
+            sessions: Arc::default(),
+            idle_cancel: Arc::default(),
+            hooks: None,
+            rules: None,
+            #[cfg(feature = "webhooks")]
+            webhooks: None,
+            #[cfg(feature = "scripting")]
+            scripts: None,
+        }
+    }
+
+    pub fn config(&self) -> Config {
+        self.devices.config()
+    }
+
+    /// Applies the configuration and every valid change to it until the sender is dropped
+    pub fn follow_config(&self, mut rx: watch::Receiver<LoadedConfig>) {
+        self.devices
+            .set_config(rx.borrow_and_update().config.clone());
+        let daemon = self.clone();
+        tokio::spawn(async move {
+            while rx.changed().await.is_ok() {
+                let loaded = rx.borrow_and_update().clone();
+                let path = loaded.path.display();
+                match loaded.error {
+                    Some(err) => daemon.publish_status(format!(
+                        "Keeping the previous configuration, '{path}' is invalid: {err}"
+                    )),
+                    None => daemon.publish_status(format!("Reloaded configuration from '{path}'")),
+                }
+                daemon.devices.set_config(loaded.config);
+            }
+        });
+    }
+
+    /// Starts running hooks for events of this daemon's device list
+    #[must_use]
+    pub fn with_hooks(mut self, hooks: Hooks) -> Self {
+        let devices = self.devices.clone();
+        let runner = hooks.clone();
+        tokio::spawn(async move { runner.run(&devices).await });
+        self.hooks = Some(hooks);
+        self
+    }
+
+    pub fn hook_runs(&self) -> Vec<HookRun> {
+        self.hooks.as_ref().map(Hooks::runs).unwrap_or_default()
+    }
+
+    /// Starts evaluating rules against this daemon's device list
+    #[must_use]
+    pub fn with_rules(mut self, rules: RuleEngine) -> Self {
+        let devices = self.devices.clone();
+        let runner = rules.clone();
+        tokio::spawn(async move { runner.run(&devices).await });
+        self.rules = Some(rules);
+        self
+    }
+
+    pub fn rule_evaluations(&self) -> Vec<RuleEvaluation> {
+        self.rules
+            .as_ref()
+            .map(RuleEngine::evaluations)
+            .unwrap_or_default()
+    }
+
+    /// Explains what every rule would do for the input given the current state
+    pub fn rules_dry_run(&self, input: &RuleInput) -> crate::Result<Vec<RuleEvaluation>> {
+        let rules = self
+            .rules
+            .as_ref()
+            .ok_or_else(|| crate::Error::VrlhDaemon("No rules are loaded!".into()))?;
+        let snapshot = rules.snapshot(snapshot(&self.devices.get_devices()));
+        Ok(rules.dry_run(input, &snapshot))
+    }
+
+    /// Starts delivering webhooks for events of this daemon's device list
+    #[cfg(feature = "webhooks")]
+    #[must_use]
+    pub fn with_webhooks(mut self, webhooks: vrlh_power_manager_core::Webhooks) -> Self {
+        let devices = self.devices.clone();
+        let runner = webhooks.clone();
+        tokio::spawn(async move { runner.run(&devices).await });
+        self.webhooks = Some(webhooks);
+        self
+    }
+
+    #[cfg(feature = "webhooks")]
+    pub fn webhook_deliveries(&self) -> Vec<vrlh_power_manager_core::WebhookDelivery> {
+        self.webhooks
+            .as_ref()
+            .map(vrlh_power_manager_core::Webhooks::deliveries)
+            .unwrap_or_default()
+    }
+
+    /// Starts calling script handlers for events of this daemon's device list
+    #[cfg(feature = "scripting")]
+    #[must_use]
+    pub fn with_scripts(mut self, scripts: vrlh_power_manager_core::Scripts) -> Self {
+        let devices = self.devices.clone();
+        let runner = scripts.clone();
+        tokio::spawn(async move { runner.run(&devices).await });
+        self.scripts = Some(scripts);
+        self
+    }
+
+    #[cfg(feature = "scripting")]
+    pub fn script_runs(&self) -> Vec<vrlh_power_manager_core::ScriptRun> {
+        self.scripts
+            .as_ref()
+            .map(vrlh_power_manager_core::Scripts::runs)
+            .unwrap_or_default()
+    }
+
+    pub fn devices(&self) -> &DeviceList {
+        &self.devices
+    }
+
+    /// Scans for the given seconds or as long as configured, joining the running scan instead if there is one
+    pub async fn scan(&self, duration: Option<u64>) -> crate::Result<Vec<DeviceInfo>> {
+        if let Ok(_guard) = self.scan_lock.try_lock() {
+            let duration = duration.unwrap_or(self.devices.config().scan.duration_secs);
+            let rx = self.devices.start_scan(duration)?;
+            self.publish_status("Scanning for lighthouses...");
+            drain(rx).await;
+            self.publish_status("Done scanning for devices!");
+        } else {
+            let _guard = self.scan_lock.lock().await;
+        }
+        Ok(self.list())
+    }
+
+    /// Applies the policy whenever a session reported by the watcher starts or stops,
+    /// leaving lighthouses on while a session of another followed source still runs
+    pub fn follow_presence(
+        &self,
+        source: impl Into<String>,
+        policy: PowerPolicy,
+        presence: watch::Receiver<bool>,
+    ) {
+        if let Ok(mut sessions) = self.sessions.lock() {
+            sessions.push(presence.clone());
+        }
+        let daemon = self.clone();
+        tokio::spawn(vrlh_power_manager_core::run_power_policy(
+            self.devices.clone(),
+            (*self.aliases).clone(),
+            policy,
+            source.into(),
+            presence,
+            move || daemon.session_active(),
+        ));
+    }
+
+    /// Sends the commands of schedules and calendar sessions as they come due
+    pub fn follow_schedules(&self, scheduler: Scheduler) {
+        let (devices, aliases) = (self.devices.clone(), self.aliases.clone());
+        tokio::spawn(async move { scheduler.run(&devices, &aliases).await });
+    }
+
+    /// Whether any followed VR session is currently running
+    pub fn session_active(&self) -> bool {
+        self.sessions
+            .lock()
+            .is_ok_and(|sessions| sessions.iter().any(|presence| *presence.borrow()))
+    }
+
+    /// Cancels a pending automatic power down announced by the idle warning
+    pub fn cancel_idle(&self) {
+        self.idle_cancel.notify_waiters();
+    }
+
+    /// Resolves once `cancel_idle` is called
+    pub async fn idle_cancelled(&self) {
+        self.idle_cancel.notified().await;
+    }
+
+    pub fn list(&self) -> Vec<DeviceInfo> {
+        snapshot(&self.devices.get_devices())
+    }
+
+    pub fn status(&self, targets: &[DeviceSelector]) -> crate::Result<Vec<DeviceInfo>> {
+        Ok(snapshot(&self.select(targets)?))
+    }
+
+    /// Longest time a single power or identify request may take before its current state is returned
+    fn command_timeout(&self) -> Duration {
+        Duration::from_secs(self.config().timeouts.command_secs)
+    }
+
+    pub async fn power(
+        &self,
+        command: &DeviceCommand,
+        targets: &[DeviceSelector],
+    ) -> crate::Result<Vec<DeviceInfo>> {
+        let selected = self.select(targets)?;
+        let names = describe(&selected);
+        self.publish_status(format!(r#"Sending "{command}" command to {names}"#));
+        let rx = self.devices.start_power(selected.clone(), command);
+        match timeout(self.command_timeout(), drain(rx)).await {
+            Ok(()) => self.publish_status(format!(r#"Finished "{command}" for {names}"#)),
+            Err(_) => self.publish_status(format!(r#"Timed out "{command}" for {names}"#)),
+        }
+        Ok(snapshot(&selected))
+    }
+
+    pub async fn identify(&self, targets: &[DeviceSelector]) -> crate::Result<Vec<DeviceInfo>> {
+        let selected = self.select(targets)?;
+        self.publish_status(format!("Identifying {}", describe(&selected)));
+        let rx = self.devices.start_identify(selected.clone());
+        let _ = timeout(self.command_timeout(), drain(rx)).await;
+        Ok(snapshot(&selected))
+    }
+
+    pub async fn shutdown(&self) {
+        join_all(self.devices.get_devices().iter().map(Device::disconnect)).await;
+    }
+
+    pub fn publish_status(&self, message: impl Into<String>) {
+        self.devices
+            .publish(DeviceListEvent::Status(message.into()));
+    }
+
+    fn select(&self, targets: &[DeviceSelector]) -> crate::Result<Vec<Device>> {
+        let selected = self.devices.select(targets, &self.aliases);
+        match selected.is_empty() {
+            true => Err(crate::Error::NoDevices),
+            false => Ok(selected),
+        }
+    }
+}
+
+/// Updates are already broadcast to subscribers, callers only need to know when it is over
+async fn drain(mut rx: Receiver<DeviceInfo>) {
+    while rx.recv().await.is_some() {}
+}
+
+/// Watches the given or looked up configuration file, refusing to start with an invalid one
+pub fn watch_config(path: Option<&Path>) -> crate::Result<watch::Receiver<LoadedConfig>> {
+    let path = path.map_or_else(|| config_path(None), Path::to_path_buf);
+    let rx = ConfigWatcher::new(path).watch();
+    if let Some(err) = &rx.borrow().error {
+        return Err(crate::Error::VrlhDaemon(err.clone()));
+    }
+    Ok(rx)
+}
+
+/// Lowercase alphanumeric form of a bluetooth address, used to name lighthouses in MQTT
+/// topics and D-Bus paths since addresses are unique and stable, unlike names
+pub fn address_slug(addr: &str) -> String {
+    addr.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+fn snapshot(devices: &[Device]) -> Vec<DeviceInfo> {
+    let mut infos = devices
+        .iter()
+        .map(|device| {
+            let (local, remote) = device.get_last_statuses();
+            DeviceInfo::from_device_statuses(device, local, remote)
+        })
+        .collect::<Vec<_>>();
+    infos.sort_by(|a, b| a.name.cmp(&b.name));
+    infos
+}
+
+fn describe(devices: &[Device]) -> String {
+    match devices {
+        [device] => format!(r#""{}""#, device.name()),
+        _ => format!("{} lighthouses", devices.len()),
+    }
+}
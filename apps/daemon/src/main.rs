@@ -0,0 +1,471 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use clap::Parser;
+use vrlh_power_manager_core::{
+    default_socket_path, parse_alias, steamvr_watcher, Aliases, DeviceCommand, DeviceList,
+    DeviceSelector, HookConfig, Hooks, IdleConfig, IdleSource, LifecycleEventKind, OpenXrConfig,
+    OpenXrEnvironment, OpenXrRuntime, OpenXrWatcher, PowerPolicy, RuleEngine, Scheduler, UsbId,
+    UsbWatcher, KNOWN_HEADSETS,
+};
+use vrlh_power_manager_daemon::{idle, rpc, watch_config, Daemon};
+
+#[derive(Debug, Parser)]
+#[allow(clippy::struct_excessive_bools)]
+#[command(
+    name = "vrlhd",
+    version,
+    about = "Background service owning the lighthouse bluetooth adapter"
+)]
+struct Cli {
+    /// Unix socket to accept JSON-RPC clients on
+    #[arg(long, env = "VRLH_SOCKET")]
+    socket: Option<PathBuf>,
+    /// Configuration file, see the README for where it is looked up otherwise
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+    /// Seconds to scan for lighthouses on startup instead of the configured duration, 0 to skip
+    #[arg(short, long)]
+    duration: Option<u64>,
+    /// Alias for a lighthouse name or address, e.g. `left=LHB-1A2B3C4D`
+    #[arg(
+        long = "alias",
+        env = "VRLH_ALIASES",
+        value_delimiter = ',',
+        value_name = "ALIAS=TARGET",
+        value_parser = parse_alias
+    )]
+    aliases: Vec<(String, String)>,
+    /// Power lighthouses on while `vrserver` or `vrmonitor` are running
+    #[arg(long, env = "VRLH_STEAMVR")]
+    steamvr: bool,
+    /// Power lighthouses on while a session of the active OpenXR runtime is running
+    #[arg(long, env = "VRLH_OPENXR")]
+    openxr: bool,
+    /// OpenXR runtimes to follow when active
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "monado,wivrn,alvr",
+        requires = "openxr"
+    )]
+    openxr_runtimes: Vec<OpenXrRuntime>,
+    /// Power lighthouses on while a headset is plugged in
+    #[arg(long, env = "VRLH_USB")]
+    usb: bool,
+    /// Headset USB IDs as `vendor:product`, defaults to the Valve Index and HTC Vive models
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_name = "VENDOR:PRODUCT",
+        requires = "usb"
+    )]
+    usb_ids: Vec<UsbId>,
+    /// Lighthouses powered on and off with VR sessions
+    #[arg(long, value_delimiter = ',', default_value = "*")]
+    auto_targets: Vec<DeviceSelector>,
+    /// Command sent once a VR session is over, the configured off command by default
+    #[arg(long)]
+    auto_off_command: Option<DeviceCommand>,
+    /// Seconds to wait after a VR session ends before sending the off command
+    #[arg(long, default_value_t = 30)]
+    auto_grace: u64,
+    /// Executable to run on a lifecycle event, e.g. `AllActive=/usr/local/bin/start-steamvr`,
+    /// use `*` to run it on every event
+    #[arg(long = "hook", value_name = "EVENT=COMMAND", value_parser = parse_hook)]
+    hooks: Vec<(Option<LifecycleEventKind>, PathBuf)>,
+    /// Seconds after which a hook is killed
+    #[arg(long, default_value_t = 30)]
+    hook_timeout: u64,
+    /// TOML file of automation rules, see the README
+    #[arg(long, env = "VRLH_RULES", value_name = "PATH")]
+    rules: Option<PathBuf>,
+    /// JSON file of schedules, calendars and groups, the same format as the app's `schedules.json`
+    #[arg(long, env = "VRLH_SCHEDULES", value_name = "PATH")]
+    schedules: Option<PathBuf>,
+    /// Also serve the HTTP API on this address
+    #[cfg(feature = "http")]
+    #[arg(long, env = "VRLH_HTTP_LISTEN", value_name = "ADDR")]
+    http: Option<std::net::SocketAddr>,
+    /// Token HTTP clients must send as `Authorization: Bearer <token>`
+    #[cfg(feature = "http")]
+    #[arg(long, env = "VRLH_HTTP_TOKEN", requires = "http")]
+    http_token: Option<String>,
+    /// Bridge to the MQTT broker at this address, e.g. `localhost:1883`
+    #[cfg(feature = "mqtt")]
+    #[arg(long, env = "VRLH_MQTT", value_name = "HOST[:PORT]", value_parser = vrlh_power_manager_daemon::mqtt::parse_broker)]
+    mqtt: Option<(String, u16)>,
+    #[cfg(feature = "mqtt")]
+    #[arg(long, env = "VRLH_MQTT_USERNAME", requires = "mqtt")]
+    mqtt_username: Option<String>,
+    #[cfg(feature = "mqtt")]
+    #[arg(
+        long,
+        env = "VRLH_MQTT_PASSWORD",
+        requires = "mqtt",
+        hide_env_values = true
+    )]
+    mqtt_password: Option<String>,
+    /// Root of every state and command topic
+    #[cfg(feature = "mqtt")]
+    #[arg(long, env = "VRLH_MQTT_PREFIX", default_value = "vrlh")]
+    mqtt_prefix: String,
+    /// Root Home Assistant listens on for discovery payloads
+    #[cfg(feature = "mqtt")]
+    #[arg(
+        long,
+        env = "VRLH_MQTT_DISCOVERY_PREFIX",
+        default_value = "homeassistant"
+    )]
+    mqtt_discovery_prefix: String,
+    /// Command sent when a Home Assistant switch is turned off, the configured off command by default
+    #[cfg(feature = "mqtt")]
+    #[arg(long)]
+    mqtt_off_command: Option<DeviceCommand>,
+    /// Accept OSC commands on this UDP address, e.g. `127.0.0.1:9100`
+    #[cfg(feature = "osc")]
+    #[arg(long, env = "VRLH_OSC_LISTEN", value_name = "ADDR")]
+    osc: Option<std::net::SocketAddr>,
+    /// Send lighthouse state changes as OSC messages to this UDP address
+    #[cfg(feature = "osc")]
+    #[arg(long, env = "VRLH_OSC_TARGET", value_name = "ADDR", requires = "osc")]
+    osc_target: Option<std::net::SocketAddr>,
+    /// Root of every OSC address
+    #[cfg(feature = "osc")]
+    #[arg(long, env = "VRLH_OSC_PREFIX", default_value = "/vrlh")]
+    osc_prefix: String,
+    /// POST lifecycle events as JSON to this URL, may be repeated
+    #[cfg(feature = "webhooks")]
+    #[arg(
+        long = "webhook",
+        env = "VRLH_WEBHOOKS",
+        value_delimiter = ',',
+        value_name = "URL"
+    )]
+    webhooks: Vec<String>,
+    /// Only send these events to webhooks, e.g. `DeviceFailed,AllActive`
+    #[cfg(feature = "webhooks")]
+    #[arg(long, env = "VRLH_WEBHOOK_EVENTS", value_delimiter = ',')]
+    webhook_events: Vec<vrlh_power_manager_core::LifecycleEventKind>,
+    /// JSON body sent to webhooks with `{{placeholder}}`s instead of the event itself
+    #[cfg(feature = "webhooks")]
+    #[arg(long, env = "VRLH_WEBHOOK_TEMPLATE")]
+    webhook_template: Option<String>,
+    /// Directory of Rhai scripts, see the README
+    #[cfg(feature = "scripting")]
+    #[arg(long, env = "VRLH_SCRIPTS", value_name = "DIR")]
+    scripts: Option<PathBuf>,
+    /// Milliseconds a single script handler may run
+    #[cfg(feature = "scripting")]
+    #[arg(long, default_value_t = 250, requires = "scripts")]
+    script_timeout: u64,
+    /// Serve the D-Bus interface on `session`, `system` or a bus address
+    #[cfg(all(feature = "dbus", target_os = "linux"))]
+    #[arg(long, env = "VRLH_DBUS", value_name = "BUS")]
+    dbus: Option<vrlh_power_manager_daemon::dbus::Bus>,
+    /// Power lighthouses down when the system suspends
+    #[cfg(all(feature = "dbus", target_os = "linux"))]
+    #[arg(long, env = "VRLH_SUSPEND")]
+    suspend: bool,
+    /// Command sent before the system suspends, the configured off command by default
+    #[cfg(all(feature = "dbus", target_os = "linux"))]
+    #[arg(long, requires = "suspend")]
+    suspend_command: Option<DeviceCommand>,
+    /// Power lighthouses back into their previous state on resume
+    #[cfg(all(feature = "dbus", target_os = "linux"))]
+    #[arg(long, requires = "suspend")]
+    suspend_restore: bool,
+    /// Bus logind is reached on, `system` unless testing against a stand-in
+    #[cfg(all(feature = "dbus", target_os = "linux"))]
+    #[arg(long, default_value = "system", value_name = "BUS")]
+    logind_bus: vrlh_power_manager_daemon::dbus::Bus,
+    /// Power active lighthouses down after this many idle minutes without a VR session
+    #[arg(long, env = "VRLH_IDLE", value_name = "MINUTES")]
+    idle: Option<u64>,
+    /// Command sent once the computer is idle, the configured off command by default
+    #[arg(long, requires = "idle")]
+    idle_command: Option<DeviceCommand>,
+    /// Seconds the idle warning can be cancelled for
+    #[arg(long, default_value_t = 60, requires = "idle")]
+    idle_warning: u64,
+    /// Executable printing the idle time in milliseconds, instead of asking logind
+    #[arg(long, value_name = "PATH", requires = "idle")]
+    idle_source_command: Option<PathBuf>,
+}
+
+fn parse_hook(value: &str) -> Result<(Option<LifecycleEventKind>, PathBuf), String> {
+    let (event, command) = value
+        .split_once('=')
+        .ok_or("Hooks must be in the form EVENT=COMMAND")?;
+    let event = match event {
+        "*" => None,
+        event => Some(event.parse().map_err(|err| format!("{err}"))?),
+    };
+    Ok((event, command.into()))
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> vrlh_power_manager_daemon::Result<()> {
+    let socket = cli.socket.clone().unwrap_or_else(default_socket_path);
+    let aliases = cli.aliases.iter().cloned().collect::<Aliases>();
+    let daemon = Daemon::new(DeviceList::init().await?, aliases);
+    daemon.follow_config(watch_config(cli.config.as_deref())?);
+    let daemon = automate(daemon, &cli)?;
+
+    if cli.duration != Some(0) {
+        let (daemon, duration) = (daemon.clone(), cli.duration);
+        tokio::spawn(async move { daemon.scan(duration).await });
+    }
+    serve_frontends(&daemon, cli);
+
+    eprintln!("Listening on '{}'", socket.display());
+    let result = tokio::select! {
+        result = rpc::serve(daemon.clone(), &socket) => result,
+        _ = tokio::signal::ctrl_c() => Ok(()),
+    };
+
+    daemon.shutdown().await;
+    let _ = std::fs::remove_file(&socket);
+    result
+}
+
+/// Attaches everything reacting to lifecycle events, before the first scan so none are missed
+fn automate(mut daemon: Daemon, cli: &Cli) -> vrlh_power_manager_daemon::Result<Daemon> {
+    if !cli.hooks.is_empty() {
+        let hooks = cli
+            .hooks
+            .iter()
+            .map(|(event, command)| HookConfig {
+                events: event.iter().copied().collect(),
+                timeout_secs: cli.hook_timeout,
+                ..HookConfig::new(command)
+            })
+            .collect();
+        daemon = daemon.with_hooks(Hooks::new(hooks));
+    }
+
+    #[cfg(feature = "webhooks")]
+    if !cli.webhooks.is_empty() {
+        use vrlh_power_manager_core::{WebhookConfig, Webhooks};
+
+        let hooks = cli
+            .webhooks
+            .iter()
+            .map(|url| WebhookConfig {
+                events: cli.webhook_events.clone(),
+                template: cli.webhook_template.clone(),
+                ..WebhookConfig::new(url)
+            })
+            .collect();
+        daemon = daemon.with_webhooks(Webhooks::new(hooks)?);
+    }
+
+    if let Some(path) = &cli.rules {
+        let aliases = cli.aliases.iter().cloned().collect::<Aliases>();
+        daemon = daemon.with_rules(RuleEngine::load(path, aliases)?);
+    }
+
+    if let Some(path) = &cli.schedules {
+        daemon.follow_schedules(Scheduler::load(path)?);
+    }
+
+    #[cfg(feature = "scripting")]
+    if let Some(dir) = &cli.scripts {
+        daemon = daemon.with_scripts(load_scripts(dir, cli)?);
+    }
+
+    if cli.steamvr {
+        daemon.follow_presence(
+            "SteamVR",
+            power_policy(&daemon, cli),
+            steamvr_watcher().watch(),
+        );
+    }
+
+    if cli.openxr {
+        let config = OpenXrConfig {
+            runtimes: cli.openxr_runtimes.clone(),
+        };
+        let watcher = OpenXrWatcher::new(OpenXrEnvironment::from_env(), config);
+        daemon.follow_presence(
+            "OpenXR session",
+            power_policy(&daemon, cli),
+            watcher.watch(),
+        );
+    }
+
+    if cli.usb {
+        let ids = match cli.usb_ids.is_empty() {
+            true => KNOWN_HEADSETS.iter().map(|(id, _)| *id).collect(),
+            false => cli.usb_ids.clone(),
+        };
+        let watcher = UsbWatcher::new(ids);
+        daemon.follow_presence("Headset", power_policy(&daemon, cli), watcher.watch());
+    }
+
+    #[cfg(all(feature = "dbus", target_os = "linux"))]
+    if cli.suspend {
+        use vrlh_power_manager_core::SuspendConfig;
+        use vrlh_power_manager_daemon::dbus;
+
+        let config = SuspendConfig {
+            targets: cli.auto_targets.clone(),
+            command: off_command(&daemon, cli.suspend_command.as_ref()),
+            restore: cli.suspend_restore,
+        };
+        let bus = cli.logind_bus.clone();
+        let daemon = daemon.clone();
+        tokio::spawn(async move {
+            if let Err(err) = dbus::run_suspend(daemon, bus, config).await {
+                eprintln!("Suspend handling stopped: {err}");
+            }
+        });
+    }
+
+    if let Some(minutes) = cli.idle {
+        let config = idle_config(&daemon, cli, minutes);
+        #[cfg(all(feature = "dbus", target_os = "linux"))]
+        let bus = cli.logind_bus.clone();
+        #[cfg(not(all(feature = "dbus", target_os = "linux")))]
+        let bus = ();
+        let daemon = daemon.clone();
+        tokio::spawn(async move {
+            if let Err(err) = idle::run_idle(daemon, config, bus).await {
+                eprintln!("Idle handling stopped: {err}");
+            }
+        });
+    }
+
+    Ok(daemon)
+}
+
+#[cfg(feature = "scripting")]
+fn load_scripts(
+    dir: &std::path::Path,
+    cli: &Cli,
+) -> vrlh_power_manager_daemon::Result<vrlh_power_manager_core::Scripts> {
+    use vrlh_power_manager_core::{ScriptConfig, Scripts};
+
+    let config = ScriptConfig {
+        timeout_ms: cli.script_timeout,
+        ..ScriptConfig::new(dir)
+    };
+    let aliases = cli.aliases.iter().cloned().collect::<Aliases>();
+    let scripts = Scripts::load(&config, aliases)?;
+    for run in scripts.runs().iter().filter(|run| run.error.is_some()) {
+        eprintln!(
+            "Script '{}' failed to load: {}",
+            run.script,
+            run.error.as_deref().unwrap_or_default()
+        );
+    }
+    Ok(scripts)
+}
+
+fn idle_config(daemon: &Daemon, cli: &Cli, minutes: u64) -> IdleConfig {
+    IdleConfig {
+        targets: cli.auto_targets.clone(),
+        minutes,
+        command: off_command(daemon, cli.idle_command.as_ref()),
+        warning_secs: cli.idle_warning,
+        source: cli
+            .idle_source_command
+            .clone()
+            .map_or(IdleSource::Logind, |command| IdleSource::Command {
+                command,
+                args: Vec::new(),
+            }),
+    }
+}
+
+/// The command given on the command line or else the configured off command
+fn off_command(daemon: &Daemon, command: Option<&DeviceCommand>) -> DeviceCommand {
+    command
+        .cloned()
+        .unwrap_or_else(|| daemon.config().power.off_command)
+}
+
+fn power_policy(daemon: &Daemon, cli: &Cli) -> PowerPolicy {
+    PowerPolicy {
+        targets: cli.auto_targets.clone(),
+        on_stop: off_command(daemon, cli.auto_off_command.as_ref()),
+        grace_secs: cli.auto_grace,
+        ..PowerPolicy::default()
+    }
+}
+
+/// Spawns every optional server besides the JSON-RPC socket
+// Nothing is used when the daemon is built without optional features
+#[allow(unused_variables, clippy::needless_pass_by_value)]
+fn serve_frontends(daemon: &Daemon, cli: Cli) {
+    #[cfg(feature = "http")]
+    if let Some(addr) = cli.http {
+        let daemon = daemon.clone();
+        eprintln!("Listening on 'http://{addr}'");
+        tokio::spawn(async move {
+            if let Err(err) =
+                vrlh_power_manager_daemon::http::serve(daemon, addr, cli.http_token).await
+            {
+                eprintln!("HTTP server stopped: {err}");
+            }
+        });
+    }
+
+    #[cfg(feature = "mqtt")]
+    if let Some((host, port)) = cli.mqtt {
+        use vrlh_power_manager_daemon::mqtt::{self, MqttConfig};
+
+        let config = MqttConfig {
+            host,
+            port,
+            username: cli.mqtt_username,
+            password: cli.mqtt_password,
+            prefix: cli.mqtt_prefix,
+            discovery_prefix: cli.mqtt_discovery_prefix,
+            off_command: off_command(daemon, cli.mqtt_off_command.as_ref()),
+            ..MqttConfig::default()
+        };
+        eprintln!("Bridging to MQTT broker at '{}'", config.broker());
+        tokio::spawn(mqtt::run(daemon.clone(), config));
+    }
+
+    #[cfg(feature = "osc")]
+    if let Some(listen) = cli.osc {
+        use vrlh_power_manager_daemon::osc::{self, OscConfig};
+
+        let config = OscConfig {
+            listen,
+            target: cli.osc_target,
+            prefix: cli.osc_prefix,
+        };
+        eprintln!("Listening for OSC on 'udp://{listen}'");
+        let daemon = daemon.clone();
+        tokio::spawn(async move {
+            if let Err(err) = osc::run(daemon, config).await {
+                eprintln!("OSC server stopped: {err}");
+            }
+        });
+    }
+
+    #[cfg(all(feature = "dbus", target_os = "linux"))]
+    if let Some(bus) = cli.dbus {
+        use vrlh_power_manager_daemon::dbus;
+
+        let daemon = daemon.clone();
+        tokio::spawn(async move {
+            if let Err(err) = dbus::run(daemon, bus).await {
+                eprintln!("D-Bus service stopped: {err}");
+            }
+        });
+    }
+}
@@ -0,0 +1,94 @@
+use serde_json::{json, Value};
+use vrlh_power_manager_core::{DeviceCommand, DeviceInfo};
+
+use super::{MqttConfig, Topics};
+use crate::address_slug;
+
+/// Home Assistant discovery payloads as `(topic, payload)` pairs for a single lighthouse
+pub fn payloads(config: &MqttConfig, topics: &Topics, info: &DeviceInfo) -> Vec<(String, Value)> {
+    let slug = address_slug(&info.addr);
+    let node = format!("{}_{slug}", config.prefix);
+    let device = json!({
+        "identifiers": [node],
+        "connections": [["bluetooth", info.addr]],
+        "name": info.name,
+        "manufacturer": "Valve",
+        "model": "Base Station 2.0",
+    });
+    let availability = json!([
+        { "topic": topics.bridge_status() },
+        { "topic": topics.availability(&slug) },
+    ]);
+    let options = [
+        DeviceCommand::Sleep,
+        DeviceCommand::Standby,
+        DeviceCommand::Activate,
+    ]
+    .iter()
+    .map(|command| format!("{command:?}"))
+    .collect::<Vec<_>>();
+
+    let entity = |component: &str, suffix: &str, mut extra: Value| {
+        let base = json!({
+            "unique_id": format!("{node}_{suffix}"),
+            "object_id": format!("{node}_{suffix}"),
+            "device": device,
+            "availability": availability,
+            "availability_mode": "all",
+        });
+        if let (Some(extra), Value::Object(base)) = (extra.as_object_mut(), base) {
+            extra.extend(base);
+        }
+        let topic = format!(
+            "{}/{component}/{node}/{suffix}/config",
+            config.discovery_prefix
+        );
+        (topic, extra)
+    };
+
+    vec![
+        entity(
+            "switch",
+            "power",
+            json!({
+                "name": "Power",
+                "icon": "mdi:lighthouse",
+                "state_topic": topics.switch_state(&slug),
+                "command_topic": topics.switch_set(&slug),
+                "payload_on": "ON",
+                "payload_off": "OFF",
+            }),
+        ),
+        entity(
+            "select",
+            "mode",
+            json!({
+                "name": "Mode",
+                "icon": "mdi:lighthouse-on",
+                "state_topic": topics.mode(&slug),
+                "command_topic": topics.set(&slug),
+                "options": options,
+            }),
+        ),
+        entity(
+            "sensor",
+            "remote",
+            json!({
+                "name": "Status",
+                "icon": "mdi:state-machine",
+                "state_topic": topics.remote(&slug),
+                "entity_category": "diagnostic",
+            }),
+        ),
+        entity(
+            "sensor",
+            "local",
+            json!({
+                "name": "Connection",
+                "icon": "mdi:bluetooth",
+                "state_topic": topics.local(&slug),
+                "entity_category": "diagnostic",
+            }),
+        ),
+    ]
+}
@@ -0,0 +1,419 @@
+mod discovery;
+
+use std::{collections::HashSet, net::Ipv6Addr, time::Duration};
+
+use rumqttc::{AsyncClient, Event, LastWill, MqttOptions, Packet, Publish, QoS};
+use tokio::{sync::broadcast::error::RecvError, time::sleep};
+use vrlh_power_manager_core::{
+    DeviceCommand, DeviceInfo, DeviceListEvent, DeviceLocalStatus, DeviceRemoteStatus,
+    DeviceSelector,
+};
+
+use crate::{address_slug, Daemon};
+
+/// Room for every retained message of a few lighthouses announced at once
+const REQUEST_CAPACITY: usize = 512;
+const DEFAULT_PORT: u16 = 1883;
+
+#[derive(Clone, Debug)]
+pub struct MqttConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub client_id: String,
+    /// Root of every state and command topic
+    pub prefix: String,
+    /// Root Home Assistant listens on for discovery payloads
+    pub discovery_prefix: String,
+    /// Command sent when a switch entity is turned off
+    pub off_command: DeviceCommand,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            host: "localhost".into(),
+            port: DEFAULT_PORT,
+            username: None,
+            password: None,
+            client_id: "vrlh-power-manager".into(),
+            prefix: "vrlh".into(),
+            discovery_prefix: "homeassistant".into(),
+            off_command: DeviceCommand::Sleep,
+        }
+    }
+}
+
+/// Topic layout below the configured prefix, lighthouses are addressed by their address slug
+#[derive(Clone, Debug)]
+pub struct Topics {
+    prefix: String,
+    discovery_prefix: String,
+}
+
+impl Topics {
+    pub fn bridge_status(&self) -> String {
+        format!("{}/status", self.prefix)
+    }
+
+    pub fn availability(&self, slug: &str) -> String {
+        format!("{}/{slug}/availability", self.prefix)
+    }
+
+    pub fn remote(&self, slug: &str) -> String {
+        format!("{}/{slug}/remote", self.prefix)
+    }
+
+    pub fn local(&self, slug: &str) -> String {
+        format!("{}/{slug}/local", self.prefix)
+    }
+
+    pub fn mode(&self, slug: &str) -> String {
+        format!("{}/{slug}/mode", self.prefix)
+    }
+
+    pub fn set(&self, slug: &str) -> String {
+        format!("{}/{slug}/set", self.prefix)
+    }
+
+    pub fn switch_state(&self, slug: &str) -> String {
+        format!("{}/{slug}/switch", self.prefix)
+    }
+
+    pub fn switch_set(&self, slug: &str) -> String {
+        format!("{}/{slug}/switch/set", self.prefix)
+    }
+
+    /// Home Assistant announces itself here after restarting and expects discovery to be resent
+    pub fn homeassistant_status(&self) -> String {
+        format!("{}/status", self.discovery_prefix)
+    }
+}
+
+impl MqttConfig {
+    /// `host:port` of the broker, with IPv6 addresses in brackets as rumqttc expects them
+    pub fn broker(&self) -> String {
+        format!("{}:{}", self.bracketed_host(), self.port)
+    }
+
+    fn bracketed_host(&self) -> String {
+        match self.host.parse::<Ipv6Addr>() {
+            Ok(_) => format!("[{}]", self.host),
+            Err(_) => self.host.clone(),
+        }
+    }
+}
+
+/// Parses `HOST[:PORT]` as accepted on command lines, IPv6 addresses need brackets to carry
+/// a port, e.g. `[::1]:1883`
+pub fn parse_broker(value: &str) -> Result<(String, u16), String> {
+    let invalid = || format!("Expected HOST[:PORT] but found '{value}'");
+    let (host, port) = match value.strip_prefix('[') {
+        Some(rest) => {
+            let (host, rest) = rest.split_once(']').ok_or_else(invalid)?;
+            match rest {
+                "" => (host, None),
+                rest => (host, Some(rest.strip_prefix(':').ok_or_else(invalid)?)),
+            }
+        }
+        // Several colons can only be a bare IPv6 address
+        None if value.matches(':').count() > 1 => (value, None),
+        None => match value.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (value, None),
+        },
+    };
+    if host.is_empty() {
+        return Err(invalid());
+    }
+    let port = match port {
+        Some(port) => port
+            .parse()
+            .map_err(|_| format!("Invalid MQTT broker port '{port}'"))?,
+        None => DEFAULT_PORT,
+    };
+    Ok((host.to_string(), port))
+}
+
+/// Bridges the daemon to an MQTT broker until the daemon shuts down
+pub async fn run(daemon: Daemon, config: MqttConfig) -> crate::Result<()> {
+    let topics = Topics {
+        prefix: config.prefix.clone(),
+        discovery_prefix: config.discovery_prefix.clone(),
+    };
+    let mut options = MqttOptions::new(&config.client_id, config.bracketed_host(), config.port);
+    options.set_keep_alive(Duration::from_secs(30));
+    options.set_last_will(LastWill::new(
+        topics.bridge_status(),
+        "offline",
+        QoS::AtLeastOnce,
+        true,
+    ));
+    if let Some(username) = &config.username {
+        options.set_credentials(username, config.password.clone().unwrap_or_default());
+    }
+    // Publishing never waits for room, so a stalled broker cannot stop the event loop from polling
+    let (client, mut eventloop) = AsyncClient::new(options, REQUEST_CAPACITY);
+
+    let bridge = Bridge {
+        daemon: daemon.clone(),
+        client,
+        config,
+        topics,
+    };
+    let mut events = daemon.devices().subscribe();
+    let mut announced = HashSet::new();
+    loop {
+        tokio::select! {
+            polled = eventloop.poll() => match polled {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    announced.clear();
+                    bridge.on_connected(&mut announced);
+                }
+                Ok(Event::Incoming(Packet::Publish(publish))) => {
+                    if publish.topic == bridge.topics.homeassistant_status() {
+                        announced.clear();
+                        bridge.on_connected(&mut announced);
+                    } else {
+                        bridge.spawn_command(&publish);
+                    }
+                }
+                Ok(_) => {}
+                // The event loop reconnects on the next poll, avoid spinning while the broker is down
+                Err(err) => {
+                    eprintln!("MQTT connection error: {err}");
+                    sleep(Duration::from_secs(5)).await;
+                }
+            },
+            event = events.recv() => match event {
+                Ok(DeviceListEvent::Device(info)) => bridge.publish_device(&info, &mut announced),
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => return Ok(()),
+            },
+        }
+    }
+}
+
+struct Bridge {
+    daemon: Daemon,
+    client: AsyncClient,
+    config: MqttConfig,
+    topics: Topics,
+}
+
+impl Bridge {
+    fn on_connected(&self, announced: &mut HashSet<String>) {
+        let prefix = &self.config.prefix;
+        let subscriptions = [
+            format!("{prefix}/+/set"),
+            format!("{prefix}/+/switch/set"),
+            self.topics.homeassistant_status(),
+        ];
+        for topic in subscriptions {
+            let _ = self.client.try_subscribe(topic, QoS::AtLeastOnce);
+        }
+        self.publish(self.topics.bridge_status(), "online");
+        for info in self.daemon.list() {
+            self.publish_device(&info, announced);
+        }
+    }
+
+    /// Publishes whichever statuses the update carries, announcing new lighthouses first
+    fn publish_device(&self, info: &DeviceInfo, announced: &mut HashSet<String>) {
+        if matches!(info.local, Some(DeviceLocalStatus::Ignored)) {
+            return;
+        }
+        let slug = address_slug(&info.addr);
+        if announced.insert(slug.clone()) {
+            let mut sent = true;
+            for (topic, payload) in discovery::payloads(&self.config, &self.topics, info) {
+                sent &= self.publish(topic, payload.to_string());
+            }
+            // Announce again with the next update if the queue was full
+            if !sent {
+                announced.remove(&slug);
+            }
+        }
+        if let Some(local) = &info.local {
+            let available = match local {
+                DeviceLocalStatus::FailConnection
+                | DeviceLocalStatus::FailVerify
+                | DeviceLocalStatus::Error(_) => "offline",
+                _ => "online",
+            };
+            self.publish(self.topics.availability(&slug), available);
+            self.publish(self.topics.local(&slug), local.to_string());
+        }
+        if let Some(remote) = &info.remote {
+            self.publish(self.topics.remote(&slug), remote.to_string());
+            let switch = match remote {
+                DeviceRemoteStatus::Active => "ON",
+                _ => "OFF",
+            };
+            self.publish(self.topics.switch_state(&slug), switch);
+            // Intermediate statuses have no matching option so the select keeps its last value
+            let mode = match remote {
+                DeviceRemoteStatus::Active => Some(DeviceCommand::Activate),
+                DeviceRemoteStatus::Standby => Some(DeviceCommand::Standby),
+                DeviceRemoteStatus::Stopped => Some(DeviceCommand::Sleep),
+                _ => None,
+            };
+            if let Some(mode) = mode {
+                self.publish(self.topics.mode(&slug), format!("{mode:?}"));
+            }
+        }
+    }
+
+    /// Commands can take a while, so they run without blocking the event loop
+    fn spawn_command(&self, publish: &Publish) {
+        let Some((slug, command)) = parse_command(&self.config, publish) else {
+            return;
+        };
+        let targets = match slug.as_str() {
+            "all" => vec![DeviceSelector::all()],
+            slug => self
+                .daemon
+                .list()
+                .into_iter()
+                .filter(|info| address_slug(&info.addr) == slug)
+                .map(|info| DeviceSelector::new(info.addr))
+                .collect(),
+        };
+        if targets.is_empty() {
+            return;
+        }
+        let daemon = self.daemon.clone();
+        tokio::spawn(async move { daemon.power(&command, &targets).await });
+    }
+
+    /// Queues a retained message, dropping it if the queue is full, returns whether it was queued
+    ///
+    /// Every state is published again once the broker connects, so nothing is lost for good
+    fn publish(&self, topic: String, payload: impl Into<Vec<u8>>) -> bool {
+        queue(&self.client, topic, payload)
+    }
+}
+
+fn queue(client: &AsyncClient, topic: String, payload: impl Into<Vec<u8>>) -> bool {
+    match client.try_publish(topic, QoS::AtLeastOnce, true, payload) {
+        Ok(()) => true,
+        Err(err) => {
+            eprintln!("Dropped MQTT message: {err}");
+            false
+        }
+    }
+}
+
+/// Reads the lighthouse slug and command of a message on a command topic
+fn parse_command(config: &MqttConfig, publish: &Publish) -> Option<(String, DeviceCommand)> {
+    let payload = std::str::from_utf8(&publish.payload).ok()?.trim();
+    let rest = publish
+        .topic
+        .strip_prefix(&config.prefix)?
+        .strip_prefix('/')?;
+    if let Some(slug) = rest.strip_suffix("/switch/set") {
+        let command = match payload {
+            "ON" => DeviceCommand::Activate,
+            "OFF" => config.off_command.clone(),
+            _ => return None,
+        };
+        return Some((slug.to_string(), command));
+    }
+    let slug = rest.strip_suffix("/set")?;
+    Some((slug.to_string(), payload.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(topic: &str, payload: &str) -> Publish {
+        Publish::new(topic, QoS::AtLeastOnce, payload)
+    }
+
+    #[test]
+    fn parses_brokers() {
+        assert_eq!(parse_broker("localhost"), Ok(("localhost".into(), 1883)));
+        assert_eq!(
+            parse_broker("broker.lan:18830"),
+            Ok(("broker.lan".into(), 18830))
+        );
+        assert_eq!(parse_broker("[::1]:1884"), Ok(("::1".into(), 1884)));
+        assert_eq!(parse_broker("[::1]"), Ok(("::1".into(), 1883)));
+        assert_eq!(parse_broker("::1"), Ok(("::1".into(), 1883)));
+        assert_eq!(
+            parse_broker("fe80::1:1883"),
+            Ok(("fe80::1:1883".into(), 1883))
+        );
+        for invalid in [
+            "host:18830x",
+            "host:",
+            "host:65536",
+            ":1883",
+            "",
+            "[::1",
+            "[::1]1883",
+            "[]:1883",
+        ] {
+            assert!(parse_broker(invalid).is_err(), "{invalid}");
+        }
+        assert_eq!(
+            parse_broker("host:18830x"),
+            Err("Invalid MQTT broker port '18830x'".into())
+        );
+    }
+
+    #[test]
+    fn brackets_ipv6_brokers() {
+        let config = |host: &str| MqttConfig {
+            host: host.into(),
+            ..MqttConfig::default()
+        };
+        assert_eq!(config("localhost").broker(), "localhost:1883");
+        assert_eq!(config("192.168.1.2").broker(), "192.168.1.2:1883");
+        assert_eq!(config("::1").broker(), "[::1]:1883");
+    }
+
+    #[test]
+    fn parses_command_topics() {
+        let config = MqttConfig::default();
+        assert_eq!(
+            parse_command(&config, &message("vrlh/aa_bb/set", " Standby\n")),
+            Some(("aa_bb".into(), DeviceCommand::Standby))
+        );
+        assert_eq!(
+            parse_command(&config, &message("vrlh/all/switch/set", "ON")),
+            Some(("all".into(), DeviceCommand::Activate))
+        );
+        assert_eq!(
+            parse_command(&config, &message("vrlh/aa_bb/switch/set", "OFF")),
+            Some(("aa_bb".into(), DeviceCommand::Sleep))
+        );
+    }
+
+    #[test]
+    fn rejects_other_topics_and_payloads() {
+        let config = MqttConfig::default();
+        for (topic, payload) in [
+            ("vrlh/aa_bb/set", "Explode"),
+            ("vrlh/aa_bb/switch/set", "TOGGLE"),
+            ("vrlh/aa_bb/mode", "Activate"),
+            ("other/aa_bb/set", "Activate"),
+            ("vrlhx/aa_bb/set", "Activate"),
+        ] {
+            assert_eq!(parse_command(&config, &message(topic, payload)), None);
+        }
+    }
+
+    #[test]
+    fn drops_messages_instead_of_waiting_on_a_full_queue() {
+        // Nothing polls the event loop, so the queue never drains
+        let options = MqttOptions::new("test", "localhost", 1883);
+        let (client, _eventloop) = AsyncClient::new(options, 2);
+        let queued = (0..4)
+            .map(|i| queue(&client, format!("vrlh/{i}"), "payload"))
+            .collect::<Vec<_>>();
+        assert_eq!(queued, [true, true, false, false]);
+    }
+}
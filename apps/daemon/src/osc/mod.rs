@@ -0,0 +1,233 @@
+use std::net::SocketAddr;
+
+use rosc::{decoder, encoder, OscMessage, OscPacket, OscType};
+use tokio::{net::UdpSocket, sync::broadcast::error::RecvError};
+use vrlh_power_manager_core::{
+    DeviceCommand, DeviceInfo, DeviceListEvent, DeviceLocalStatus, DeviceRemoteStatus,
+    DeviceSelector,
+};
+
+use crate::Daemon;
+
+#[derive(Clone, Debug)]
+pub struct OscConfig {
+    /// UDP address to receive commands on
+    pub listen: SocketAddr,
+    /// UDP address state changes are sent to, if any
+    pub target: Option<SocketAddr>,
+    /// Root of every incoming and outgoing address
+    pub prefix: String,
+}
+
+impl Default for OscConfig {
+    fn default() -> Self {
+        Self {
+            listen: SocketAddr::from(([127, 0, 0, 1], 9100)),
+            target: None,
+            prefix: "/vrlh".into(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OscAction {
+    Power(DeviceCommand),
+    Identify,
+    Scan,
+}
+
+/// Listens for OSC commands and forwards state changes until the daemon shuts down
+pub async fn run(daemon: Daemon, config: OscConfig) -> crate::Result<()> {
+    let socket = UdpSocket::bind(config.listen).await?;
+    let mut events = daemon.devices().subscribe();
+    let mut buf = vec![0; decoder::MTU];
+
+    if let Some(target) = config.target {
+        for info in daemon.list() {
+            send_device(&socket, target, &config.prefix, &info).await;
+        }
+    }
+    loop {
+        tokio::select! {
+            received = socket.recv_from(&mut buf) => {
+                let Ok((len, _)) = received else {
+                    continue;
+                };
+                if let Ok((_, packet)) = decoder::decode_udp(&buf[..len]) {
+                    handle_packet(&daemon, &config.prefix, packet);
+                }
+            }
+            event = events.recv() => match event {
+                Ok(DeviceListEvent::Device(info)) => {
+                    if let Some(target) = config.target {
+                        send_device(&socket, target, &config.prefix, &info).await;
+                    }
+                }
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => return Ok(()),
+            },
+        }
+    }
+}
+
+/// Parses `<prefix>/scan` and `<prefix>/<target>/<action>`, where the target is `all`
+/// or any selector and the action is `identify` or a power command
+pub fn parse_address(prefix: &str, addr: &str) -> Option<(Vec<DeviceSelector>, OscAction)> {
+    let rest = addr.strip_prefix(prefix)?.strip_prefix('/')?;
+    if rest == "scan" {
+        return Some((Vec::new(), OscAction::Scan));
+    }
+    let (target, action) = rest.split_once('/')?;
+    let action = match action {
+        "identify" => OscAction::Identify,
+        action => OscAction::Power(action.parse().ok()?),
+    };
+    let target = match target {
+        "all" => DeviceSelector::all(),
+        target => DeviceSelector::new(target),
+    };
+    Some((vec![target], action))
+}
+
+/// Buttons send a truthy argument when pressed and a falsy one when released,
+/// so only the press triggers a command
+pub fn is_triggered(args: &[OscType]) -> bool {
+    match args.first() {
+        Some(OscType::Bool(value)) => *value,
+        Some(OscType::Int(value)) => *value != 0,
+        Some(OscType::Long(value)) => *value != 0,
+        Some(OscType::Float(value)) => *value != 0.0,
+        Some(OscType::Double(value)) => *value != 0.0,
+        _ => true,
+    }
+}
+
+/// Commands can take a while, so they run without blocking the socket
+fn handle_packet(daemon: &Daemon, prefix: &str, packet: OscPacket) {
+    match packet {
+        OscPacket::Bundle(bundle) => {
+            for packet in bundle.content {
+                handle_packet(daemon, prefix, packet);
+            }
+        }
+        OscPacket::Message(message) => {
+            if !is_triggered(&message.args) {
+                return;
+            }
+            let Some((targets, action)) = parse_address(prefix, &message.addr) else {
+                return;
+            };
+            let daemon = daemon.clone();
+            tokio::spawn(async move {
+                let result = match action {
+                    OscAction::Power(command) => daemon.power(&command, &targets).await,
+                    OscAction::Identify => daemon.identify(&targets).await,
+                    OscAction::Scan => daemon.scan(None).await,
+                };
+                if let Err(err) = result {
+                    daemon.publish_status(format!("OSC command '{}' failed: {err}", message.addr));
+                }
+            });
+        }
+    }
+}
+
+/// Sends whichever statuses the update carries below `<prefix>/<name>/`
+async fn send_device(socket: &UdpSocket, target: SocketAddr, prefix: &str, info: &DeviceInfo) {
+    if matches!(info.local, Some(DeviceLocalStatus::Ignored)) {
+        return;
+    }
+    let base = format!("{prefix}/{}", info.name);
+    let mut messages = Vec::new();
+    if let Some(local) = &info.local {
+        messages.push((format!("{base}/local"), OscType::String(local.to_string())));
+    }
+    if let Some(remote) = &info.remote {
+        messages.push((
+            format!("{base}/remote"),
+            OscType::String(remote.to_string()),
+        ));
+        let active = matches!(remote, DeviceRemoteStatus::Active);
+        messages.push((format!("{base}/active"), OscType::Bool(active)));
+    }
+    for (addr, arg) in messages {
+        let packet = OscPacket::Message(OscMessage {
+            addr,
+            args: vec![arg],
+        });
+        if let Ok(bytes) = encoder::encode(&packet) {
+            let _ = socket.send_to(&bytes, target).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_scans_and_device_commands() {
+        assert_eq!(
+            parse_address("/vrlh", "/vrlh/scan"),
+            Some((Vec::new(), OscAction::Scan))
+        );
+        assert_eq!(
+            parse_address("/vrlh", "/vrlh/all/on"),
+            Some((
+                vec![DeviceSelector::all()],
+                OscAction::Power(DeviceCommand::Activate)
+            ))
+        );
+        assert_eq!(
+            parse_address("/vrlh", "/vrlh/LHB-1A2B3C4D/Standby"),
+            Some((
+                vec![DeviceSelector::new("LHB-1A2B3C4D")],
+                OscAction::Power(DeviceCommand::Standby)
+            ))
+        );
+        assert_eq!(
+            parse_address("/avatar/vrlh", "/avatar/vrlh/left/identify"),
+            Some((vec![DeviceSelector::new("left")], OscAction::Identify))
+        );
+    }
+
+    #[test]
+    fn ignores_other_addresses() {
+        for addr in [
+            "/other/all/on",
+            "/vrlhx/all/on",
+            "/vrlh",
+            "/vrlh/",
+            "/vrlh/all",
+            "/vrlh/all/explode",
+            "/vrlh/all/on/now",
+            "vrlh/all/on",
+        ] {
+            assert_eq!(parse_address("/vrlh", addr), None, "{addr}");
+        }
+    }
+
+    #[test]
+    fn triggers_on_press_only() {
+        for released in [
+            OscType::Int(0),
+            OscType::Long(0),
+            OscType::Bool(false),
+            OscType::Float(0.0),
+            OscType::Double(0.0),
+        ] {
+            let args = [released];
+            assert!(!is_triggered(&args), "{args:?}");
+        }
+        for pressed in [
+            OscType::Int(1),
+            OscType::Bool(true),
+            OscType::Float(1.0),
+            OscType::String("on".into()),
+        ] {
+            let args = [pressed];
+            assert!(is_triggered(&args), "{args:?}");
+        }
+        assert!(is_triggered(&[]));
+    }
+}
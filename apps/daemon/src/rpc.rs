@@ -0,0 +1,268 @@
+use std::{
+    fs::{DirBuilder, Permissions},
+    os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt},
+    path::Path,
+};
+
+use rustix::process::getuid;
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+    sync::{broadcast::error::RecvError, mpsc},
+    task::JoinHandle,
+};
+use vrlh_power_manager_core::{
+    IdentifyParams, PowerParams, RpcNotification, RpcRequest, RpcResponse, RulesDryRunParams,
+    ScanParams, StatusParams, ERROR_INVALID_REQUEST, ERROR_METHOD_NOT_FOUND, ERROR_PARSE,
+    JSONRPC_VERSION, METHOD_CANCEL_IDLE, METHOD_HOOK_RUNS, METHOD_IDENTIFY, METHOD_POWER,
+    METHOD_RULES_DRY_RUN, METHOD_RULE_EVALUATIONS, METHOD_SCAN, METHOD_STATUS, METHOD_SUBSCRIBE,
+    NOTIFICATION_EVENT,
+};
+
+use crate::Daemon;
+
+/// Accepts JSON-RPC clients on a unix socket until the listener fails
+pub async fn serve(daemon: Daemon, path: &Path) -> crate::Result<()> {
+    let listener = bind(path).await?;
+    loop {
+        let (stream, _) = listener.accept().await?;
+        tokio::spawn(handle_connection(daemon.clone(), stream));
+    }
+}
+
+/// Binds a socket only the user can connect to, in a directory no one else controls
+async fn bind(path: &Path) -> crate::Result<UnixListener> {
+    let uid = getuid().as_raw();
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        if !dir.exists() {
+            DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+        }
+        // Anyone owning the directory could swap the socket for their own
+        let owner = std::fs::metadata(dir)?.uid();
+        if owner != uid && owner != 0 {
+            let msg = format!("'{}' belongs to another user!", dir.display());
+            return Err(crate::Error::VrlhDaemon(msg));
+        }
+    }
+
+    // A socket file left behind by a crashed daemon would otherwise prevent binding
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if metadata.uid() != uid {
+            let msg = format!("'{}' belongs to another user!", path.display());
+            return Err(crate::Error::VrlhDaemon(msg));
+        }
+        if UnixStream::connect(path).await.is_ok() {
+            let msg = format!(
+                "Another daemon is already listening on '{}'!",
+                path.display()
+            );
+            return Err(crate::Error::VrlhDaemon(msg));
+        }
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    std::fs::set_permissions(path, Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+async fn handle_connection(daemon: Daemon, stream: UnixStream) {
+    let (reader, mut writer) = stream.into_split();
+    let (tx, mut rx) = mpsc::channel::<String>(32);
+    let writer_task = tokio::spawn(async move {
+        while let Some(mut line) = rx.recv().await {
+            line.push('\n');
+            if writer.write_all(line.as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut lines = BufReader::new(reader).lines();
+    let mut subscription: Option<JoinHandle<()>> = None;
+    while let Ok(Some(line)) = lines.next_line().await {
+        let request = match serde_json::from_str::<RpcRequest>(&line) {
+            Ok(request) if request.jsonrpc == JSONRPC_VERSION => request,
+            Ok(request) => {
+                let id = request.id.unwrap_or(Value::Null);
+                let response = RpcResponse::error(id, ERROR_INVALID_REQUEST, "Unsupported version");
+                send(&tx, &response).await;
+                continue;
+            }
+            Err(err) => {
+                send(
+                    &tx,
+                    &RpcResponse::error(Value::Null, ERROR_PARSE, err.to_string()),
+                )
+                .await;
+                continue;
+            }
+        };
+
+        if request.method == METHOD_SUBSCRIBE {
+            if subscription.is_none() {
+                subscription = Some(subscribe(&daemon, tx.clone()));
+            }
+            if let Some(id) = request.id {
+                send(&tx, &RpcResponse::result(id, Value::Bool(true))).await;
+            }
+            continue;
+        }
+
+        // Requests run concurrently so a long power command does not block status queries
+        let daemon = daemon.clone();
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            let result = dispatch(&daemon, &request.method, request.params).await;
+            // Requests without an id are notifications and must not be answered
+            let Some(id) = request.id else {
+                return;
+            };
+            let response = match result {
+                Ok(value) => RpcResponse::result(id, value),
+                Err(err) => RpcResponse::error(id, err.code, err.message),
+            };
+            send(&tx, &response).await;
+        });
+    }
+
+    if let Some(subscription) = subscription {
+        subscription.abort();
+    }
+    drop(tx);
+    let _ = writer_task.await;
+}
+
+fn subscribe(daemon: &Daemon, tx: mpsc::Sender<String>) -> JoinHandle<()> {
+    let mut events = daemon.devices().subscribe();
+    tokio::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(event) => {
+                    let Ok(params) = serde_json::to_value(event) else {
+                        continue;
+                    };
+                    let notification = RpcNotification::new(NOTIFICATION_EVENT, params);
+                    if !send(&tx, &notification).await {
+                        break;
+                    }
+                }
+                // Slow subscribers miss events rather than holding up everyone else
+                Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+struct DispatchError {
+    code: i64,
+    message: String,
+}
+
+impl From<crate::Error> for DispatchError {
+    fn from(err: crate::Error) -> Self {
+        Self {
+            code: err.code(),
+            message: err.to_string(),
+        }
+    }
+}
+
+impl From<serde_json::Error> for DispatchError {
+    fn from(err: serde_json::Error) -> Self {
+        crate::Error::from(err).into()
+    }
+}
+
+async fn dispatch(daemon: &Daemon, method: &str, params: Value) -> Result<Value, DispatchError> {
+    let result = match method {
+        METHOD_SCAN => {
+            let params = parse::<ScanParams>(params)?;
+            serde_json::to_value(daemon.scan(params.duration).await?)?
+        }
+        METHOD_STATUS => {
+            let params = parse::<StatusParams>(params)?;
+            serde_json::to_value(daemon.status(&params.targets)?)?
+        }
+        METHOD_POWER => {
+            let params = parse::<PowerParams>(params)?;
+            serde_json::to_value(daemon.power(&params.command, &params.targets).await?)?
+        }
+        METHOD_IDENTIFY => {
+            let params = parse::<IdentifyParams>(params)?;
+            serde_json::to_value(daemon.identify(&params.targets).await?)?
+        }
+        METHOD_CANCEL_IDLE => {
+            daemon.cancel_idle();
+            Value::Bool(true)
+        }
+        METHOD_HOOK_RUNS => serde_json::to_value(daemon.hook_runs())?,
+        METHOD_RULE_EVALUATIONS => serde_json::to_value(daemon.rule_evaluations())?,
+        METHOD_RULES_DRY_RUN => {
+            let params = parse::<RulesDryRunParams>(params)?;
+            serde_json::to_value(daemon.rules_dry_run(&params.input)?)?
+        }
+        #[cfg(feature = "webhooks")]
+        vrlh_power_manager_core::METHOD_WEBHOOK_DELIVERIES => {
+            serde_json::to_value(daemon.webhook_deliveries())?
+        }
+        #[cfg(feature = "scripting")]
+        vrlh_power_manager_core::METHOD_SCRIPT_RUNS => serde_json::to_value(daemon.script_runs())?,
+        _ => {
+            return Err(DispatchError {
+                code: ERROR_METHOD_NOT_FOUND,
+                message: format!("Unknown method '{method}'"),
+            })
+        }
+    };
+    Ok(result)
+}
+
+/// Missing params are treated as an empty object so defaults apply
+fn parse<T: DeserializeOwned>(params: Value) -> Result<T, serde_json::Error> {
+    match params {
+        Value::Null => serde_json::from_value(Value::Object(serde_json::Map::new())),
+        params => serde_json::from_value(params),
+    }
+}
+
+async fn send<T: serde::Serialize>(tx: &mpsc::Sender<String>, message: &T) -> bool {
+    match serde_json::to_string(message) {
+        Ok(line) => tx.send(line).await.is_ok(),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn creates_a_private_socket() {
+        let root = tempfile::tempdir().unwrap();
+        let path = root.path().join("private").join("daemon.sock");
+        let _listener = bind(&path).await.unwrap();
+
+        let dir = fs::metadata(path.parent().unwrap()).unwrap();
+        assert_eq!(dir.permissions().mode() & 0o777, 0o700);
+        let socket = fs::metadata(&path).unwrap();
+        assert_eq!(socket.permissions().mode() & 0o777, 0o600);
+    }
+
+    #[tokio::test]
+    async fn replaces_stale_sockets_only() {
+        let root = tempfile::tempdir().unwrap();
+        let path = root.path().join("daemon.sock");
+        drop(bind(&path).await.unwrap());
+        assert!(path.exists());
+
+        let listener = bind(&path).await.unwrap();
+        assert!(bind(&path).await.is_err());
+        drop(listener);
+    }
+}
@@ -0,0 +1,47 @@
+// Tauri hands command arguments over by value
+#![allow(clippy::needless_pass_by_value)]
+
+use tauri::{AppHandle, State};
+use vrlh_power_manager_core::{Config, HotkeyRegistration, LoadedConfig};
+
+use crate::{hotkeys, startup, AppState};
+
+#[tauri::command]
+pub fn get_config(state: State<'_, AppState>) -> LoadedConfig {
+    state.get_config()
+}
+
+/// Writes the settings to the configuration file and applies them right away
+#[tauri::command]
+pub fn save_config(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    config: Config,
+) -> crate::Result<LoadedConfig> {
+    let path = state.get_config().path;
+    config.save(&path)?;
+    let rebind = state.settings().hotkeys != config.hotkeys;
+    let loaded = LoadedConfig {
+        config,
+        path,
+        error: None,
+    };
+    state.set_config(loaded.clone());
+    if rebind {
+        hotkeys::register(&app);
+    }
+    startup::sync_autostart(&app);
+    Ok(loaded)
+}
+
+/// Configured hotkeys along with why any of them could not be registered
+#[tauri::command]
+pub fn list_hotkeys(state: State<'_, AppState>) -> Vec<HotkeyRegistration> {
+    state.get_hotkeys()
+}
+
+/// Problems found while starting, which happened before the window could show them
+#[tauri::command]
+pub fn startup_notices(state: State<'_, AppState>) -> Vec<String> {
+    state.notices.clone()
+}
@@ -2,30 +2,14 @@ use tauri::{AppHandle, Manager as _};
 use vrlh_power_manager_core::{DeviceInfo, DeviceList};
 
 use crate::{
-    events::{EmitEvent, StatusPayload},
-    AppState,
+    events::{forward_events, EmitEvent, StatusPayload},
+    tray, AppState,
 };
 
 #[tauri::command(async)]
-pub async fn discover(app: AppHandle, duration: u64) -> crate::Result<()> {
-    let state = app.state::<AppState>();
-    let devices = match state.get_devices() {
-        // Initialize device list if not yet initialized
-        None => {
-            let init = DeviceList::init().await.inspect_err(|_| {
-                let _ = app.emit_event(StatusPayload::from("No bluetooth adapter available!"));
-            })?;
-            let mut guard = state
-                .devices
-                .lock()
-                .expect("Device list mutex must not be poisoned");
-            if guard.is_some() {
-                let msg = "Aborting potential duplicate scan!".into();
-                return Err(crate::Error::VrlhApp(msg));
-            }
-            *guard = Some(init.clone());
-            init
-        }
+pub async fn discover(app: AppHandle, duration: Option<u64>) -> crate::Result<()> {
+    let devices = match app.state::<AppState>().get_devices() {
+        None => device_list(&app).await?,
         // Immediately send all statuses of currently available devices
         Some(list) => {
             for device in list
@@ -43,11 +27,35 @@ pub async fn discover(app: AppHandle, duration: u64) -> crate::Result<()> {
     };
 
     let _ = app.emit_event(StatusPayload::from("Scanning for lighthouses..."));
+    // Updates reach the window through the forwarded device list events
+    let duration = duration.unwrap_or_else(|| devices.config().scan.duration_secs);
     let mut rx = devices.start_scan(duration)?;
-    while let Some(payload) = rx.recv().await {
-        let _ = app.emit_event(payload);
-    }
+    while rx.recv().await.is_some() {}
 
     let _ = app.emit_event(StatusPayload::from("Done scanning for devices!"));
     Ok(())
 }
+
+/// The device list, initializing it and forwarding its events if not yet initialized
+pub async fn device_list(app: &AppHandle) -> crate::Result<DeviceList> {
+    let state = app.state::<AppState>();
+    if let Some(devices) = state.get_devices() {
+        return Ok(devices);
+    }
+    let init = DeviceList::init().await.inspect_err(|_| {
+        let _ = app.emit_event(StatusPayload::from("No bluetooth adapter available!"));
+    })?;
+    let mut guard = state
+        .devices
+        .lock()
+        .expect("Device list mutex must not be poisoned");
+    // The window, the scheduler and startup actions may all initialize at launch, keep the first
+    if let Some(existing) = guard.as_ref() {
+        return Ok(existing.clone());
+    }
+    init.set_config(state.settings());
+    *guard = Some(init.clone());
+    forward_events(app.clone(), &init);
+    tray::follow_devices(app.clone(), &init);
+    Ok(init)
+}
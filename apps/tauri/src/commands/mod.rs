@@ -1,5 +1,9 @@
+mod config;
 mod discover;
 mod power;
+mod schedule;
 
+pub use config::*;
 pub use discover::*;
 pub use power::*;
+pub use schedule::*;
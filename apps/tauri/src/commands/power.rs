@@ -1,6 +1,8 @@
 use btleplug::platform::PeripheralId;
 use tauri::{AppHandle, Manager};
-use vrlh_power_manager_core::{DeviceCommand, DeviceLocalStatus};
+use vrlh_power_manager_core::{
+    expand_groups, Aliases, Device, DeviceCommand, DeviceLocalStatus, DeviceSelector,
+};
 
 use crate::{
     events::{EmitEvent, StatusPayload},
@@ -10,15 +12,50 @@ use crate::{
 
 #[tauri::command(async)]
 pub async fn power(app: AppHandle, cmd: u8, id: PeripheralId) -> crate::Result<()> {
-    let command = match cmd {
-        0 => DeviceCommand::Sleep,
-        1 => DeviceCommand::Activate,
-        2 => DeviceCommand::Standby,
-        _ => return Err(crate::Error::VrlhApp("Invalid power command".into())),
-    };
+    let command = DeviceCommand::try_from(cmd)?;
     handle_power_command(app, id, command).await
 }
 
+/// Sends the command to every lighthouse matching the targets, which may name groups
+#[tauri::command(async)]
+pub async fn power_targets(
+    app: AppHandle,
+    command: DeviceCommand,
+    targets: Vec<DeviceSelector>,
+) -> crate::Result<()> {
+    let state = app.state::<AppState>();
+    let devices = state.assert_devices()?;
+    let targets = expand_groups(&targets, &state.scheduler.groups());
+    let selected = devices.select(&targets, &Aliases::new());
+    if selected.is_empty() {
+        return Err(crate::Error::VrlhApp(
+            "No lighthouses matched the selection!".into(),
+        ));
+    }
+
+    let names = describe(&selected);
+    let _ = app.emit_event(StatusPayload::from(format!(
+        r#"Sending "{command}" command to {names}"#
+    )));
+    // Updates reach the window through the forwarded device list events
+    let mut rx = devices.start_power(selected, &command);
+    while rx.recv().await.is_some() {}
+    let _ = app.emit_event(StatusPayload::from(format!(
+        r#"Finished "{command}" for {names}"#
+    )));
+    Ok(())
+}
+
+/// Runs [`power_targets`] in the background, reporting failures as status messages
+pub fn spawn_power_targets(app: &AppHandle, command: DeviceCommand, targets: Vec<DeviceSelector>) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(err) = power_targets(app.clone(), command, targets).await {
+            let _ = app.emit_event(StatusPayload::from(err.to_string()));
+        }
+    });
+}
+
 async fn handle_power_command(
     app: AppHandle,
     id: PeripheralId,
@@ -47,3 +84,10 @@ async fn handle_power_command(
     )));
     Ok(())
 }
+
+fn describe(devices: &[Device]) -> String {
+    match devices {
+        [device] => format!(r#""{}""#, device.name()),
+        _ => format!("{} lighthouses", devices.len()),
+    }
+}
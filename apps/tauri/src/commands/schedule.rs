@@ -0,0 +1,82 @@
+// Tauri hands command arguments over by value
+#![allow(clippy::needless_pass_by_value)]
+
+use chrono::Utc;
+use tauri::{AppHandle, State};
+use vrlh_power_manager_core::{
+    CalendarConfig, CalendarEvent, DeviceSelector, Groups, Schedule, ScheduleNextRun,
+};
+
+use crate::{hotkeys, tray, AppState};
+
+#[tauri::command]
+pub fn list_schedules(state: State<'_, AppState>) -> Vec<Schedule> {
+    state.scheduler.schedules()
+}
+
+#[tauri::command]
+pub fn save_schedule(state: State<'_, AppState>, schedule: Schedule) -> crate::Result<()> {
+    Ok(state.scheduler.save_schedule(schedule)?)
+}
+
+#[tauri::command]
+pub fn delete_schedule(state: State<'_, AppState>, id: String) -> crate::Result<bool> {
+    Ok(state.scheduler.delete_schedule(&id)?)
+}
+
+#[tauri::command]
+pub fn next_schedule_runs(state: State<'_, AppState>) -> Vec<ScheduleNextRun> {
+    state.scheduler.next_runs(Utc::now())
+}
+
+#[tauri::command]
+pub fn list_calendars(state: State<'_, AppState>) -> Vec<CalendarConfig> {
+    state.scheduler.calendars()
+}
+
+#[tauri::command]
+pub fn save_calendar(state: State<'_, AppState>, calendar: CalendarConfig) -> crate::Result<()> {
+    Ok(state.scheduler.save_calendar(calendar)?)
+}
+
+#[tauri::command]
+pub fn delete_calendar(state: State<'_, AppState>, id: String) -> crate::Result<bool> {
+    Ok(state.scheduler.delete_calendar(&id)?)
+}
+
+#[tauri::command(async)]
+pub fn calendar_sessions(
+    state: State<'_, AppState>,
+    id: String,
+) -> crate::Result<Vec<CalendarEvent>> {
+    Ok(state.scheduler.calendar_sessions(&id)?)
+}
+
+#[tauri::command]
+pub fn list_groups(state: State<'_, AppState>) -> Groups {
+    state.scheduler.groups()
+}
+
+#[tauri::command]
+pub fn save_group(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    name: String,
+    members: Vec<DeviceSelector>,
+) -> crate::Result<()> {
+    state.scheduler.save_group(name, members)?;
+    hotkeys::register(&app);
+    tray::refresh_menu(&app)
+}
+
+#[tauri::command]
+pub fn delete_group(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    name: String,
+) -> crate::Result<bool> {
+    let existed = state.scheduler.delete_group(&name)?;
+    hotkeys::register(&app);
+    tray::refresh_menu(&app)?;
+    Ok(existed)
+}
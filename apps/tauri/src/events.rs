@@ -1,6 +1,9 @@
 use serde::Serialize;
-use tauri::{AppHandle, Emitter};
-use vrlh_power_manager_core::DeviceInfo;
+use tauri::{AppHandle, Emitter, Manager as _};
+use tokio::sync::{broadcast::error::RecvError, watch};
+use vrlh_power_manager_core::{DeviceInfo, DeviceList, DeviceListEvent, LoadedConfig};
+
+use crate::{hotkeys, startup, AppState};
 
 #[derive(Clone, Debug, Serialize)]
 pub struct StatusPayload(String);
@@ -26,3 +29,50 @@ impl EmitEvent<StatusPayload> for AppHandle {
         self.emit("status", payload).map_err(Into::into)
     }
 }
+
+impl EmitEvent<LoadedConfig> for AppHandle {
+    fn emit_event(&self, payload: LoadedConfig) -> crate::Result<()> {
+        self.emit("config", payload).map_err(Into::into)
+    }
+}
+
+/// Applies every reloaded configuration and emits it to the window
+pub async fn forward_config(app: AppHandle, mut rx: watch::Receiver<LoadedConfig>) {
+    while rx.changed().await.is_ok() {
+        let loaded = rx.borrow_and_update().clone();
+        let path = loaded.path.display();
+        let message = match &loaded.error {
+            Some(err) => format!("Keeping the previous configuration, '{path}' is invalid: {err}"),
+            None => format!("Reloaded configuration from '{path}'"),
+        };
+        let _ = app.emit_event(StatusPayload::from(message));
+        let state = app.state::<AppState>();
+        let rebind = state.settings().hotkeys != loaded.config.hotkeys;
+        state.set_config(loaded.clone());
+        if rebind {
+            hotkeys::register(&app);
+        }
+        startup::sync_autostart(&app);
+        let _ = app.emit_event(loaded);
+    }
+}
+
+/// Emits device updates and status messages of the list to the window,
+/// including those caused by schedules rather than the window itself
+pub fn forward_events(app: AppHandle, devices: &DeviceList) {
+    let mut events = devices.subscribe();
+    tokio::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(DeviceListEvent::Device(info)) => {
+                    let _ = app.emit_event(info);
+                }
+                Ok(DeviceListEvent::Status(message)) => {
+                    let _ = app.emit_event(StatusPayload::from(message));
+                }
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => return,
+            }
+        }
+    });
+}
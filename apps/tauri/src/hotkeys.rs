@@ -0,0 +1,83 @@
+use std::str::FromStr;
+
+use tauri::{AppHandle, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutEvent, ShortcutState};
+use vrlh_power_manager_core::{DeviceSelector, HotkeyConfig, HotkeyRegistration};
+
+use crate::{
+    commands,
+    events::{EmitEvent, StatusPayload},
+    AppState,
+};
+
+/// Replaces every registered hotkey with the configured ones, reporting the ones that failed
+pub fn register(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    let shortcuts = app.global_shortcut();
+    if let Err(err) = shortcuts.unregister_all() {
+        let _ = app.emit_event(StatusPayload::from(format!(
+            "Could not unregister hotkeys: {err}"
+        )));
+    }
+
+    let groups = state.scheduler.groups();
+    let mut registered = Vec::<Shortcut>::new();
+    let registrations = state
+        .settings()
+        .hotkeys
+        .into_iter()
+        .map(|hotkey| {
+            let result = Shortcut::from_str(&hotkey.keys)
+                .map_err(|err| format!("Invalid key combination: {err}"))
+                .and_then(|shortcut| {
+                    if let Some(group) = &hotkey.group {
+                        if !groups.keys().any(|name| name.eq_ignore_ascii_case(group)) {
+                            return Err(format!("Group '{group}' does not exist"));
+                        }
+                    }
+                    if registered.iter().any(|other| other.id() == shortcut.id()) {
+                        return Err("Conflicts with another hotkey".into());
+                    }
+                    shortcuts
+                        .register(shortcut)
+                        .map_err(|err| format!("Already taken or not allowed: {err}"))?;
+                    registered.push(shortcut);
+                    Ok(())
+                });
+            if let Err(err) = &result {
+                let _ = app.emit_event(StatusPayload::from(format!(
+                    "Hotkey '{}' was not registered: {err}",
+                    hotkey.keys
+                )));
+            }
+            HotkeyRegistration {
+                hotkey,
+                error: result.err(),
+            }
+        })
+        .collect();
+    *state
+        .hotkeys
+        .lock()
+        .expect("Hotkey mutex must not be poisoned") = registrations;
+}
+
+/// Sends the command of the hotkey matching the pressed shortcut
+pub fn handle(app: &AppHandle, shortcut: &Shortcut, event: ShortcutEvent) {
+    if event.state() != ShortcutState::Pressed {
+        return;
+    }
+    let hotkey = app
+        .state::<AppState>()
+        .get_hotkeys()
+        .into_iter()
+        .filter(|registration| registration.error.is_none())
+        .map(|registration| registration.hotkey)
+        .find(|hotkey| {
+            Shortcut::from_str(&hotkey.keys).is_ok_and(|other| other.id() == shortcut.id())
+        });
+    if let Some(HotkeyConfig { command, group, .. }) = hotkey {
+        let target = group.map_or_else(DeviceSelector::all, DeviceSelector::new);
+        commands::spawn_power_targets(app, command, vec![target]);
+    }
+}
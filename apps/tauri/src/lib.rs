@@ -1,24 +1,80 @@
 mod commands;
 mod error;
 mod events;
+mod hotkeys;
+mod startup;
 mod traits;
+mod tray;
 
-use std::sync::Mutex;
+use std::{path::Path, sync::Mutex};
 
 use btleplug::platform::PeripheralId;
 pub use error::*;
 use futures::future::join_all;
 use tauri::{
-    async_runtime::block_on, generate_context, generate_handler, Builder, Manager, RunEvent,
+    async_runtime::block_on, generate_context, generate_handler, AppHandle, Builder, Manager,
+    RunEvent, WindowEvent,
 };
-use vrlh_power_manager_core::{Device, DeviceList};
+use vrlh_power_manager_core::{
+    config_path, Aliases, Config, ConfigWatcher, Device, DeviceList, HotkeyRegistration,
+    LoadedConfig, Scheduler,
+};
+
+/// File in the app config directory schedules, calendars and groups are kept in
+const SCHEDULES_FILE: &str = "schedules.json";
 
-#[derive(Default)]
 pub struct AppState {
     devices: Mutex<Option<DeviceList>>,
+    scheduler: Scheduler,
+    config: Mutex<LoadedConfig>,
+    hotkeys: Mutex<Vec<HotkeyRegistration>>,
+    /// Problems found while starting, before the window listens for statuses
+    notices: Vec<String>,
 }
 
 impl AppState {
+    fn new(scheduler: Scheduler, config: LoadedConfig, notices: Vec<String>) -> Self {
+        for notice in &notices {
+            eprintln!("{notice}");
+        }
+        Self {
+            devices: Mutex::new(None),
+            scheduler,
+            config: Mutex::new(config),
+            hotkeys: Mutex::new(Vec::new()),
+            notices,
+        }
+    }
+
+    fn get_hotkeys(&self) -> Vec<HotkeyRegistration> {
+        self.hotkeys
+            .lock()
+            .expect("Hotkey mutex must not be poisoned")
+            .clone()
+    }
+
+    fn get_config(&self) -> LoadedConfig {
+        self.config
+            .lock()
+            .expect("Config mutex must not be poisoned")
+            .clone()
+    }
+
+    /// Stores the settings and applies them to the device list if it is initialized
+    fn set_config(&self, loaded: LoadedConfig) {
+        if let Some(devices) = self.get_devices() {
+            devices.set_config(loaded.config.clone());
+        }
+        *self
+            .config
+            .lock()
+            .expect("Config mutex must not be poisoned") = loaded;
+    }
+
+    fn settings(&self) -> Config {
+        self.get_config().config
+    }
+
     fn get_devices(&self) -> Option<DeviceList> {
         self.devices
             .lock()
@@ -39,18 +95,103 @@ impl AppState {
     }
 }
 
+/// Starts without schedules when the file is invalid, moving it aside so it is not overwritten
+fn load_scheduler(path: &Path, notices: &mut Vec<String>) -> Scheduler {
+    Scheduler::load(path).unwrap_or_else(|err| {
+        let backup = path.with_extension("json.invalid");
+        let (scheduler, kept) = match std::fs::rename(path, &backup) {
+            Ok(()) => (
+                Scheduler::load(path),
+                format!("it was moved to '{}'", backup.display()),
+            ),
+            // Without the file schedules still work but changes are not saved
+            Err(_) => (Ok(Scheduler::default()), "changes will not be saved".into()),
+        };
+        notices.push(format!(
+            "Could not load schedules from '{}', {kept}: {err}",
+            path.display()
+        ));
+        scheduler.unwrap_or_default()
+    })
+}
+
+/// Runs schedules and calendars from launch, they scan for lighthouses themselves when due
+fn run_scheduler(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        // The bluetooth adapter being unavailable is already reported
+        if let Ok(devices) = commands::device_list(&app).await {
+            let scheduler = app.state::<AppState>().scheduler.clone();
+            scheduler.run(&devices, &Aliases::new()).await;
+        }
+    });
+}
+
 // Async unaware mutex guard for devices is held across an await
 // But it's fine since the program is shutting down
 #[allow(clippy::await_holding_lock)]
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    let cli = startup::Cli::parse_or_default();
     Builder::default()
+        // Registered first so a second launch exits before setting anything else up
+        .plugin(tauri_plugin_single_instance::init(|app, argv, _cwd| {
+            startup::forward(app, argv);
+        }))
         .plugin(tauri_plugin_opener::init())
-        .setup(|app| {
-            app.manage(AppState::default());
+        .plugin(
+            tauri_plugin_autostart::Builder::new()
+                .arg(startup::AUTOSTART_FLAG)
+                .build(),
+        )
+        .plugin(
+            tauri_plugin_global_shortcut::Builder::new()
+                .with_handler(hotkeys::handle)
+                .build(),
+        )
+        .setup(move |app| {
+            let dir = app.path().app_config_dir()?;
+            let watcher = ConfigWatcher::new(config_path(Some(&dir)));
+            let mut notices = Vec::new();
+            let scheduler = load_scheduler(&dir.join(SCHEDULES_FILE), &mut notices);
+            app.manage(AppState::new(scheduler, watcher.load(), notices));
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                events::forward_config(handle, watcher.watch()).await;
+            });
+            run_scheduler(app.handle().clone());
+            tray::create(app.handle())?;
+            hotkeys::register(app.handle());
+            startup::sync_autostart(app.handle());
+            startup::run(app.handle(), cli);
             Ok(())
         })
-        .invoke_handler(generate_handler![commands::discover, commands::power])
+        // Closing the window keeps the app running in the tray
+        .on_window_event(|window, event| {
+            if let WindowEvent::CloseRequested { api, .. } = event {
+                api.prevent_close();
+                let _ = window.hide();
+            }
+        })
+        .invoke_handler(generate_handler![
+            commands::discover,
+            commands::power,
+            commands::power_targets,
+            commands::get_config,
+            commands::save_config,
+            commands::list_hotkeys,
+            commands::startup_notices,
+            commands::list_schedules,
+            commands::save_schedule,
+            commands::delete_schedule,
+            commands::next_schedule_runs,
+            commands::list_calendars,
+            commands::save_calendar,
+            commands::delete_calendar,
+            commands::calendar_sessions,
+            commands::list_groups,
+            commands::save_group,
+            commands::delete_group,
+        ])
         .build(generate_context!())
         .expect("Error occured while building application!")
         .run(|handle, event| {
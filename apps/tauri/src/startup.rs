@@ -0,0 +1,156 @@
+use clap::{ArgGroup, Parser};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_autostart::ManagerExt;
+use vrlh_power_manager_core::{DeviceCommand, DeviceSelector, StartupSettings};
+
+use crate::{
+    commands,
+    events::{EmitEvent, StatusPayload},
+    tray, AppState,
+};
+
+/// Passed by the login item so the startup settings apply
+pub const AUTOSTART_FLAG: &str = "--autostart";
+
+#[derive(Debug, Default, Parser)]
+#[allow(clippy::struct_excessive_bools)]
+#[command(
+    name = "vrlh-power-manager",
+    version,
+    about = "Manually power on and off your virtual reality lighthouses"
+)]
+#[command(group(ArgGroup::new("command").multiple(false)))]
+pub struct Cli {
+    /// Start hidden in the tray
+    #[arg(long)]
+    pub minimized: bool,
+    /// Scan for lighthouses right away
+    #[arg(long)]
+    pub scan: bool,
+    /// Activate every lighthouse once found
+    #[arg(long, group = "command")]
+    pub activate_all: bool,
+    /// Put every lighthouse in standby once found
+    #[arg(long, group = "command")]
+    pub standby_all: bool,
+    /// Put every lighthouse to sleep once found
+    #[arg(long, group = "command")]
+    pub sleep_all: bool,
+    /// Activate the lighthouses of a group once found
+    #[arg(long, group = "command", value_name = "GROUP")]
+    pub activate_group: Option<String>,
+    /// Exit once the command is done, without opening the window
+    #[arg(long, requires = "command")]
+    pub exit_when_done: bool,
+    /// Launched at login, applying the startup settings
+    #[arg(long, hide = true)]
+    pub autostart: bool,
+}
+
+impl Cli {
+    /// Parses the launch flags, starting with none instead of failing on invalid ones
+    pub fn parse_or_default() -> Self {
+        Self::try_parse().unwrap_or_else(|err| {
+            if !err.use_stderr() {
+                // Help and version requests print and exit as usual
+                err.exit();
+            }
+            eprintln!("Ignoring invalid launch flags: {err}");
+            Self::default()
+        })
+    }
+
+    /// Fills in the startup settings when launched at login, flags take precedence
+    #[must_use]
+    pub fn with_settings(mut self, settings: &StartupSettings) -> Self {
+        if !self.autostart {
+            return self;
+        }
+        self.minimized |= settings.minimized;
+        self.scan |= settings.scan;
+        if self.command().is_none() {
+            self.activate_group.clone_from(&settings.activate_group);
+        }
+        self
+    }
+
+    fn command(&self) -> Option<(DeviceCommand, DeviceSelector)> {
+        if let Some(group) = &self.activate_group {
+            return Some((DeviceCommand::Activate, DeviceSelector::new(group)));
+        }
+        let command = match (self.activate_all, self.standby_all, self.sleep_all) {
+            (true, _, _) => DeviceCommand::Activate,
+            (_, true, _) => DeviceCommand::Standby,
+            (_, _, true) => DeviceCommand::Sleep,
+            _ => return None,
+        };
+        Some((command, DeviceSelector::all()))
+    }
+}
+
+/// Shows the window unless asked not to, then scans and sends the requested command
+pub fn run(app: &AppHandle, cli: Cli) {
+    let cli = cli.with_settings(&app.state::<AppState>().settings().startup);
+    if !cli.minimized && !cli.exit_when_done {
+        tray::show_window(app);
+    }
+
+    let command = cli.command();
+    if !cli.scan && command.is_none() {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let result = async {
+            commands::discover(app.clone(), None).await?;
+            if let Some((command, target)) = command {
+                commands::power_targets(app.clone(), command, vec![target]).await?;
+            }
+            crate::Result::Ok(())
+        }
+        .await;
+        if let Err(err) = &result {
+            eprintln!("{err}");
+            let _ = app.emit_event(StatusPayload::from(err.to_string()));
+        }
+        if cli.exit_when_done {
+            app.exit(i32::from(result.is_err()));
+        }
+    });
+}
+
+/// Runs the flags of a second launch in this instance instead, without exiting once done
+pub fn forward(app: &AppHandle, argv: Vec<String>) {
+    let cli = match Cli::try_parse_from(argv) {
+        Ok(cli) => cli,
+        Err(err) => {
+            let _ = app.emit_event(StatusPayload::from(format!(
+                "Ignoring invalid launch flags: {err}"
+            )));
+            Cli::default()
+        }
+    };
+    let cli = Cli {
+        minimized: cli.minimized || cli.exit_when_done,
+        exit_when_done: false,
+        ..cli
+    };
+    run(app, cli);
+}
+
+/// Registers or removes the login item to match the settings
+pub fn sync_autostart(app: &AppHandle) {
+    let enabled = app.state::<AppState>().settings().startup.launch_at_login;
+    let autolaunch = app.autolaunch();
+    let result = match (enabled, autolaunch.is_enabled()) {
+        (_, Err(err)) => Err(err),
+        (true, Ok(false)) => autolaunch.enable(),
+        (false, Ok(true)) => autolaunch.disable(),
+        _ => Ok(()),
+    };
+    if let Err(err) = result {
+        let _ = app.emit_event(StatusPayload::from(format!(
+            "Could not update launch at login: {err}"
+        )));
+    }
+}
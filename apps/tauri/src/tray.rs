@@ -0,0 +1,292 @@
+use std::str::FromStr;
+
+use tauri::{
+    image::Image,
+    menu::{IsMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu},
+    tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
+    AppHandle, Manager, Wry,
+};
+use tokio::sync::broadcast::error::RecvError;
+use vrlh_power_manager_core::{
+    Device, DeviceCommand, DeviceList, DeviceListEvent, DeviceLocalStatus, DeviceRemoteStatus,
+    DeviceSelector,
+};
+
+use crate::{
+    commands,
+    events::{EmitEvent, StatusPayload},
+    AppState,
+};
+
+const TRAY_ID: &str = "main";
+const TITLE: &str = "VRLH Power Manager";
+const ICON_SIZE: u32 = 32;
+
+const RESCAN: &str = "rescan";
+const SHOW: &str = "show";
+const QUIT: &str = "quit";
+const COMMANDS: [(DeviceCommand, &str); 3] = [
+    (DeviceCommand::Activate, "Activate"),
+    (DeviceCommand::Standby, "Standby"),
+    (DeviceCommand::Sleep, "Sleep"),
+];
+
+/// Combined state of every lighthouse shown by the tray icon
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TrayStatus {
+    Unknown,
+    Active,
+    Mixed,
+    Off,
+    Error,
+}
+
+impl TrayStatus {
+    fn from_statuses(statuses: &[(DeviceLocalStatus, DeviceRemoteStatus)]) -> Self {
+        let statuses = statuses
+            .iter()
+            .filter(|(local, _)| *local != DeviceLocalStatus::Ignored)
+            .collect::<Vec<_>>();
+        let failed = statuses.iter().any(|(local, _)| {
+            matches!(
+                local,
+                DeviceLocalStatus::FailConnection
+                    | DeviceLocalStatus::FailVerify
+                    | DeviceLocalStatus::Error(_)
+            )
+        });
+        // Lighthouses that have not reported a mode yet do not count either way
+        let remotes = statuses
+            .iter()
+            .map(|(_, remote)| remote)
+            .filter(|remote| {
+                !matches!(
+                    remote,
+                    DeviceRemoteStatus::Unavailable | DeviceRemoteStatus::Unknown(_)
+                )
+            })
+            .collect::<Vec<_>>();
+        if failed {
+            Self::Error
+        } else if remotes.is_empty() {
+            Self::Unknown
+        } else if remotes
+            .iter()
+            .all(|remote| **remote == DeviceRemoteStatus::Active)
+        {
+            Self::Active
+        } else if remotes.iter().all(|remote| {
+            matches!(
+                remote,
+                DeviceRemoteStatus::Stopped | DeviceRemoteStatus::Standby
+            )
+        }) {
+            Self::Off
+        } else {
+            Self::Mixed
+        }
+    }
+
+    fn color(self) -> Option<[u8; 3]> {
+        match self {
+            Self::Unknown => None,
+            Self::Active => Some([0x22, 0xc5, 0x5e]),
+            Self::Mixed => Some([0xf5, 0x9e, 0x0b]),
+            Self::Off => Some([0x64, 0x74, 0x8b]),
+            Self::Error => Some([0xef, 0x44, 0x44]),
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Self::Unknown => "No lighthouses found",
+            Self::Active => "All lighthouses active",
+            Self::Mixed => "Some lighthouses active",
+            Self::Off => "All lighthouses off",
+            Self::Error => "Some lighthouses failed",
+        }
+    }
+}
+
+/// Adds the tray icon, which starts out with the app icon until lighthouses are found
+pub fn create(app: &AppHandle) -> crate::Result<()> {
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .menu(&menu(app)?)
+        .tooltip(TITLE)
+        .show_menu_on_left_click(false)
+        .on_menu_event(|app, event| handle_menu(app, event.id().as_ref()))
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                show_window(tray.app_handle());
+            }
+        });
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+    Ok(())
+}
+
+/// Rebuilds the menu so it lists the current groups
+pub fn refresh_menu(app: &AppHandle) -> crate::Result<()> {
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        tray.set_menu(Some(menu(app)?))?;
+    }
+    Ok(())
+}
+
+/// Keeps the tray icon in sync with the lighthouses of the list
+pub fn follow_devices(app: AppHandle, devices: &DeviceList) {
+    let mut events = devices.subscribe();
+    let devices = devices.clone();
+    tokio::spawn(async move {
+        update_icon(&app, &devices);
+        loop {
+            match events.recv().await {
+                Ok(DeviceListEvent::Device(_)) | Err(RecvError::Lagged(_)) => {
+                    update_icon(&app, &devices);
+                }
+                Ok(_) => {}
+                Err(RecvError::Closed) => return,
+            }
+        }
+    });
+}
+
+pub fn show_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+}
+
+fn update_icon(app: &AppHandle, devices: &DeviceList) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    let statuses = devices
+        .get_devices()
+        .iter()
+        .map(Device::get_last_statuses)
+        .collect::<Vec<_>>();
+    let status = TrayStatus::from_statuses(&statuses);
+    let icon = match status.color() {
+        Some(color) => Some(dot(color)),
+        None => app.default_window_icon().cloned(),
+    };
+    let _ = tray.set_icon(icon);
+    let _ = tray.set_tooltip(Some(format!("{TITLE}\n{}", status.describe())));
+}
+
+/// Filled circle of the color on a transparent background
+fn dot([r, g, b]: [u8; 3]) -> Image<'static> {
+    let size = i64::from(ICON_SIZE);
+    let rgba = (0..size * size)
+        .flat_map(|i| {
+            // Doubled distances from the center keep the math in integers
+            let (dx, dy) = (2 * (i % size) + 1 - size, 2 * (i / size) + 1 - size);
+            let alpha = match dx * dx + dy * dy <= (size - 2) * (size - 2) {
+                true => u8::MAX,
+                false => 0,
+            };
+            [r, g, b, alpha]
+        })
+        .collect();
+    Image::new_owned(rgba, ICON_SIZE, ICON_SIZE)
+}
+
+fn menu(app: &AppHandle) -> crate::Result<Menu<Wry>> {
+    let all = COMMANDS
+        .iter()
+        .map(|(command, label)| {
+            MenuItem::with_id(
+                app,
+                power_id(command, "*"),
+                format!("{label} all"),
+                true,
+                None::<&str>,
+            )
+        })
+        .collect::<tauri::Result<Vec<_>>>()?;
+
+    let mut groups = app
+        .state::<AppState>()
+        .scheduler
+        .groups()
+        .into_keys()
+        .collect::<Vec<_>>();
+    groups.sort();
+    let groups = groups
+        .iter()
+        .map(|group| {
+            let items = COMMANDS
+                .iter()
+                .map(|(command, label)| {
+                    MenuItem::with_id(app, power_id(command, group), label, true, None::<&str>)
+                })
+                .collect::<tauri::Result<Vec<_>>>()?;
+            Submenu::with_items(app, group, true, &as_items(&items))
+        })
+        .collect::<tauri::Result<Vec<_>>>()?;
+
+    let separator = PredefinedMenuItem::separator(app)?;
+    let groups_separator = PredefinedMenuItem::separator(app)?;
+    let rescan = MenuItem::with_id(app, RESCAN, "Rescan", true, None::<&str>)?;
+    let show = MenuItem::with_id(app, SHOW, "Show window", true, None::<&str>)?;
+    let quit = MenuItem::with_id(app, QUIT, "Quit", true, None::<&str>)?;
+
+    let mut items = as_items(&all);
+    if !groups.is_empty() {
+        items.push(&groups_separator);
+        items.extend(as_items(&groups));
+    }
+    items.extend([&separator as &dyn IsMenuItem<Wry>, &rescan, &show, &quit]);
+    Ok(Menu::with_items(app, &items)?)
+}
+
+fn as_items<I: IsMenuItem<Wry>>(items: &[I]) -> Vec<&dyn IsMenuItem<Wry>> {
+    items
+        .iter()
+        .map(|item| item as &dyn IsMenuItem<Wry>)
+        .collect()
+}
+
+fn power_id(command: &DeviceCommand, target: &str) -> String {
+    format!("power:{command}:{target}")
+}
+
+fn handle_menu(app: &AppHandle, id: &str) {
+    match id {
+        RESCAN => {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(err) = commands::discover(app.clone(), None).await {
+                    let _ = app.emit_event(StatusPayload::from(err.to_string()));
+                }
+            });
+        }
+        SHOW => show_window(app),
+        QUIT => app.exit(0),
+        id => {
+            let Some((command, target)) = id
+                .strip_prefix("power:")
+                .and_then(|rest| rest.split_once(':'))
+            else {
+                return;
+            };
+            let (Ok(command), Ok(target)) = (
+                DeviceCommand::from_str(command),
+                DeviceSelector::from_str(target),
+            ) else {
+                return;
+            };
+            commands::spawn_power_targets(app, command, vec![target]);
+        }
+    }
+}
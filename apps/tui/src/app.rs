@@ -0,0 +1,236 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use tokio::sync::mpsc::{Receiver, Sender};
+use vrlh_power_manager_core::{
+    Device, DeviceCommand, DeviceInfo, DeviceList, DeviceLocalStatus, LoadedConfig,
+};
+
+const LOG_CAPACITY: usize = 500;
+
+#[derive(Debug)]
+pub enum AppEvent {
+    Device(DeviceInfo),
+    Status(String),
+    ScanFinished,
+    Config(LoadedConfig),
+}
+
+pub struct LogLine {
+    pub elapsed: Duration,
+    pub message: String,
+}
+
+pub struct App {
+    devices: DeviceList,
+    /// Scan duration overriding the configured one
+    duration: Option<u64>,
+    tx: Sender<AppEvent>,
+    rows: BTreeMap<String, DeviceInfo>,
+    started: Instant,
+    scan_started: Option<(Instant, u64)>,
+    pub selected: usize,
+    pub log: VecDeque<LogLine>,
+    /// Lines scrolled up from the newest log entry
+    pub log_offset: usize,
+    pub quit: bool,
+}
+
+impl App {
+    pub fn new(devices: DeviceList, duration: Option<u64>, tx: Sender<AppEvent>) -> Self {
+        Self {
+            devices,
+            duration,
+            tx,
+            rows: BTreeMap::new(),
+            started: Instant::now(),
+            scan_started: None,
+            selected: 0,
+            log: VecDeque::new(),
+            log_offset: 0,
+            quit: false,
+        }
+    }
+
+    /// Devices sorted by name, excluding anything that is not a lighthouse
+    pub fn rows(&self) -> Vec<&DeviceInfo> {
+        let mut rows = self.rows.values().collect::<Vec<_>>();
+        rows.sort_by(|a, b| a.name.cmp(&b.name));
+        rows
+    }
+
+    /// Fraction of the scan duration that has elapsed, or `None` when not scanning
+    pub fn scan_progress(&self) -> Option<f64> {
+        let (started, duration) = self.scan_started?;
+        let total = Duration::from_secs(duration).as_secs_f64();
+        Some((started.elapsed().as_secs_f64() / total).min(1.0))
+    }
+
+    pub fn start_scan(&mut self) {
+        if self.scan_started.is_some() {
+            return;
+        }
+        let duration = self
+            .duration
+            .unwrap_or_else(|| self.devices.config().scan.duration_secs);
+        match self.devices.start_scan(duration) {
+            Ok(rx) => {
+                self.scan_started = Some((Instant::now(), duration));
+                self.push_log("Scanning for lighthouses...".into());
+                forward(rx, self.tx.clone(), AppEvent::ScanFinished);
+            }
+            Err(err) => self.push_log(format!("Could not start scan: {err}")),
+        }
+    }
+
+    pub fn handle_event(&mut self, event: AppEvent) {
+        match event {
+            AppEvent::Device(info) => self.update_device(info),
+            AppEvent::Status(message) => self.push_log(message),
+            AppEvent::ScanFinished => {
+                self.scan_started = None;
+                self.push_log("Done scanning for devices!".into());
+            }
+            AppEvent::Config(loaded) => {
+                let path = loaded.path.display();
+                match loaded.error {
+                    Some(err) => self.push_log(format!(
+                        "Keeping the previous configuration, '{path}' is invalid: {err}"
+                    )),
+                    None => self.push_log(format!("Reloaded configuration from '{path}'")),
+                }
+                self.devices.set_config(loaded.config);
+            }
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        if key.kind != KeyEventKind::Press {
+            return;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => self.quit = true,
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1).min(self.rows.len().saturating_sub(1));
+            }
+            KeyCode::PageUp => {
+                self.log_offset = (self.log_offset + 5).min(self.log.len().saturating_sub(1));
+            }
+            KeyCode::PageDown => self.log_offset = self.log_offset.saturating_sub(5),
+            KeyCode::Char('r') => self.start_scan(),
+            KeyCode::Char('i') => self.identify(),
+            KeyCode::Char('a') => self.power(&DeviceCommand::Activate, false),
+            KeyCode::Char('s') => self.power(&DeviceCommand::Standby, false),
+            KeyCode::Char('o') => self.power(&DeviceCommand::Sleep, false),
+            KeyCode::Char('A') => self.power(&DeviceCommand::Activate, true),
+            KeyCode::Char('S') => self.power(&DeviceCommand::Standby, true),
+            KeyCode::Char('O') => self.power(&DeviceCommand::Sleep, true),
+            _ => {}
+        }
+    }
+
+    fn power(&mut self, command: &DeviceCommand, all: bool) {
+        let targets = match all {
+            true => self.devices.get_devices(),
+            false => self.selected_device().into_iter().collect(),
+        };
+        if targets.is_empty() {
+            self.push_log("No lighthouses to send command to!".into());
+            return;
+        }
+        let names = describe_targets(&targets);
+        self.push_log(format!(r#"Sending "{command}" command to {names}"#));
+        let rx = self.devices.start_power(targets, command);
+        let done = AppEvent::Status(format!(r#"Finished "{command}" for {names}"#));
+        forward(rx, self.tx.clone(), done);
+    }
+
+    fn identify(&mut self) {
+        let Some(device) = self.selected_device() else {
+            return;
+        };
+        let name = device.name().to_string();
+        self.push_log(format!(r#"Identifying "{name}""#));
+        let rx = self.devices.start_identify(vec![device]);
+        forward(
+            rx,
+            self.tx.clone(),
+            AppEvent::Status(format!(r#"Identified "{name}""#)),
+        );
+    }
+
+    fn selected_device(&self) -> Option<Device> {
+        let addr = &self.rows().get(self.selected)?.addr;
+        self.devices
+            .get_devices()
+            .into_iter()
+            .find(|device| &device.address() == addr)
+    }
+
+    fn update_device(&mut self, info: DeviceInfo) {
+        if matches!(info.local, Some(DeviceLocalStatus::Ignored)) {
+            return;
+        }
+        let mut parts = Vec::new();
+        if let Some(local) = &info.local {
+            parts.push(format!("local {local}"));
+        }
+        if let Some(remote) = &info.remote {
+            parts.push(format!("remote {remote}"));
+        }
+        parts.extend(
+            info.warnings
+                .iter()
+                .map(|warning| format!("warning {warning}")),
+        );
+        self.push_log(format!("{}: {}", info.name, parts.join(", ")));
+
+        // Updates only carry the statuses that changed, so merge them into the existing row
+        let row = self
+            .rows
+            .entry(info.addr.clone())
+            .or_insert_with(|| info.clone());
+        row.local = info.local.or(row.local.take());
+        row.remote = info.remote.or(row.remote.take());
+        if !info.warnings.is_empty() {
+            row.warnings = info.warnings;
+        }
+    }
+
+    fn push_log(&mut self, message: String) {
+        self.log.push_back(LogLine {
+            elapsed: self.started.elapsed(),
+            message,
+        });
+        if self.log.len() > LOG_CAPACITY {
+            self.log.pop_front();
+        }
+        // Keep the same lines in view while scrolled back
+        if self.log_offset > 0 {
+            self.log_offset = (self.log_offset + 1).min(self.log.len().saturating_sub(1));
+        }
+    }
+}
+
+fn forward(mut rx: Receiver<DeviceInfo>, tx: Sender<AppEvent>, done: AppEvent) {
+    tokio::spawn(async move {
+        while let Some(info) = rx.recv().await {
+            if tx.send(AppEvent::Device(info)).await.is_err() {
+                return;
+            }
+        }
+        let _ = tx.send(done).await;
+    });
+}
+
+fn describe_targets(targets: &[Device]) -> String {
+    match targets {
+        [device] => format!(r#""{}""#, device.name()),
+        _ => format!("{} lighthouses", targets.len()),
+    }
+}
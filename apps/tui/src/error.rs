@@ -0,0 +1,13 @@
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("{}", .0)]
+    VrlhTui(String),
+    #[error("{}", .0)]
+    VrlhCore(#[from] vrlh_power_manager_core::Error),
+    #[error("{}", .0)]
+    Io(#[from] std::io::Error),
+}
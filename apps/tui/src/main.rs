@@ -0,0 +1,84 @@
+mod app;
+mod error;
+mod ui;
+
+use std::{path::PathBuf, process::ExitCode, time::Duration};
+
+use clap::Parser;
+use crossterm::event::{Event, EventStream};
+use futures::StreamExt;
+use tokio::{sync::mpsc::channel, time::interval};
+use vrlh_power_manager_core::{config_path, ConfigWatcher, DeviceList};
+
+pub use error::*;
+
+use crate::app::{App, AppEvent};
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "vrlh-tui",
+    version,
+    about = "Terminal dashboard for your virtual reality lighthouses"
+)]
+struct Cli {
+    /// Seconds to scan for lighthouses, instead of the configured duration
+    #[arg(short, long)]
+    duration: Option<u64>,
+    /// Configuration file, see the README for where it is looked up otherwise
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> crate::Result<()> {
+    // Initialize before taking over the terminal so adapter errors stay readable
+    let mut config = ConfigWatcher::new(cli.config.unwrap_or_else(|| config_path(None))).watch();
+    let loaded = config.borrow_and_update().clone();
+    if let Some(err) = loaded.error {
+        return Err(crate::Error::VrlhTui(err));
+    }
+    let devices = DeviceList::init().await?;
+    devices.set_config(loaded.config);
+    let (tx, mut rx) = channel(16);
+    let mut app = App::new(devices, cli.duration, tx);
+
+    let mut terminal = ratatui::init();
+    let mut events = EventStream::new();
+    let mut tick = interval(Duration::from_millis(250));
+    app.start_scan();
+
+    let result = loop {
+        if let Err(err) = terminal.draw(|frame| ui::render(frame, &app)) {
+            break Err(err.into());
+        }
+        tokio::select! {
+            Some(event) = events.next() => match event {
+                Ok(Event::Key(key)) => app.handle_key(key),
+                Ok(_) => {}
+                Err(err) => break Err(err.into()),
+            },
+            Some(event) = rx.recv() => app.handle_event(event),
+            Ok(()) = config.changed() => {
+                app.handle_event(AppEvent::Config(config.borrow_and_update().clone()));
+            }
+            _ = tick.tick() => {}
+        }
+        if app.quit {
+            break Ok(());
+        }
+    };
+
+    ratatui::restore();
+    result
+}
@@ -0,0 +1,130 @@
+use ratatui::{
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Borders, Cell, Gauge, Paragraph, Row, Table, TableState},
+    Frame,
+};
+use vrlh_power_manager_core::{DeviceLocalStatus, DeviceRemoteStatus};
+
+use crate::app::App;
+
+const HELP: &str =
+    "↑↓ select  a/s/o activate/standby/off  A/S/O all  i identify  r rescan  PgUp/PgDn log  q quit";
+
+pub fn render(frame: &mut Frame, app: &App) {
+    let [title, table, gauge, log, help] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(5),
+        Constraint::Length(1),
+        Constraint::Length(10),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    frame.render_widget(Line::from("VRLH Power Manager").bold().centered(), title);
+    render_table(frame, app, table);
+    render_gauge(frame, app, gauge);
+    render_log(frame, app, log);
+    frame.render_widget(Line::from(HELP).dim(), help);
+}
+
+fn render_table(frame: &mut Frame, app: &App, area: ratatui::layout::Rect) {
+    let rows = app.rows().into_iter().map(|info| {
+        let remote = info
+            .remote
+            .as_ref()
+            .unwrap_or(&DeviceRemoteStatus::Unavailable);
+        let local = info
+            .local
+            .as_ref()
+            .map(ToString::to_string)
+            .unwrap_or_default();
+        Row::new([
+            Cell::from(info.name.clone()),
+            Cell::from(info.addr.clone()),
+            Cell::from(remote.to_string()).style(remote_style(remote)),
+            Cell::from(local).style(local_style(info.local.as_ref())),
+            Cell::from(match info.warnings.len() {
+                0 => String::new(),
+                count => format!("⚠ {count}"),
+            })
+            .fg(Color::Yellow),
+        ])
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(14),
+            Constraint::Length(17),
+            Constraint::Length(14),
+            Constraint::Min(14),
+            Constraint::Length(4),
+        ],
+    )
+    .header(Row::new(["NAME", "ADDRESS", "REMOTE", "LOCAL", ""]).bold())
+    .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+    .block(Block::new().borders(Borders::ALL).title(" Lighthouses "));
+    let mut state = TableState::new().with_selected(Some(app.selected));
+    frame.render_stateful_widget(table, area, &mut state);
+}
+
+fn render_gauge(frame: &mut Frame, app: &App, area: ratatui::layout::Rect) {
+    let gauge = match app.scan_progress() {
+        Some(ratio) => Gauge::default()
+            .ratio(ratio)
+            .label("Scanning...")
+            .gauge_style(Style::new().fg(Color::Blue)),
+        None => Gauge::default()
+            .ratio(0.0)
+            .label("Idle, press r to rescan")
+            .gauge_style(Style::new().fg(Color::DarkGray)),
+    };
+    frame.render_widget(gauge, area);
+}
+
+fn render_log(frame: &mut Frame, app: &App, area: ratatui::layout::Rect) {
+    let height = usize::from(area.height.saturating_sub(2));
+    let end = app.log.len().saturating_sub(app.log_offset);
+    let start = end.saturating_sub(height);
+    let lines = app
+        .log
+        .range(start..end)
+        .map(|line| {
+            Line::from(vec![
+                Span::from(format!("{:>8.1}s ", line.elapsed.as_secs_f64())).dim(),
+                Span::from(line.message.clone()),
+            ])
+        })
+        .collect::<Vec<_>>();
+    let title = match app.log_offset {
+        0 => " Log ".to_string(),
+        offset => format!(" Log (-{offset}) "),
+    };
+    let paragraph = Paragraph::new(lines).block(Block::new().borders(Borders::ALL).title(title));
+    frame.render_widget(paragraph, area);
+}
+
+fn remote_style(status: &DeviceRemoteStatus) -> Style {
+    match status {
+        DeviceRemoteStatus::Active => Style::new().fg(Color::Green),
+        DeviceRemoteStatus::Standby => Style::new().fg(Color::Cyan),
+        DeviceRemoteStatus::Stopped => Style::new().fg(Color::Gray),
+        DeviceRemoteStatus::Unknown(_) => Style::new().fg(Color::Red),
+        DeviceRemoteStatus::Unavailable => Style::new().dim(),
+        _ => Style::new().fg(Color::Yellow),
+    }
+}
+
+fn local_style(status: Option<&DeviceLocalStatus>) -> Style {
+    match status {
+        Some(DeviceLocalStatus::Initializing) => Style::new().fg(Color::Yellow),
+        Some(DeviceLocalStatus::Connected) => Style::new().fg(Color::Blue),
+        Some(
+            DeviceLocalStatus::FailConnection
+            | DeviceLocalStatus::FailVerify
+            | DeviceLocalStatus::Error(_),
+        ) => Style::new().fg(Color::Red),
+        _ => Style::new().dim(),
+    }
+}
@@ -0,0 +1,27 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DeviceCommand } from "./DeviceCommand";
+
+/**
+ * Powers lighthouses for the sessions booked in an iCalendar file
+ */
+export type CalendarConfig = { id: string, 
+/**
+ * `.ics` file, or a directory whose `.ics` files are all read
+ */
+path: string, 
+/**
+ * Only sessions whose location contains one of these, ignoring case
+ */
+locations: Array<string>, 
+/**
+ * Only sessions with one of these categories, ignoring case
+ */
+categories: Array<string>, 
+/**
+ * Names, addresses, aliases or groups
+ */
+targets: Array<string>, 
+/**
+ * Minutes before a session starts to send `on_start`
+ */
+lead_mins: number, on_start: DeviceCommand, on_end: DeviceCommand, enabled: boolean, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Single booked session read from a calendar
+ */
+export type CalendarEvent = { uid: string, summary: string, location: string | null, categories: Array<string>, start: string, end: string, };
@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { HotkeyConfig } from "./HotkeyConfig";
+import type { PowerSettings } from "./PowerSettings";
+import type { ScanSettings } from "./ScanSettings";
+import type { StartupSettings } from "./StartupSettings";
+import type { TimeoutSettings } from "./TimeoutSettings";
+
+/**
+ * Settings shared by the core and every frontend, kept in `config.toml`
+ */
+export type Config = { scan: ScanSettings, timeouts: TimeoutSettings, power: PowerSettings, startup: StartupSettings, 
+/**
+ * Global shortcuts of the GUI
+ */
+hotkeys: Array<HotkeyConfig>, };
@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DeviceLocalStatus } from "./DeviceLocalStatus";
+import type { DeviceRemoteStatus } from "./DeviceRemoteStatus";
+import type { DeviceWarning } from "./DeviceWarning";
+
+export type DeviceInfo = { 
+/**
+ * Serializes differently per platform
+ */
+id: unknown, addr: string, name: string, local: DeviceLocalStatus | null, remote: DeviceRemoteStatus | null, warnings: Array<DeviceWarning>, };
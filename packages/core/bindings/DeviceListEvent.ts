@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DeviceInfo } from "./DeviceInfo";
+
+/**
+ * Everything observable about a device list, broadcast to all subscribers
+ */
+export type DeviceListEvent = { "type": "Device", "payload": DeviceInfo } | { "type": "Status", "payload": string } | { "type": "ScanStarted", "payload": { duration: number, } } | { "type": "ScanFinished" };
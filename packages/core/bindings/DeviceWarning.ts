@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DeviceCommand } from "./DeviceCommand";
+import type { DeviceRemoteStatus } from "./DeviceRemoteStatus";
+
+export type DeviceWarning = { "UnexpectedTransition": { command: DeviceCommand | null, from: DeviceRemoteStatus, to: DeviceRemoteStatus, } } | { "Stuck": { command: DeviceCommand | null, status: DeviceRemoteStatus, elapsed_secs: number, } };
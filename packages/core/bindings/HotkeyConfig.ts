@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DeviceCommand } from "./DeviceCommand";
+
+/**
+ * Key combination sending a command to every lighthouse or a group
+ */
+export type HotkeyConfig = { 
+/**
+ * e.g. `Ctrl+Alt+F9`
+ */
+keys: string, command: DeviceCommand, 
+/**
+ * Group the command is sent to, every lighthouse if missing
+ */
+group?: string, };
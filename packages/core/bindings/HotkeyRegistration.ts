@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { HotkeyConfig } from "./HotkeyConfig";
+
+/**
+ * Outcome of registering a hotkey, which fails if the keys are taken or invalid
+ */
+export type HotkeyRegistration = { hotkey: HotkeyConfig, error: string | null, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DeviceInfo } from "./DeviceInfo";
+
+/**
+ * Higher level events derived from a device list, see `LifecycleTracker`
+ */
+export type LifecycleEvent = { "type": "ScanFinished", devices: Array<DeviceInfo>, } | { "type": "DeviceChanged", device: DeviceInfo, } | { "type": "DeviceFailed", device: DeviceInfo, reason: string, } | { "type": "AllActive", devices: Array<DeviceInfo>, } | { "type": "AllOff", devices: Array<DeviceInfo>, };
@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Config } from "./Config";
+
+/**
+ * Configuration in effect along with where it came from
+ */
+export type LoadedConfig = { config: Config, path: string, 
+/**
+ * Why the file could not be used, the previous or default configuration stays in effect
+ */
+error: string | null, };
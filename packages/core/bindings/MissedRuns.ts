@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * What happens to runs that were due while the computer was off or asleep
+ */
+export type MissedRuns = "Skip" | "RunLatest";
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DeviceCommand } from "./DeviceCommand";
+
+export type PowerSettings = { 
+/**
+ * Sent when lighthouses are powered off without naming a command
+ */
+off_command: DeviceCommand, };
@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type ScanSettings = { 
+/**
+ * Seconds a scan runs when the frontend does not ask for a duration
+ */
+duration_secs: number, 
+/**
+ * Devices whose bluetooth name does not start with this are ignored
+ */
+name_prefix: string, };
@@ -0,0 +1,24 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DeviceCommand } from "./DeviceCommand";
+import type { MissedRuns } from "./MissedRuns";
+
+/**
+ * Sends a command to lighthouses whenever a cron expression matches
+ */
+export type Schedule = { id: string, name: string, 
+/**
+ * Standard five field expression, e.g. `50 9 * * MON-FRI`
+ */
+cron: string, 
+/**
+ * IANA name such as `Europe/Berlin`, the system timezone when absent
+ */
+timezone?: string, command: DeviceCommand, 
+/**
+ * Names, addresses, aliases or groups
+ */
+targets: Array<string>, 
+/**
+ * Dates in the schedule's timezone on which it does not run, e.g. holidays
+ */
+exceptions: Array<string>, missed: MissedRuns, enabled: boolean, };
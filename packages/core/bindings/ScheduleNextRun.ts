@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Upcoming run of a schedule, `None` when disabled or never matching again
+ */
+export type ScheduleNextRun = { id: string, next: string | null, };
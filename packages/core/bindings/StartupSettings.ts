@@ -0,0 +1,22 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * What the GUI does when it is launched at login
+ */
+export type StartupSettings = { 
+/**
+ * Registers the GUI to be launched at login
+ */
+launch_at_login: boolean, 
+/**
+ * Stay hidden in the tray instead of opening the window
+ */
+minimized: boolean, 
+/**
+ * Scan for lighthouses right away
+ */
+scan: boolean, 
+/**
+ * Group activated once lighthouses are found
+ */
+activate_group?: string, };
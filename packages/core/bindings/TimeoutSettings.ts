@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type TimeoutSettings = { 
+/**
+ * Seconds to wait for lighthouses to reach the requested state
+ */
+command_secs: number, 
+/**
+ * Seconds a lighthouse may stay `Initiated` or `Acknowledged` before it is stuck
+ */
+transition_secs: number, 
+/**
+ * Seconds a lighthouse may stay in `Spinup` before it is stuck
+ */
+spinup_secs: number, };
@@ -1,3 +1,21 @@
 export * from "./bindings/DeviceLocalStatus";
 export * from "./bindings/DeviceRemoteStatus";
 export * from "./bindings/DeviceInfo";
+export * from "./bindings/DeviceCommand";
+export * from "./bindings/DeviceWarning";
+export * from "./bindings/DeviceListEvent";
+export * from "./bindings/LifecycleEventKind";
+export * from "./bindings/LifecycleEvent";
+export * from "./bindings/CalendarConfig";
+export * from "./bindings/CalendarEvent";
+export * from "./bindings/MissedRuns";
+export * from "./bindings/Schedule";
+export * from "./bindings/ScheduleNextRun";
+export * from "./bindings/Config";
+export * from "./bindings/ScanSettings";
+export * from "./bindings/TimeoutSettings";
+export * from "./bindings/PowerSettings";
+export * from "./bindings/StartupSettings";
+export * from "./bindings/HotkeyConfig";
+export * from "./bindings/HotkeyRegistration";
+export * from "./bindings/LoadedConfig";
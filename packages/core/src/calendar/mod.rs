@@ -0,0 +1,352 @@
+use std::{
+    fs,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeDelta, TimeZone, Utc};
+use chrono_tz::Tz;
+use ical::{parser::ical::component::IcalEvent, property::Property, IcalParser};
+
+use crate::{schedule::local_zone, CalendarConfig, CalendarEvent};
+
+const DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%S";
+
+/// Reads every event of an iCalendar stream, skipping cancelled and unreadable ones
+///
+/// Recurrence rules are not expanded, booking systems export every session on its own
+pub fn parse_calendar(reader: impl BufRead) -> crate::Result<Vec<CalendarEvent>> {
+    let mut events = Vec::new();
+    for calendar in IcalParser::new(reader) {
+        let calendar = calendar.map_err(|err| crate::Error::InvalidCalendar(err.to_string()))?;
+        events.extend(calendar.events.iter().filter_map(parse_event));
+    }
+    Ok(events)
+}
+
+/// Reads a calendar file, or every `.ics` file of a directory
+pub fn load_calendar(path: &Path) -> crate::Result<Vec<CalendarEvent>> {
+    let mut events = Vec::new();
+    for file in calendar_files(path)? {
+        events.extend(parse_calendar(BufReader::new(fs::File::open(file)?))?);
+    }
+    Ok(events)
+}
+
+/// Latest modification of the calendar or the directory holding it, to notice changes
+pub fn calendar_modified(path: &Path) -> Option<SystemTime> {
+    let own = fs::metadata(path).ok()?.modified().ok()?;
+    calendar_files(path)
+        .ok()?
+        .iter()
+        .filter_map(|file| fs::metadata(file).ok()?.modified().ok())
+        .chain([own])
+        .max()
+}
+
+fn calendar_files(path: &Path) -> crate::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut files = fs::read_dir(path)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|file| {
+            file.extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("ics"))
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+    Ok(files)
+}
+
+impl CalendarConfig {
+    pub fn accepts(&self, event: &CalendarEvent) -> bool {
+        let location = event.location.as_deref().unwrap_or_default().to_lowercase();
+        let location_matches = self.locations.is_empty()
+            || self
+                .locations
+                .iter()
+                .any(|wanted| location.contains(&wanted.to_lowercase()));
+        let category_matches = self.categories.is_empty()
+            || self.categories.iter().any(|wanted| {
+                event
+                    .categories
+                    .iter()
+                    .any(|category| category.eq_ignore_ascii_case(wanted))
+            });
+        location_matches && category_matches
+    }
+
+    /// When lighthouses are woken for a session, `None` when the lead time is out of range
+    pub fn wake_at(&self, event: &CalendarEvent) -> Option<DateTime<Utc>> {
+        let lead = TimeDelta::try_minutes(i64::try_from(self.lead_mins).ok()?)?;
+        event.start.checked_sub_signed(lead)
+    }
+
+    /// Accepted session lighthouses should be powered for at the given time
+    pub fn active_session<'a>(
+        &self,
+        events: &'a [CalendarEvent],
+        time: DateTime<Utc>,
+    ) -> Option<&'a CalendarEvent> {
+        events
+            .iter()
+            .filter(|event| self.accepts(event))
+            .find(|event| {
+                self.wake_at(event)
+                    .is_some_and(|wake| wake <= time && time < event.end)
+            })
+    }
+
+    /// Next time an accepted session is woken for or ends
+    pub fn next_change(
+        &self,
+        events: &[CalendarEvent],
+        after: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        events
+            .iter()
+            .filter(|event| self.accepts(event))
+            .filter_map(|event| Some([self.wake_at(event)?, event.end]))
+            .flatten()
+            .filter(|time| *time > after)
+            .min()
+    }
+}
+
+fn parse_event(event: &IcalEvent) -> Option<CalendarEvent> {
+    let property = |name: &str| {
+        event
+            .properties
+            .iter()
+            .find(|property| property.name == name)
+    };
+    let text = |name: &str| {
+        property(name)
+            .and_then(|property| property.value.as_deref())
+            .map(unescape)
+    };
+
+    if text("STATUS").is_some_and(|status| status.eq_ignore_ascii_case("CANCELLED")) {
+        return None;
+    }
+    let (start, all_day) = parse_time(property("DTSTART")?)?;
+    let end = if let Some((end, _)) = property("DTEND").and_then(parse_time) {
+        end
+    } else {
+        let duration = match text("DURATION") {
+            // Durations out of range drop the event rather than guessing its end
+            Some(duration) => parse_duration(&duration)?,
+            None => match all_day {
+                true => TimeDelta::days(1),
+                false => TimeDelta::zero(),
+            },
+        };
+        start.checked_add_signed(duration)?
+    };
+    let categories = event
+        .properties
+        .iter()
+        .filter(|property| property.name == "CATEGORIES")
+        .filter_map(|property| property.value.as_deref())
+        .flat_map(split_list)
+        .collect();
+
+    Some(CalendarEvent {
+        uid: text("UID").unwrap_or_default(),
+        summary: text("SUMMARY").unwrap_or_default(),
+        location: text("LOCATION"),
+        categories,
+        start,
+        end,
+    })
+}
+
+/// Returns the time in UTC and whether it was a date without a time
+fn parse_time(property: &Property) -> Option<(DateTime<Utc>, bool)> {
+    let value = property.value.as_deref()?.trim();
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y%m%d") {
+        return Some((in_zone(date.and_hms_opt(0, 0, 0)?, local_zone())?, true));
+    }
+    if let Some(utc) = value.strip_suffix('Z') {
+        let time = NaiveDateTime::parse_from_str(utc, DATE_TIME_FORMAT).ok()?;
+        return Some((time.and_utc(), false));
+    }
+    let time = NaiveDateTime::parse_from_str(value, DATE_TIME_FORMAT).ok()?;
+    // Unknown zones are usually Windows names, the local zone is the best guess for those
+    let zone = property
+        .params
+        .iter()
+        .flatten()
+        .find(|(name, _)| name.eq_ignore_ascii_case("TZID"))
+        .and_then(|(_, values)| values.first())
+        .and_then(|zone| zone.trim_matches('"').parse::<Tz>().ok())
+        .unwrap_or_else(local_zone);
+    Some((in_zone(time, zone)?, false))
+}
+
+fn in_zone(time: NaiveDateTime, zone: Tz) -> Option<DateTime<Utc>> {
+    zone.from_local_datetime(&time)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+/// Parses durations such as `PT1H30M` or `P1D`, `None` when invalid or out of range
+fn parse_duration(value: &str) -> Option<TimeDelta> {
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(value) => (true, value),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let mut total = TimeDelta::zero();
+    let mut number = String::new();
+    for char in value.strip_prefix('P')?.chars() {
+        match char {
+            '0'..='9' => number.push(char),
+            'T' => {}
+            unit => {
+                let amount = number.parse().ok()?;
+                number.clear();
+                let delta = match unit {
+                    'W' => TimeDelta::try_weeks(amount),
+                    'D' => TimeDelta::try_days(amount),
+                    'H' => TimeDelta::try_hours(amount),
+                    'M' => TimeDelta::try_minutes(amount),
+                    'S' => TimeDelta::try_seconds(amount),
+                    _ => return None,
+                };
+                total = total.checked_add(&delta?)?;
+            }
+        }
+    }
+    Some(if negative { -total } else { total })
+}
+
+/// Splits a comma separated text value, keeping escaped commas
+fn split_list(value: &str) -> Vec<String> {
+    let mut items = vec![String::new()];
+    let mut chars = value.chars();
+    while let Some(char) = chars.next() {
+        match char {
+            '\\' => {
+                let current = items.last_mut().expect("Items must never be empty");
+                current.push(char);
+                current.extend(chars.next());
+            }
+            ',' => items.push(String::new()),
+            _ => items
+                .last_mut()
+                .expect("Items must never be empty")
+                .push(char),
+        }
+    }
+    items
+        .iter()
+        .map(|item| unescape(item.trim()))
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+fn unescape(value: &str) -> String {
+    let mut text = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(char) = chars.next() {
+        match char {
+            '\\' => match chars.next() {
+                Some('n' | 'N') => text.push('\n'),
+                Some(escaped) => text.push(escaped),
+                None => {}
+            },
+            _ => text.push(char),
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calendar(events: &str) -> Vec<CalendarEvent> {
+        let ics = format!("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n{events}END:VCALENDAR\r\n");
+        parse_calendar(ics.as_bytes()).unwrap()
+    }
+
+    fn event(uid: &str, lines: &str) -> String {
+        format!("BEGIN:VEVENT\r\nUID:{uid}\r\nDTSTART:20260302T090000Z\r\n{lines}END:VEVENT\r\n")
+    }
+
+    fn config(lead_mins: u64) -> CalendarConfig {
+        serde_json::from_value(serde_json::json!({
+            "id": "test",
+            "path": "bookings.ics",
+            "lead_mins": lead_mins,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("PT1H30M"), Some(TimeDelta::minutes(90)));
+        assert_eq!(parse_duration("P1W2D"), Some(TimeDelta::days(9)));
+        assert_eq!(parse_duration("-PT15S"), Some(TimeDelta::seconds(-15)));
+        assert_eq!(parse_duration("PT"), Some(TimeDelta::zero()));
+        assert_eq!(parse_duration("1H"), None);
+        assert_eq!(parse_duration("PT1X"), None);
+    }
+
+    #[test]
+    fn rejects_durations_out_of_range() {
+        assert_eq!(parse_duration("P9223372036854775807W"), None);
+        assert_eq!(parse_duration("P15250000000000D"), None);
+        assert_eq!(parse_duration("PT9223372036854775807S"), None);
+        assert_eq!(parse_duration("P10000000000000DT10000000000000D"), None);
+    }
+
+    #[test]
+    fn reads_events_and_skips_unusable_ones() {
+        let events = calendar(
+            &[
+                event("dtend", "DTEND:20260302T100000Z\r\nLOCATION:Room 2\r\n"),
+                event("duration", "DURATION:PT45M\r\nCATEGORIES:VR,Booked\r\n"),
+                event(
+                    "cancelled",
+                    "DTEND:20260302T100000Z\r\nSTATUS:CANCELLED\r\n",
+                ),
+                event("overflow", "DURATION:P9999999999999D\r\n"),
+            ]
+            .concat(),
+        );
+        let uids = events
+            .iter()
+            .map(|event| event.uid.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(uids, ["dtend", "duration"]);
+        assert_eq!(events[1].end - events[1].start, TimeDelta::minutes(45));
+        assert_eq!(events[1].categories, ["VR", "Booked"]);
+    }
+
+    #[test]
+    fn wakes_lighthouses_ahead_of_sessions() {
+        let events = calendar(&event("session", "DURATION:PT1H\r\n"));
+        let config = config(10);
+        let wake = config.wake_at(&events[0]).unwrap();
+        assert_eq!(
+            wake,
+            "2026-03-02T08:50:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert!(config.active_session(&events, wake).is_some());
+        assert!(config.active_session(&events, events[0].end).is_none());
+        assert_eq!(config.next_change(&events, wake), Some(events[0].end));
+    }
+
+    #[test]
+    fn ignores_sessions_with_a_lead_time_out_of_range() {
+        let events = calendar(&event("session", "DURATION:PT1H\r\n"));
+        let config = config(u64::MAX);
+        assert_eq!(config.wake_at(&events[0]), None);
+        assert!(config.active_session(&events, events[0].start).is_none());
+        assert_eq!(config.next_change(&events, events[0].start), None);
+    }
+}
@@ -0,0 +1,411 @@
+use std::{
+    collections::HashSet,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use tokio::{sync::watch, time::interval};
+
+use crate::{Config, LoadedConfig};
+
+pub const CONFIG_FILE: &str = "config.toml";
+/// Environment variable pointing at the configuration file, taking precedence over the lookup
+pub const CONFIG_ENV: &str = "VRLH_CONFIG";
+
+/// Where the configuration is read from: `VRLH_CONFIG` if set, then `config.toml` in the app's
+/// own config directory if it exists there, then the shared user config directory
+pub fn config_path(app_dir: Option<&Path>) -> PathBuf {
+    if let Some(path) = std::env::var_os(CONFIG_ENV) {
+        return path.into();
+    }
+    if let Some(path) = app_dir
+        .map(|dir| dir.join(CONFIG_FILE))
+        .filter(|path| path.exists())
+    {
+        return path;
+    }
+    dirs::config_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("vrlh-power-manager")
+        .join(CONFIG_FILE)
+}
+
+impl Config {
+    /// Reads the file, a missing one yields the defaults, and applies environment overrides
+    pub fn load(path: &Path) -> crate::Result<Self> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err.into()),
+        };
+        Self::parse_with_env(&text, |name| std::env::var(name).ok())
+    }
+
+    /// Parses TOML, overriding every `key` of a `[section]` with `VRLH_<SECTION>_<KEY>` if the
+    /// lookup has a value, e.g. `VRLH_SCAN_DURATION_SECS=10`
+    pub fn parse_with_env(text: &str, env: impl Fn(&str) -> Option<String>) -> crate::Result<Self> {
+        let mut table = text.parse::<toml::Table>().map_err(invalid)?;
+        for (section, key, value) in env_overrides(&env)? {
+            let entry = table
+                .entry(section)
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+            if let Some(entry) = entry.as_table_mut() {
+                entry.insert(key, value);
+            }
+        }
+        let config = table.try_into::<Self>().map_err(invalid)?;
+        match config.validate().as_slice() {
+            [] => Ok(config),
+            problems => Err(crate::Error::InvalidConfig(problems.join("; "))),
+        }
+    }
+
+    /// Every problem found in the settings, empty if they are fine
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.scan.duration_secs == 0 {
+            problems.push("scan.duration_secs must be at least 1".to_string());
+        }
+        if self.scan.name_prefix.is_empty() {
+            problems.push("scan.name_prefix must not be empty".to_string());
+        }
+        let timeouts = [
+            ("command_secs", self.timeouts.command_secs),
+            ("transition_secs", self.timeouts.transition_secs),
+            ("spinup_secs", self.timeouts.spinup_secs),
+        ];
+        for (name, value) in timeouts {
+            if value == 0 {
+                problems.push(format!("timeouts.{name} must be at least 1"));
+            }
+        }
+        if self
+            .startup
+            .activate_group
+            .as_ref()
+            .is_some_and(String::is_empty)
+        {
+            problems.push("startup.activate_group must not be empty".to_string());
+        }
+        let mut bound = HashSet::new();
+        for hotkey in &self.hotkeys {
+            // Modifiers may be written in any order and case
+            let mut keys = hotkey
+                .keys
+                .split('+')
+                .map(|key| key.trim().to_lowercase())
+                .collect::<Vec<_>>();
+            keys.sort();
+            if keys.iter().any(String::is_empty) {
+                problems.push(format!(
+                    "hotkeys: '{}' is not a key combination",
+                    hotkey.keys
+                ));
+            } else if !bound.insert(keys) {
+                problems.push(format!(
+                    "hotkeys: '{}' is bound more than once",
+                    hotkey.keys
+                ));
+            }
+            if hotkey.group.as_ref().is_some_and(String::is_empty) {
+                problems.push(format!("hotkeys: '{}' has an empty group", hotkey.keys));
+            }
+        }
+        problems
+    }
+
+    /// Writes the settings as TOML, creating the directory if needed
+    pub fn save(&self, path: &Path) -> crate::Result<()> {
+        self.save_with_env(path, |name| std::env::var(name).ok())
+    }
+
+    /// Writes the settings as TOML, except for values still equal to their environment
+    /// override, which keep whatever the file had so the override is not made permanent
+    pub fn save_with_env(
+        &self,
+        path: &Path,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> crate::Result<()> {
+        if let problems @ [_, ..] = self.validate().as_slice() {
+            return Err(crate::Error::InvalidConfig(problems.join("; ")));
+        }
+        let file = match fs::read_to_string(path) {
+            Ok(text) => text.parse::<toml::Table>().unwrap_or_default(),
+            Err(err) if err.kind() == ErrorKind::NotFound => toml::Table::new(),
+            Err(err) => return Err(err.into()),
+        };
+        let mut table = toml::Table::try_from(self).map_err(invalid)?;
+        for (section, key, value) in env_overrides(&env)? {
+            let Some(keys) = table.get_mut(&section).and_then(toml::Value::as_table_mut) else {
+                continue;
+            };
+            if keys.get(&key) != Some(&value) {
+                continue;
+            }
+            match file.get(&section).and_then(|keys| keys.get(&key)) {
+                Some(saved) => keys.insert(key, saved.clone()),
+                None => keys.remove(&key),
+            };
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, toml::to_string_pretty(&table).map_err(invalid)?)?;
+        Ok(())
+    }
+}
+
+/// Reloads the configuration file whenever it changes
+#[derive(Clone, Debug)]
+pub struct ConfigWatcher {
+    path: PathBuf,
+    interval: Duration,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            interval: Duration::from_secs(2),
+        }
+    }
+
+    #[must_use]
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Loads the configuration, falling back to the defaults if it is invalid
+    pub fn load(&self) -> LoadedConfig {
+        self.reload(Config::default())
+    }
+
+    /// Polls in the background until every receiver is dropped, an invalid change keeps the
+    /// previous configuration in effect and reports why
+    pub fn watch(self) -> watch::Receiver<LoadedConfig> {
+        let mut modified = self.modified();
+        let (tx, rx) = watch::channel(self.load());
+        tokio::spawn(async move {
+            let mut ticks = interval(self.interval);
+            loop {
+                tokio::select! {
+                    _ = ticks.tick() => {}
+                    () = tx.closed() => return,
+                }
+                let current = self.modified();
+                if current == modified {
+                    continue;
+                }
+                modified = current;
+                let previous = tx.borrow().config.clone();
+                let loaded = self.reload(previous);
+                tx.send_if_modified(|current| {
+                    let changed = *current != loaded;
+                    *current = loaded;
+                    changed
+                });
+            }
+        });
+        rx
+    }
+
+    fn reload(&self, fallback: Config) -> LoadedConfig {
+        let (config, error) = match Config::load(&self.path) {
+            Ok(config) => (config, None),
+            Err(err) => (fallback, Some(err.to_string())),
+        };
+        LoadedConfig {
+            config,
+            path: self.path.clone(),
+            error,
+        }
+    }
+
+    fn modified(&self) -> Option<SystemTime> {
+        fs::metadata(&self.path).ok()?.modified().ok()
+    }
+}
+
+/// `(section, key, value)` for every setting the lookup has a `VRLH_<SECTION>_<KEY>` for
+fn env_overrides(
+    env: &impl Fn(&str) -> Option<String>,
+) -> crate::Result<Vec<(String, String, toml::Value)>> {
+    let defaults = toml::Table::try_from(Config::default()).map_err(invalid)?;
+    let mut overrides = Vec::new();
+    for (section, keys) in &defaults {
+        let Some(keys) = keys.as_table() else {
+            continue;
+        };
+        for (key, default) in keys {
+            if let Some(raw) = env(&format!("VRLH_{section}_{key}").to_uppercase()) {
+                overrides.push((section.clone(), key.clone(), env_value(raw, default)));
+            }
+        }
+    }
+    Ok(overrides)
+}
+
+/// Reads a value as TOML so numbers and booleans keep their type, while settings that are
+/// strings take the value as it is
+fn env_value(raw: String, default: &toml::Value) -> toml::Value {
+    if default.is_str() {
+        return toml::Value::String(raw);
+    }
+    format!("value = {raw}")
+        .parse::<toml::Table>()
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or(toml::Value::String(raw))
+}
+
+fn invalid(err: impl std::fmt::Display) -> crate::Error {
+    crate::Error::InvalidConfig(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn env<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        let vars = vars.iter().copied().collect::<HashMap<_, _>>();
+        move |name| vars.get(name).map(ToString::to_string)
+    }
+
+    #[test]
+    fn missing_files_yield_the_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::load(&dir.path().join(CONFIG_FILE)).unwrap();
+        assert_eq!(config, Config::default());
+        assert_eq!(
+            Config::parse_with_env("", env(&[])).unwrap(),
+            Config::default()
+        );
+    }
+
+    #[test]
+    fn overrides_settings_from_the_environment() {
+        let vars = [
+            ("VRLH_SCAN_DURATION_SECS", "10"),
+            ("VRLH_SCAN_NAME_PREFIX", "LHB-"),
+        ];
+        let config = Config::parse_with_env("[scan]\nduration_secs = 7\n", env(&vars)).unwrap();
+        assert_eq!(config.scan.duration_secs, 10);
+        assert_eq!(config.scan.name_prefix, "LHB-");
+
+        // Strings stay strings even when they read as another type
+        let config = Config::parse_with_env("", env(&[("VRLH_SCAN_NAME_PREFIX", "10")])).unwrap();
+        assert_eq!(config.scan.name_prefix, "10");
+        let vars = [("VRLH_SCAN_DURATION_SECS", "soon")];
+        assert!(Config::parse_with_env("", env(&vars)).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        for text in [
+            "[scan]\nduraton_secs = 10\n",
+            "[scanning]\n",
+            "verbose = true\n",
+        ] {
+            let err = Config::parse_with_env(text, env(&[])).unwrap_err();
+            assert!(matches!(err, crate::Error::InvalidConfig(_)), "{text}");
+        }
+    }
+
+    #[test]
+    fn reports_invalid_settings() {
+        let text = r#"
+            [scan]
+            duration_secs = 0
+
+            [[hotkeys]]
+            keys = "Ctrl+Alt+F9"
+            command = "Activate"
+
+            [[hotkeys]]
+            keys = "alt + ctrl + f9"
+            command = "Sleep"
+        "#;
+        let config = toml::from_str::<Config>(text).unwrap();
+        assert_eq!(
+            config.validate(),
+            [
+                "scan.duration_secs must be at least 1",
+                "hotkeys: 'alt + ctrl + f9' is bound more than once",
+            ]
+        );
+        let err = Config::parse_with_env(text, env(&[])).unwrap_err();
+        assert!(err.to_string().contains("is bound more than once"));
+    }
+
+    #[tokio::test]
+    async fn keeps_the_previous_configuration_on_invalid_reloads() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE);
+        // Edits land within the same timestamp granularity, so move the time along by hand
+        let write = |secs: u64, text: &str| {
+            fs::write(&path, text).unwrap();
+            let time = SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+            fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(time)
+                .unwrap();
+        };
+        write(1, "[scan]\nduration_secs = 7\n");
+        let mut rx = ConfigWatcher::new(&path)
+            .with_interval(Duration::from_millis(10))
+            .watch();
+        assert_eq!(rx.borrow_and_update().config.scan.duration_secs, 7);
+
+        write(2, "[scan]\nduration_secs = 0\n");
+        tokio::time::timeout(Duration::from_secs(5), rx.changed())
+            .await
+            .unwrap()
+            .unwrap();
+        let loaded = rx.borrow_and_update().clone();
+        assert_eq!(loaded.config.scan.duration_secs, 7);
+        assert!(loaded.error.unwrap().contains("scan.duration_secs"));
+
+        write(3, "[scan]\nduration_secs = 8\n");
+        tokio::time::timeout(Duration::from_secs(5), rx.changed())
+            .await
+            .unwrap()
+            .unwrap();
+        let loaded = rx.borrow_and_update().clone();
+        assert_eq!(loaded.config.scan.duration_secs, 8);
+        assert_eq!(loaded.error, None);
+    }
+
+    #[test]
+    fn saves_only_values_from_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE);
+        fs::write(&path, "[scan]\nduration_secs = 7\n").unwrap();
+        let vars = [
+            ("VRLH_SCAN_DURATION_SECS", "10"),
+            ("VRLH_TIMEOUTS_COMMAND_SECS", "90"),
+            ("VRLH_POWER_OFF_COMMAND", "Standby"),
+        ];
+        let text = fs::read_to_string(&path).unwrap();
+        let mut config = Config::parse_with_env(&text, env(&vars)).unwrap();
+        assert_eq!(config.scan.duration_secs, 10);
+        config.scan.name_prefix = "LHX-".into();
+        config.power.off_command = crate::DeviceCommand::Sleep;
+        config.save_with_env(&path, env(&vars)).unwrap();
+
+        let saved = Config::parse_with_env(&fs::read_to_string(&path).unwrap(), env(&[])).unwrap();
+        assert_eq!(saved.scan.duration_secs, 7);
+        assert_eq!(saved.scan.name_prefix, "LHX-");
+        // Overridden but missing from the file, so the default applies again
+        assert_eq!(saved.timeouts.command_secs, 60);
+        // Changed after loading, so it is no longer the override
+        assert_eq!(saved.power.off_command, crate::DeviceCommand::Sleep);
+    }
+}
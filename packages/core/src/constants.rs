@@ -7,3 +7,7 @@ pub const LHV2_GATT_POWER_SERVICE: Uuid =
 // 00001525-1212-efde-1523-785feabcd124
 pub const LHV2_GATT_POWER_CHARACTERISTIC: Uuid =
     Uuid::from_u128(0x0000_1525_1212_efde_1523_785f_eabc_d124);
+
+// 00008421-1212-efde-1523-785feabcd124
+pub const LHV2_GATT_IDENTIFY_CHARACTERISTIC: Uuid =
+    Uuid::from_u128(0x0000_8421_1212_efde_1523_785f_eabc_d124);
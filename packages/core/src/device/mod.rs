@@ -1,16 +1,21 @@
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, MutexGuard};
 
 use btleplug::{
     api::{Characteristic, Peripheral as _, WriteType},
     platform::{Peripheral, PeripheralId},
 };
 use futures::{StreamExt, TryFutureExt};
-use tokio::sync::mpsc::Sender;
+use tokio::{sync::mpsc::Sender, time::timeout};
+use uuid::Uuid;
 
 use crate::{
-    constants::{LHV2_GATT_POWER_CHARACTERISTIC, LHV2_GATT_POWER_SERVICE},
+    constants::{
+        LHV2_GATT_IDENTIFY_CHARACTERISTIC, LHV2_GATT_POWER_CHARACTERISTIC, LHV2_GATT_POWER_SERVICE,
+    },
+    remote_state::RemoteStateMachine,
     traits::SendDeviceStatus,
-    DeviceCommand, DeviceInfo, DeviceLocalStatus, DeviceRemoteStatus,
+    DeviceCommand, DeviceInfo, DeviceLocalStatus, DeviceRemoteStatus, DeviceWarning,
+    TimeoutSettings,
 };
 
 #[derive(Clone, Debug)]
@@ -18,7 +23,7 @@ pub struct Device {
     peripheral: Peripheral,
     name: String,
     local: Arc<Mutex<DeviceLocalStatus>>,
-    remote: Arc<Mutex<DeviceRemoteStatus>>,
+    remote: Arc<Mutex<RemoteStateMachine>>,
 }
 
 impl Device {
@@ -27,7 +32,7 @@ impl Device {
             peripheral,
             name,
             local: Arc::new(Mutex::new(DeviceLocalStatus::Initializing)),
-            remote: Arc::new(Mutex::new(DeviceRemoteStatus::Unavailable)),
+            remote: Arc::new(Mutex::new(RemoteStateMachine::default())),
         }
     }
 
@@ -49,6 +54,7 @@ impl Device {
         command: DeviceCommand,
     ) -> crate::Result<()> {
         self.ensure_connected(tx.clone()).await?;
+        self.lock_remote().begin(command.clone());
         let result = self
             .get_power_characteristic()
             .and_then(async |char| {
@@ -61,7 +67,23 @@ impl Device {
                     .write(&char, command.into(), WriteType::WithResponse)
                     .await?;
                 if let Ok(mut events) = maybe_events {
-                    while let Some(event) = events.next().await {
+                    loop {
+                        // Intermediate statuses and the pending command both have a deadline
+                        let remaining = self.lock_remote().remaining();
+                        let next = match remaining {
+                            Some(remaining) => timeout(remaining, events.next()).await.ok(),
+                            None => Some(events.next().await),
+                        };
+                        let Some(next) = next else {
+                            let stuck = self.lock_remote().check_stuck();
+                            if let Some(warning) = stuck {
+                                let _ = tx.send_device_status(self, warning).await;
+                            }
+                            break;
+                        };
+                        let Some(event) = next else {
+                            break;
+                        };
                         let remote = DeviceRemoteStatus::from(event.value);
                         let stop = matches!(
                             remote,
@@ -82,6 +104,7 @@ impl Device {
                 Ok::<(), crate::Error>(())
             })
             .await;
+        self.lock_remote().finish();
 
         let disconnected = match self.disconnect().await {
             Ok(()) => DeviceLocalStatus::Disconnected,
@@ -92,6 +115,26 @@ impl Device {
         result
     }
 
+    /// Makes the device blink its status light so it can be located
+    pub async fn identify(&self, tx: Sender<DeviceInfo>) -> crate::Result<()> {
+        self.ensure_connected(tx.clone()).await?;
+        let result = self
+            .get_identify_characteristic()
+            .and_then(async |char| {
+                self.peripheral
+                    .write(&char, &[0x00], WriteType::WithResponse)
+                    .await
+                    .map_err(Into::into)
+            })
+            .await;
+        let disconnected = match self.disconnect().await {
+            Ok(()) => DeviceLocalStatus::Disconnected,
+            Err(_) => DeviceLocalStatus::FailConnection,
+        };
+        let _ = tx.send_device_status(self, disconnected).await;
+        result
+    }
+
     pub async fn ensure_connected(&self, tx: Sender<DeviceInfo>) -> crate::Result<()> {
         if self.peripheral.is_connected().await? {
             return Ok(());
@@ -125,13 +168,35 @@ impl Device {
                 .lock()
                 .expect("Device local status mutex should not be poisoned")
                 .clone(),
-            self.remote
-                .lock()
-                .expect("Device remote status mutex should not be poisoned")
-                .clone(),
+            self.lock_remote().current().clone(),
         )
     }
 
+    pub fn set_timeouts(&self, timeouts: TimeoutSettings) {
+        self.lock_remote().set_timeouts(timeouts);
+    }
+
+    pub fn get_remote_state(&self) -> RemoteStateMachine {
+        self.lock_remote().clone()
+    }
+
+    pub(crate) fn record_local(&self, status: DeviceLocalStatus) {
+        *self
+            .local
+            .lock()
+            .expect("Device local status mutex should not be poisoned") = status;
+    }
+
+    pub(crate) fn record_remote(&self, status: DeviceRemoteStatus) -> Vec<DeviceWarning> {
+        self.lock_remote().record(status)
+    }
+
+    fn lock_remote(&self) -> MutexGuard<'_, RemoteStateMachine> {
+        self.remote
+            .lock()
+            .expect("Device remote status mutex should not be poisoned")
+    }
+
     pub async fn fetch_remote_status(&self, tx: Sender<DeviceInfo>) -> crate::Result<()> {
         let _ = tx
             .send_device_status(self, DeviceLocalStatus::Initializing)
@@ -157,6 +222,21 @@ impl Device {
 
     /// The characteristic must be used during the same connection session during which it was retrieved
     pub async fn get_power_characteristic(&self) -> crate::Result<Characteristic> {
+        self.get_characteristic(LHV2_GATT_POWER_CHARACTERISTIC)
+            .await?
+            .ok_or(crate::Error::Vrlh("Could not verify power charateristic!"))
+    }
+
+    /// The characteristic must be used during the same connection session during which it was retrieved
+    pub async fn get_identify_characteristic(&self) -> crate::Result<Characteristic> {
+        self.get_characteristic(LHV2_GATT_IDENTIFY_CHARACTERISTIC)
+            .await?
+            .ok_or(crate::Error::Vrlh(
+                "Could not verify identify charateristic!",
+            ))
+    }
+
+    async fn get_characteristic(&self, uuid: Uuid) -> crate::Result<Option<Characteristic>> {
         self.peripheral.discover_services().await?;
         let service = self
             .peripheral
@@ -164,10 +244,9 @@ impl Device {
             .into_iter()
             .find(|service| service.uuid == LHV2_GATT_POWER_SERVICE)
             .ok_or(crate::Error::Vrlh("Could not verify power service!"))?;
-        service
+        Ok(service
             .characteristics
             .into_iter()
-            .find(|char| char.uuid == LHV2_GATT_POWER_CHARACTERISTIC)
-            .ok_or(crate::Error::Vrlh("Could not verify power charateristic!"))
+            .find(|char| char.uuid == uuid))
     }
 }
@@ -10,29 +10,100 @@ use btleplug::{
 };
 use futures::StreamExt;
 use tokio::{
-    sync::mpsc::{channel, Receiver, Sender},
+    sync::{
+        broadcast,
+        mpsc::{channel, Receiver, Sender},
+    },
     time::sleep,
 };
 
-use crate::{device::Device, get_default_adapter, DeviceInfo, DeviceLocalStatus};
+use crate::{
+    device::Device, get_default_adapter, traits::SendDeviceStatus, Aliases, Config, DeviceCommand,
+    DeviceInfo, DeviceListEvent, DeviceLocalStatus, DeviceSelector,
+};
+
+const EVENT_CAPACITY: usize = 64;
+const NO_ADAPTER: crate::Error = crate::Error::Vrlh("No bluetooth adapter available!");
 
 /// Can be cloned and will retain references to the same devices
 #[derive(Clone, Debug)]
 pub struct DeviceList {
-    adapter: Adapter,
+    adapter: Option<Adapter>,
     map: Arc<Mutex<HashMap<PeripheralId, Device>>>,
+    events: broadcast::Sender<DeviceListEvent>,
+    config: Arc<Mutex<Config>>,
 }
 
 impl DeviceList {
     pub async fn init() -> crate::Result<Self> {
         Ok(Self {
             map: Arc::new(Mutex::new(HashMap::new())),
-            adapter: get_default_adapter().await?,
+            adapter: Some(get_default_adapter().await?),
+            events: broadcast::channel(EVENT_CAPACITY).0,
+            config: Arc::default(),
         })
     }
 
-    pub fn get_adapter(&self) -> &Adapter {
-        &self.adapter
+    /// A list without a bluetooth adapter, which never finds devices and refuses to scan
+    pub fn detached() -> Self {
+        Self {
+            map: Arc::new(Mutex::new(HashMap::new())),
+            adapter: None,
+            events: broadcast::channel(EVENT_CAPACITY).0,
+            config: Arc::default(),
+        }
+    }
+
+    pub fn config(&self) -> Config {
+        self.config
+            .lock()
+            .expect("Config mutex must not be poisoned")
+            .clone()
+    }
+
+    /// Applies new settings to future scans and every known device
+    pub fn set_config(&self, config: Config) {
+        let timeouts = config.timeouts;
+        *self
+            .config
+            .lock()
+            .expect("Config mutex must not be poisoned") = config;
+        for device in self.get_devices() {
+            device.set_timeouts(timeouts);
+        }
+    }
+
+    /// Receives every update produced by operations on this list, regardless of who started them
+    pub fn subscribe(&self) -> broadcast::Receiver<DeviceListEvent> {
+        self.events.subscribe()
+    }
+
+    pub fn publish(&self, event: DeviceListEvent) {
+        // Having no subscribers is not an error
+        let _ = self.events.send(event);
+    }
+
+    /// Returns a sender for devices to report to and a receiver for the caller,
+    /// broadcasting everything in between and `done` once all senders are dropped
+    fn relay(&self, done: Option<DeviceListEvent>) -> (Sender<DeviceInfo>, Receiver<DeviceInfo>) {
+        let (tx_in, mut rx_in) = channel::<DeviceInfo>(1);
+        let (tx_out, rx_out) = channel(1);
+        let list = self.clone();
+        tokio::spawn(async move {
+            while let Some(info) = rx_in.recv().await {
+                list.publish(DeviceListEvent::Device(info.clone()));
+                // Keep broadcasting even if the caller stopped listening
+                let _ = tx_out.send(info).await;
+            }
+            if let Some(done) = done {
+                list.publish(done);
+            }
+        });
+        (tx_in, rx_out)
+    }
+
+    pub fn get_adapter(&self) -> Option<&Adapter> {
+        self.adapter.as_ref()
     }
 
     pub fn get_device_map(&self) -> Arc<Mutex<HashMap<PeripheralId, Device>>> {
@@ -48,8 +119,66 @@ impl DeviceList {
             .cloned()
     }
 
+    pub fn get_devices(&self) -> Vec<Device> {
+        self.map
+            .lock()
+            .expect("Device map mutex must not be poisoned")
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Devices matching any of the selectors, in no particular order
+    pub fn select(&self, selectors: &[DeviceSelector], aliases: &Aliases) -> Vec<Device> {
+        self.get_devices()
+            .into_iter()
+            .filter(|device| {
+                selectors
+                    .iter()
+                    .any(|selector| selector.matches_device(device, aliases))
+            })
+            .collect()
+    }
+
+    /// Sends a command to every device at once, the receiver closes after all devices finish
+    pub fn start_power(
+        &self,
+        devices: Vec<Device>,
+        command: &DeviceCommand,
+    ) -> Receiver<DeviceInfo> {
+        let (tx, rx) = self.relay(None);
+        for device in devices {
+            let tx_clone = tx.clone();
+            let command_clone = command.clone();
+            tokio::spawn(async move {
+                if let Err(err) = device.power_set(tx_clone.clone(), command_clone).await {
+                    let status = DeviceLocalStatus::Error(err.to_string());
+                    let _ = tx_clone.send_device_status(&device, status).await;
+                }
+            });
+        }
+        rx
+    }
+
+    /// Identifies every device at once, the receiver closes after all devices finish
+    pub fn start_identify(&self, devices: Vec<Device>) -> Receiver<DeviceInfo> {
+        let (tx, rx) = self.relay(None);
+        for device in devices {
+            let tx_clone = tx.clone();
+            tokio::spawn(async move {
+                if let Err(err) = device.identify(tx_clone.clone()).await {
+                    let status = DeviceLocalStatus::Error(err.to_string());
+                    let _ = tx_clone.send_device_status(&device, status).await;
+                }
+            });
+        }
+        rx
+    }
+
     pub fn start_scan(&self, duration: u64) -> crate::Result<Receiver<DeviceInfo>> {
-        let (tx, rx) = channel(1);
+        let adapter = self.adapter.clone().ok_or(NO_ADAPTER)?;
+        self.publish(DeviceListEvent::ScanStarted { duration });
+        let (tx, rx) = self.relay(Some(DeviceListEvent::ScanFinished));
 
         let refresh = self
             .get_device_map()
@@ -64,8 +193,6 @@ impl DeviceList {
 
         let devices = self.clone();
         tokio::spawn(async move {
-            let adapter = devices.get_adapter();
-
             let timer = Box::pin(sleep(Duration::from_secs(duration)));
             let mut stream = adapter.events().await?.take_until(timer);
 
@@ -98,18 +225,24 @@ async fn handle_discovered_device(
     {
         return Ok(());
     }
-    let peripheral = list.get_adapter().peripheral(&id).await?;
+    let peripheral = list
+        .get_adapter()
+        .ok_or(NO_ADAPTER)?
+        .peripheral(&id)
+        .await?;
     let maybe_name = peripheral
         .properties()
         .await
         .unwrap_or(None)
         .and_then(|props| props.local_name);
-    let valid_name = maybe_name
-        .clone()
-        .and_then(|name| match name.starts_with("LHB-") {
-            true => Some(name),
-            false => None,
-        });
+    let config = list.config();
+    let valid_name =
+        maybe_name
+            .clone()
+            .and_then(|name| match name.starts_with(&config.scan.name_prefix) {
+                true => Some(name),
+                false => None,
+            });
     let Some(name) = valid_name else {
         let addr = peripheral.address().to_string();
         let _ = tx
@@ -119,12 +252,14 @@ async fn handle_discovered_device(
                 addr,
                 local: Some(DeviceLocalStatus::Ignored),
                 remote: None,
+                warnings: Vec::new(),
             })
             .await;
         return Ok(());
     };
 
     let device = Device::new(peripheral.clone(), name.clone());
+    device.set_timeouts(config.timeouts);
     list.map
         .lock()
         .expect("Device map mutex must not be poisoned")
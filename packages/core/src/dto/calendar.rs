@@ -0,0 +1,71 @@
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{DeviceCommand, DeviceSelector};
+
+/// Powers lighthouses for the sessions booked in an iCalendar file
+#[derive(Clone, Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct CalendarConfig {
+    pub id: String,
+    /// `.ics` file, or a directory whose `.ics` files are all read
+    #[ts(type = "string")]
+    pub path: PathBuf,
+    /// Only sessions whose location contains one of these, ignoring case
+    #[serde(default)]
+    pub locations: Vec<String>,
+    /// Only sessions with one of these categories, ignoring case
+    #[serde(default)]
+    pub categories: Vec<String>,
+    /// Names, addresses, aliases or groups
+    #[serde(default = "default_targets")]
+    #[ts(type = "Array<string>")]
+    pub targets: Vec<DeviceSelector>,
+    /// Minutes before a session starts to send `on_start`
+    #[serde(default = "default_lead_mins")]
+    #[ts(type = "number")]
+    pub lead_mins: u64,
+    #[serde(default = "default_on_start")]
+    pub on_start: DeviceCommand,
+    #[serde(default = "default_on_end")]
+    pub on_end: DeviceCommand,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+/// Single booked session read from a calendar
+#[derive(Clone, Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct CalendarEvent {
+    pub uid: String,
+    pub summary: String,
+    pub location: Option<String>,
+    pub categories: Vec<String>,
+    #[ts(type = "string")]
+    pub start: DateTime<Utc>,
+    #[ts(type = "string")]
+    pub end: DateTime<Utc>,
+}
+
+fn default_targets() -> Vec<DeviceSelector> {
+    vec![DeviceSelector::all()]
+}
+
+fn default_lead_mins() -> u64 {
+    10
+}
+
+fn default_on_start() -> DeviceCommand {
+    DeviceCommand::Activate
+}
+
+fn default_on_end() -> DeviceCommand {
+    DeviceCommand::Sleep
+}
+
+fn default_enabled() -> bool {
+    true
+}
@@ -1,6 +1,13 @@
-use std::fmt::Display;
+use std::{fmt::Display, str::FromStr};
 
-#[derive(Clone, Debug)]
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::DeviceRemoteStatus;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[ts(export)]
 pub enum DeviceCommand {
     Sleep,
     Activate,
@@ -27,3 +34,40 @@ impl Display for DeviceCommand {
         write!(f, "{str}")
     }
 }
+
+impl DeviceCommand {
+    /// Remote status a device settles on once the command has been carried out
+    pub fn target(&self) -> DeviceRemoteStatus {
+        match self {
+            DeviceCommand::Sleep => DeviceRemoteStatus::Stopped,
+            DeviceCommand::Activate => DeviceRemoteStatus::Active,
+            DeviceCommand::Standby => DeviceRemoteStatus::Standby,
+        }
+    }
+}
+
+impl TryFrom<u8> for DeviceCommand {
+    type Error = crate::Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(DeviceCommand::Sleep),
+            1 => Ok(DeviceCommand::Activate),
+            2 => Ok(DeviceCommand::Standby),
+            _ => Err(crate::Error::Vrlh("Invalid power command!")),
+        }
+    }
+}
+
+impl FromStr for DeviceCommand {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "sleep" | "off" | "stop" => Ok(DeviceCommand::Sleep),
+            "activate" | "on" | "active" => Ok(DeviceCommand::Activate),
+            "standby" => Ok(DeviceCommand::Standby),
+            _ => Err(crate::Error::Vrlh("Invalid power command!")),
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::DeviceCommand;
+
+/// Settings shared by the core and every frontend, kept in `config.toml`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(default, deny_unknown_fields)]
+#[ts(export)]
+pub struct Config {
+    pub scan: ScanSettings,
+    pub timeouts: TimeoutSettings,
+    pub power: PowerSettings,
+    pub startup: StartupSettings,
+    /// Global shortcuts of the GUI
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub hotkeys: Vec<HotkeyConfig>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(default, deny_unknown_fields)]
+#[ts(export)]
+pub struct ScanSettings {
+    /// Seconds a scan runs when the frontend does not ask for a duration
+    #[ts(type = "number")]
+    pub duration_secs: u64,
+    /// Devices whose bluetooth name does not start with this are ignored
+    pub name_prefix: String,
+}
+
+impl Default for ScanSettings {
+    fn default() -> Self {
+        Self {
+            duration_secs: 5,
+            name_prefix: "LHB-".into(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(default, deny_unknown_fields)]
+#[ts(export)]
+pub struct TimeoutSettings {
+    /// Seconds to wait for lighthouses to reach the requested state
+    #[ts(type = "number")]
+    pub command_secs: u64,
+    /// Seconds a lighthouse may stay `Initiated` or `Acknowledged` before it is stuck
+    #[ts(type = "number")]
+    pub transition_secs: u64,
+    /// Seconds a lighthouse may stay in `Spinup` before it is stuck
+    #[ts(type = "number")]
+    pub spinup_secs: u64,
+}
+
+impl Default for TimeoutSettings {
+    fn default() -> Self {
+        Self {
+            command_secs: 60,
+            transition_secs: 5,
+            spinup_secs: 30,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(default, deny_unknown_fields)]
+#[ts(export)]
+pub struct PowerSettings {
+    /// Sent when lighthouses are powered off without naming a command
+    pub off_command: DeviceCommand,
+}
+
+impl Default for PowerSettings {
+    fn default() -> Self {
+        Self {
+            off_command: DeviceCommand::Sleep,
+        }
+    }
+}
+
+/// What the GUI does when it is launched at login
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(default, deny_unknown_fields)]
+#[ts(export)]
+pub struct StartupSettings {
+    /// Registers the GUI to be launched at login
+    pub launch_at_login: bool,
+    /// Stay hidden in the tray instead of opening the window
+    pub minimized: bool,
+    /// Scan for lighthouses right away
+    pub scan: bool,
+    /// Group activated once lighthouses are found
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub activate_group: Option<String>,
+}
+
+/// Key combination sending a command to every lighthouse or a group
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(deny_unknown_fields)]
+#[ts(export)]
+pub struct HotkeyConfig {
+    /// e.g. `Ctrl+Alt+F9`
+    pub keys: String,
+    pub command: DeviceCommand,
+    /// Group the command is sent to, every lighthouse if missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub group: Option<String>,
+}
+
+/// Outcome of registering a hotkey, which fails if the keys are taken or invalid
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct HotkeyRegistration {
+    pub hotkey: HotkeyConfig,
+    pub error: Option<String>,
+}
+
+/// Configuration in effect along with where it came from
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct LoadedConfig {
+    pub config: Config,
+    #[ts(type = "string")]
+    pub path: PathBuf,
+    /// Why the file could not be used, the previous or default configuration stays in effect
+    pub error: Option<String>,
+}
@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::DeviceInfo;
+
+/// Everything observable about a device list, broadcast to all subscribers
+#[derive(Clone, Debug, Serialize, Deserialize, TS)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "type", content = "payload")]
+#[ts(export)]
+pub enum DeviceListEvent {
+    Device(DeviceInfo),
+    Status(String),
+    ScanStarted {
+        #[ts(type = "number")]
+        #[cfg_attr(feature = "openapi", schema(value_type = u64))]
+        duration: u64,
+    },
+    ScanFinished,
+}
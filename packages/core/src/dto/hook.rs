@@ -0,0 +1,57 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::LifecycleEventKind;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HookConfig {
+    pub command: PathBuf,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Events that run the hook, empty for every event
+    #[serde(default)]
+    pub events: Vec<LifecycleEventKind>,
+    /// Seconds after which the hook is killed
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl HookConfig {
+    pub fn new(command: impl Into<PathBuf>) -> Self {
+        Self {
+            command: command.into(),
+            args: Vec::new(),
+            events: Vec::new(),
+            timeout_secs: default_timeout_secs(),
+        }
+    }
+
+    pub fn accepts(&self, kind: LifecycleEventKind) -> bool {
+        self.events.is_empty() || self.events.contains(&kind)
+    }
+}
+
+fn default_timeout_secs() -> u64 {
+    30
+}
+
+/// Outcome of a single hook execution
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HookRun {
+    pub command: PathBuf,
+    pub event: LifecycleEventKind,
+    /// Exit code of the process, missing if it could not be started, timed out or was killed by a signal
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    pub error: Option<String>,
+    /// Seconds since the unix epoch
+    pub timestamp: u64,
+}
+
+impl HookRun {
+    pub fn succeeded(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+}
@@ -0,0 +1,64 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{DeviceCommand, DeviceSelector};
+
+/// Where the time since the user was last active comes from
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum IdleSource {
+    /// `IdleHint` of the current logind session
+    #[default]
+    Logind,
+    /// Executable printing the idle time in milliseconds, like `xprintidle`
+    Command {
+        command: PathBuf,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+}
+
+/// Powers down lighthouses left active while nobody is using the computer
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IdleConfig {
+    #[serde(default = "default_targets")]
+    pub targets: Vec<DeviceSelector>,
+    #[serde(default = "default_minutes")]
+    pub minutes: u64,
+    #[serde(default = "default_command")]
+    pub command: DeviceCommand,
+    /// Seconds between the cancellable warning and the command being sent
+    #[serde(default = "default_warning_secs")]
+    pub warning_secs: u64,
+    #[serde(default)]
+    pub source: IdleSource,
+}
+
+impl Default for IdleConfig {
+    fn default() -> Self {
+        Self {
+            targets: default_targets(),
+            minutes: default_minutes(),
+            command: default_command(),
+            warning_secs: default_warning_secs(),
+            source: IdleSource::default(),
+        }
+    }
+}
+
+fn default_targets() -> Vec<DeviceSelector> {
+    vec![DeviceSelector::all()]
+}
+
+fn default_minutes() -> u64 {
+    30
+}
+
+fn default_command() -> DeviceCommand {
+    DeviceCommand::Sleep
+}
+
+fn default_warning_secs() -> u64 {
+    60
+}
@@ -1,21 +1,24 @@
 use std::fmt::Debug;
 
 use btleplug::platform::PeripheralId;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
-use crate::{Device, DeviceLocalStatus, DeviceRemoteStatus};
+use crate::{Device, DeviceLocalStatus, DeviceRemoteStatus, DeviceWarning};
 
-#[derive(Clone, Debug, Serialize, TS)]
+#[derive(Clone, Debug, Serialize, Deserialize, TS)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[ts(export)]
 pub struct DeviceInfo {
     /// Serializes differently per platform
     #[ts(type = "unknown")]
+    #[cfg_attr(feature = "openapi", schema(value_type = Object))]
     pub id: PeripheralId,
     pub addr: String,
     pub name: String,
     pub local: Option<DeviceLocalStatus>,
     pub remote: Option<DeviceRemoteStatus>,
+    pub warnings: Vec<DeviceWarning>,
 }
 
 impl DeviceInfo {
@@ -30,6 +33,24 @@ impl DeviceInfo {
             name: device.name().to_string(),
             local: Some(local),
             remote: Some(remote),
+            warnings: Vec::new(),
         }
     }
 }
+
+#[cfg(test)]
+impl DeviceInfo {
+    /// Info for a lighthouse that was never connected to
+    pub(crate) fn fake(name: &str, addr: &str, remote: &DeviceRemoteStatus) -> Self {
+        let object_path = format!("/org/bluez/hci0/dev_{}", addr.replace(':', "_"));
+        serde_json::from_value(serde_json::json!({
+            "id": { "object_path": object_path },
+            "addr": addr,
+            "name": name,
+            "local": null,
+            "remote": remote,
+            "warnings": [],
+        }))
+        .expect("Fake device info must deserialize")
+    }
+}
@@ -0,0 +1,111 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::DeviceInfo;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub enum LifecycleEventKind {
+    ScanFinished,
+    DeviceChanged,
+    DeviceFailed,
+    AllActive,
+    AllOff,
+}
+
+impl Display for LifecycleEventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            Self::ScanFinished => "SCAN_FINISHED",
+            Self::DeviceChanged => "DEVICE_CHANGED",
+            Self::DeviceFailed => "DEVICE_FAILED",
+            Self::AllActive => "ALL_ACTIVE",
+            Self::AllOff => "ALL_OFF",
+        };
+        write!(f, "{str}")
+    }
+}
+
+impl FromStr for LifecycleEventKind {
+    type Err = crate::Error;
+
+    /// Accepts any casing with or without `_` and `-` separators
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized = s.replace(['_', '-'], "").to_ascii_lowercase();
+        match normalized.as_str() {
+            "scanfinished" => Ok(Self::ScanFinished),
+            "devicechanged" => Ok(Self::DeviceChanged),
+            "devicefailed" => Ok(Self::DeviceFailed),
+            "allactive" => Ok(Self::AllActive),
+            "alloff" => Ok(Self::AllOff),
+            _ => Err(crate::Error::Vrlh("Invalid lifecycle event!")),
+        }
+    }
+}
+
+/// Higher level events derived from a device list, see `LifecycleTracker`
+#[derive(Clone, Debug, Serialize, Deserialize, TS)]
+#[serde(tag = "type")]
+#[ts(export)]
+pub enum LifecycleEvent {
+    ScanFinished {
+        devices: Vec<DeviceInfo>,
+    },
+    /// Local or remote status of a lighthouse differs from the last update
+    DeviceChanged {
+        device: DeviceInfo,
+    },
+    /// Lighthouse could not be reached, reported an unknown status or got stuck
+    DeviceFailed {
+        device: DeviceInfo,
+        reason: String,
+    },
+    /// Every known lighthouse reached `Active`
+    AllActive {
+        devices: Vec<DeviceInfo>,
+    },
+    /// Every known lighthouse reached `Standby` or `Stopped`
+    AllOff {
+        devices: Vec<DeviceInfo>,
+    },
+}
+
+impl LifecycleEvent {
+    pub fn kind(&self) -> LifecycleEventKind {
+        match self {
+            Self::ScanFinished { .. } => LifecycleEventKind::ScanFinished,
+            Self::DeviceChanged { .. } => LifecycleEventKind::DeviceChanged,
+            Self::DeviceFailed { .. } => LifecycleEventKind::DeviceFailed,
+            Self::AllActive { .. } => LifecycleEventKind::AllActive,
+            Self::AllOff { .. } => LifecycleEventKind::AllOff,
+        }
+    }
+
+    pub fn device(&self) -> Option<&DeviceInfo> {
+        match self {
+            Self::DeviceChanged { device } | Self::DeviceFailed { device, .. } => Some(device),
+            _ => None,
+        }
+    }
+
+    /// Every lighthouse the event concerns
+    pub fn devices(&self) -> &[DeviceInfo] {
+        match self {
+            Self::ScanFinished { devices }
+            | Self::AllActive { devices }
+            | Self::AllOff { devices } => devices,
+            Self::DeviceChanged { device } | Self::DeviceFailed { device, .. } => {
+                std::slice::from_ref(device)
+            }
+        }
+    }
+
+    pub fn reason(&self) -> Option<&str> {
+        match self {
+            Self::DeviceFailed { reason, .. } => Some(reason),
+            _ => None,
+        }
+    }
+}
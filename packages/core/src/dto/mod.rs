@@ -1,9 +1,39 @@
+mod calendar;
 mod command;
+mod config;
+mod event;
+mod hook;
+mod idle;
 mod info;
+mod lifecycle;
 mod local;
+mod openxr;
+mod policy;
 mod remote;
+mod rule;
+mod schedule;
+mod script;
+mod suspend;
+mod usb;
+mod warning;
+mod webhook;
 
+pub use calendar::*;
 pub use command::*;
+pub use config::*;
+pub use event::*;
+pub use hook::*;
+pub use idle::*;
 pub use info::*;
+pub use lifecycle::*;
 pub use local::*;
+pub use openxr::*;
+pub use policy::*;
 pub use remote::*;
+pub use rule::*;
+pub use schedule::*;
+pub use script::*;
+pub use suspend::*;
+pub use usb::*;
+pub use warning::*;
+pub use webhook::*;
@@ -0,0 +1,81 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum OpenXrRuntime {
+    SteamVr,
+    Monado,
+    WiVRn,
+    /// Streams through SteamVR, so it is only followed while SteamVR is the active runtime
+    Alvr,
+}
+
+impl OpenXrRuntime {
+    pub const ALL: [Self; 4] = [Self::SteamVr, Self::Monado, Self::WiVRn, Self::Alvr];
+
+    /// Processes running for as long as a session of the runtime is
+    ///
+    /// The ALVR streamer is a driver loaded by SteamVR's `vrserver`, its dashboard and
+    /// launcher stay open without a headset so they do not count
+    pub fn processes(self) -> &'static [&'static str] {
+        match self {
+            Self::SteamVr => crate::STEAMVR_PROCESSES,
+            Self::Monado => &["monado-service"],
+            Self::WiVRn => &["wivrn-server"],
+            Self::Alvr => &["vrserver"],
+        }
+    }
+}
+
+impl Display for OpenXrRuntime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            Self::SteamVr => "SteamVR",
+            Self::Monado => "Monado",
+            Self::WiVRn => "WiVRn",
+            Self::Alvr => "ALVR",
+        };
+        write!(f, "{str}")
+    }
+}
+
+impl FromStr for OpenXrRuntime {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "steamvr" => Ok(Self::SteamVr),
+            "monado" => Ok(Self::Monado),
+            "wivrn" => Ok(Self::WiVRn),
+            "alvr" => Ok(Self::Alvr),
+            _ => Err(crate::Error::Vrlh("Invalid OpenXR runtime!")),
+        }
+    }
+}
+
+/// Runtimes whose sessions power lighthouses on and off
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OpenXrConfig {
+    /// `SteamVr` is left out by default as the SteamVR watcher already covers it
+    pub runtimes: Vec<OpenXrRuntime>,
+}
+
+impl Default for OpenXrConfig {
+    fn default() -> Self {
+        Self {
+            runtimes: vec![
+                OpenXrRuntime::Monado,
+                OpenXrRuntime::WiVRn,
+                OpenXrRuntime::Alvr,
+            ],
+        }
+    }
+}
+
+impl OpenXrConfig {
+    pub fn is_enabled(&self, runtime: OpenXrRuntime) -> bool {
+        self.runtimes.contains(&runtime)
+    }
+}
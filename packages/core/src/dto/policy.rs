@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{DeviceCommand, DeviceSelector};
+
+/// Commands sent when a VR session starts and stops
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PowerPolicy {
+    #[serde(default = "default_targets")]
+    pub targets: Vec<DeviceSelector>,
+    #[serde(default = "default_on_start")]
+    pub on_start: DeviceCommand,
+    #[serde(default = "default_on_stop")]
+    pub on_stop: DeviceCommand,
+    /// Seconds to wait after a session stops, a session starting again in the meantime cancels `on_stop`
+    #[serde(default = "default_grace_secs")]
+    pub grace_secs: u64,
+}
+
+impl Default for PowerPolicy {
+    fn default() -> Self {
+        Self {
+            targets: default_targets(),
+            on_start: default_on_start(),
+            on_stop: default_on_stop(),
+            grace_secs: default_grace_secs(),
+        }
+    }
+}
+
+fn default_targets() -> Vec<DeviceSelector> {
+    vec![DeviceSelector::all()]
+}
+
+fn default_on_start() -> DeviceCommand {
+    DeviceCommand::Activate
+}
+
+fn default_on_stop() -> DeviceCommand {
+    DeviceCommand::Sleep
+}
+
+fn default_grace_secs() -> u64 {
+    30
+}
@@ -1,9 +1,10 @@
-use std::fmt::{Debug, Write};
+use std::fmt::{Debug, Display, Write};
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
-#[derive(Clone, Serialize, TS)]
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[ts(export)]
 pub enum DeviceRemoteStatus {
     Unavailable,
@@ -38,6 +39,21 @@ impl Debug for DeviceRemoteStatus {
     }
 }
 
+impl Display for DeviceRemoteStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unavailable => write!(f, "UNAVAILABLE"),
+            Self::Stopped => write!(f, "STOPPED"),
+            Self::Initiated => write!(f, "INITIATED"),
+            Self::Standby => write!(f, "STANDBY"),
+            Self::Acknowledged => write!(f, "ACKNOWLEDGED"),
+            Self::Spinup => write!(f, "SPINUP"),
+            Self::Active => write!(f, "ACTIVE"),
+            Self::Unknown(_) => write!(f, "UNKNOWN({self:?})"),
+        }
+    }
+}
+
 impl From<Vec<u8>> for DeviceRemoteStatus {
     fn from(value: Vec<u8>) -> Self {
         if value.len() != 1 {
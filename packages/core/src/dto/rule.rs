@@ -0,0 +1,157 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{DeviceCommand, DeviceRemoteStatus, DeviceSelector, Groups, LifecycleEventKind, UsbId};
+
+/// Rules as written in a TOML file, every rule being a `[[rule]]` table
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RuleSet {
+    #[serde(default)]
+    pub groups: Groups,
+    #[serde(default, rename = "rule")]
+    pub rules: Vec<Rule>,
+}
+
+/// Runs its actions when the trigger happens while every condition holds
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Rule {
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    pub trigger: RuleTrigger,
+    #[serde(default)]
+    pub conditions: Vec<RuleCondition>,
+    pub actions: Vec<RuleAction>,
+    /// Seconds after firing during which the rule does not fire again
+    #[serde(default)]
+    pub cooldown_secs: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum RuleTrigger {
+    /// A selected lighthouse changes to the remote status
+    Status {
+        #[serde(default = "default_targets")]
+        targets: Vec<DeviceSelector>,
+        status: DeviceRemoteStatus,
+    },
+    Lifecycle {
+        event: LifecycleEventKind,
+    },
+    /// Every minute matching a standard five field cron expression
+    Time {
+        cron: String,
+        /// IANA name, the system timezone when absent
+        #[serde(default)]
+        timezone: Option<String>,
+    },
+    /// One of the processes starts, or stops when `running` is false
+    Process {
+        names: Vec<String>,
+        #[serde(default = "default_true")]
+        running: bool,
+    },
+    /// One of the USB devices is plugged in, or removed when `present` is false
+    Usb {
+        ids: Vec<UsbId>,
+        #[serde(default = "default_true")]
+        present: bool,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum RuleCondition {
+    /// Every selected lighthouse has the remote status
+    AllStatus {
+        #[serde(default = "default_targets")]
+        targets: Vec<DeviceSelector>,
+        status: DeviceRemoteStatus,
+    },
+    /// At least one selected lighthouse has the remote status
+    AnyStatus {
+        #[serde(default = "default_targets")]
+        targets: Vec<DeviceSelector>,
+        status: DeviceRemoteStatus,
+    },
+    Process {
+        names: Vec<String>,
+        #[serde(default = "default_true")]
+        running: bool,
+    },
+    Usb {
+        ids: Vec<UsbId>,
+        #[serde(default = "default_true")]
+        present: bool,
+    },
+    /// The service process of SteamVR or any OpenXR runtime is running
+    VrRuntime {
+        #[serde(default = "default_true")]
+        running: bool,
+    },
+    /// The clock is between two `HH:MM` times, which may wrap past midnight
+    TimeBetween {
+        after: String,
+        before: String,
+        #[serde(default)]
+        timezone: Option<String>,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum RuleAction {
+    /// Sends the command to the targets, or to the lighthouse that triggered a `Status` rule
+    Power {
+        command: DeviceCommand,
+        #[serde(default)]
+        targets: Vec<DeviceSelector>,
+    },
+    /// Publishes the message to every subscriber of the device list
+    Emit { message: String },
+}
+
+/// Something that happened, checked against the trigger of every rule
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum RuleInput {
+    Status {
+        name: String,
+        addr: String,
+        status: DeviceRemoteStatus,
+    },
+    Lifecycle {
+        event: LifecycleEventKind,
+    },
+    Time {
+        at: DateTime<Utc>,
+    },
+    Process {
+        name: String,
+        running: bool,
+    },
+    Usb {
+        id: UsbId,
+        present: bool,
+    },
+}
+
+/// Why a rule did or did not fire for an input
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RuleEvaluation {
+    pub rule: String,
+    pub at: DateTime<Utc>,
+    pub input: RuleInput,
+    pub fired: bool,
+    /// One line for the trigger, every condition and the cooldown, in the order checked
+    pub explanation: Vec<String>,
+}
+
+fn default_targets() -> Vec<DeviceSelector> {
+    vec![DeviceSelector::all()]
+}
+
+fn default_true() -> bool {
+    true
+}
@@ -0,0 +1,60 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{DeviceCommand, DeviceSelector};
+
+/// What happens to runs that were due while the computer was off or asleep
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub enum MissedRuns {
+    #[default]
+    Skip,
+    /// Sends the command once for the latest missed run
+    RunLatest,
+}
+
+/// Sends a command to lighthouses whenever a cron expression matches
+#[derive(Clone, Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct Schedule {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    /// Standard five field expression, e.g. `50 9 * * MON-FRI`
+    pub cron: String,
+    /// IANA name such as `Europe/Berlin`, the system timezone when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub timezone: Option<String>,
+    pub command: DeviceCommand,
+    /// Names, addresses, aliases or groups
+    #[serde(default = "default_targets")]
+    #[ts(type = "Array<string>")]
+    pub targets: Vec<DeviceSelector>,
+    /// Dates in the schedule's timezone on which it does not run, e.g. holidays
+    #[serde(default)]
+    #[ts(type = "Array<string>")]
+    pub exceptions: Vec<NaiveDate>,
+    #[serde(default)]
+    pub missed: MissedRuns,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+/// Upcoming run of a schedule, `None` when disabled or never matching again
+#[derive(Clone, Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct ScheduleNextRun {
+    pub id: String,
+    #[ts(type = "string | null")]
+    pub next: Option<DateTime<Utc>>,
+}
+
+fn default_targets() -> Vec<DeviceSelector> {
+    vec![DeviceSelector::all()]
+}
+
+fn default_enabled() -> bool {
+    true
+}
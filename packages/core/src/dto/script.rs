@@ -0,0 +1,37 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScriptConfig {
+    /// Directory every `.rhai` file is loaded from
+    pub dir: PathBuf,
+    /// Milliseconds a single handler may run before it is stopped
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl ScriptConfig {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            timeout_ms: default_timeout_ms(),
+        }
+    }
+}
+
+fn default_timeout_ms() -> u64 {
+    250
+}
+
+/// Outcome of loading a script or calling one of its handlers
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScriptRun {
+    pub script: String,
+    /// `load` or the name of the handler, e.g. `on_device`
+    pub handler: String,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+    /// Seconds since the unix epoch
+    pub timestamp: u64,
+}
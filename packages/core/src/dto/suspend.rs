@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{DeviceCommand, DeviceSelector};
+
+/// What happens to lighthouses when the computer suspends
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SuspendConfig {
+    #[serde(default = "default_targets")]
+    pub targets: Vec<DeviceSelector>,
+    #[serde(default = "default_command")]
+    pub command: DeviceCommand,
+    /// Powers lighthouses that were active or in standby back into that state on resume
+    #[serde(default)]
+    pub restore: bool,
+}
+
+impl Default for SuspendConfig {
+    fn default() -> Self {
+        Self {
+            targets: default_targets(),
+            command: default_command(),
+            restore: false,
+        }
+    }
+}
+
+fn default_targets() -> Vec<DeviceSelector> {
+    vec![DeviceSelector::all()]
+}
+
+fn default_command() -> DeviceCommand {
+    DeviceCommand::Sleep
+}
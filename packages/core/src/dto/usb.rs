@@ -0,0 +1,57 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// USB vendor and product ID, written as `vvvv:pppp` in hexadecimal like `lsusb` does
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct UsbId {
+    pub vendor: u16,
+    pub product: u16,
+}
+
+/// Headsets recognized when no IDs are configured
+pub const KNOWN_HEADSETS: &[(UsbId, &str)] = &[
+    (UsbId::new(0x28de, 0x2300), "Valve Index"),
+    (UsbId::new(0x0bb4, 0x2c87), "HTC Vive"),
+    (UsbId::new(0x0bb4, 0x0309), "HTC Vive Pro"),
+];
+
+impl UsbId {
+    pub const fn new(vendor: u16, product: u16) -> Self {
+        Self { vendor, product }
+    }
+}
+
+impl Display for UsbId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04x}:{:04x}", self.vendor, self.product)
+    }
+}
+
+impl FromStr for UsbId {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || crate::Error::Vrlh("Invalid USB ID, expected vvvv:pppp!");
+        let (vendor, product) = s.trim().split_once(':').ok_or_else(invalid)?;
+        Ok(Self {
+            vendor: u16::from_str_radix(vendor, 16).map_err(|_| invalid())?,
+            product: u16::from_str_radix(product, 16).map_err(|_| invalid())?,
+        })
+    }
+}
+
+impl TryFrom<String> for UsbId {
+    type Error = crate::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<UsbId> for String {
+    fn from(value: UsbId) -> Self {
+        value.to_string()
+    }
+}
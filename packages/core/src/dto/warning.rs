@@ -0,0 +1,40 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{DeviceCommand, DeviceRemoteStatus};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[ts(export)]
+pub enum DeviceWarning {
+    /// Remote status changed in a way the pending command (if any) does not allow
+    UnexpectedTransition {
+        command: Option<DeviceCommand>,
+        from: DeviceRemoteStatus,
+        to: DeviceRemoteStatus,
+    },
+    /// Remote status stayed in an intermediate state for longer than expected,
+    /// or did not reach the target of the pending command in time
+    Stuck {
+        command: Option<DeviceCommand>,
+        status: DeviceRemoteStatus,
+        elapsed_secs: u32,
+    },
+}
+
+impl Display for DeviceWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnexpectedTransition { from, to, .. } => {
+                write!(f, "unexpected transition from {from} to {to}")
+            }
+            Self::Stuck {
+                status,
+                elapsed_secs,
+                ..
+            } => write!(f, "stuck in {status} for {elapsed_secs}s"),
+        }
+    }
+}
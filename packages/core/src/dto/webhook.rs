@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+
+use crate::LifecycleEventKind;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Events that trigger the webhook, empty for every event
+    #[serde(default)]
+    pub events: Vec<LifecycleEventKind>,
+    /// JSON body with `{{placeholder}}`s, the event itself is sent when unset
+    #[serde(default)]
+    pub template: Option<String>,
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled after every failed attempt
+    #[serde(default = "default_backoff_secs")]
+    pub backoff_secs: u64,
+}
+
+impl WebhookConfig {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            events: Vec::new(),
+            template: None,
+            max_attempts: default_max_attempts(),
+            backoff_secs: default_backoff_secs(),
+        }
+    }
+
+    pub fn accepts(&self, kind: LifecycleEventKind) -> bool {
+        self.events.is_empty() || self.events.contains(&kind)
+    }
+}
+
+fn default_max_attempts() -> u32 {
+    5
+}
+
+fn default_backoff_secs() -> u64 {
+    2
+}
+
+/// Outcome of a single attempt at delivering a webhook
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub url: String,
+    pub event: LifecycleEventKind,
+    /// Starts at 1 and increases with every retry
+    pub attempt: u32,
+    /// HTTP status of the response, if one was received
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    /// Seconds since the unix epoch
+    pub timestamp: u64,
+}
+
+impl WebhookDelivery {
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+}
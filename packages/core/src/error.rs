@@ -13,6 +13,20 @@ pub enum Error {
     JoinError,
     #[error("Channel closed early, cannot send event!")]
     ChannelClosed,
+    #[error("{}", .0)]
+    Io(#[from] std::io::Error),
+    #[error("{}", .0)]
+    Json(#[from] serde_json::Error),
+    #[error("{} ({})", .1, .0)]
+    Rpc(i64, String),
+    #[error("Invalid schedule: {}", .0)]
+    InvalidSchedule(String),
+    #[error("Invalid calendar: {}", .0)]
+    InvalidCalendar(String),
+    #[error("Invalid configuration: {}", .0)]
+    InvalidConfig(String),
+    #[error("Invalid rules: {}", .0)]
+    InvalidRules(String),
 }
 
 impl<T> From<SendError<T>> for Error {
@@ -0,0 +1,73 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Most recent records kept for inspection, dropping the oldest once full
+///
+/// Can be cloned and will retain references to the same records
+#[derive(Debug)]
+pub(crate) struct History<T> {
+    records: Arc<Mutex<VecDeque<T>>>,
+    capacity: usize,
+}
+
+impl<T> Clone for History<T> {
+    fn clone(&self) -> Self {
+        Self {
+            records: Arc::clone(&self.records),
+            capacity: self.capacity,
+        }
+    }
+}
+
+impl<T: Clone> History<T> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            records: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    /// Every kept record, oldest first
+    pub(crate) fn records(&self) -> Vec<T> {
+        self.lock().iter().cloned().collect()
+    }
+
+    pub(crate) fn push(&self, record: T) {
+        let mut records = self.lock();
+        if records.len() == self.capacity {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, VecDeque<T>> {
+        self.records
+            .lock()
+            .expect("History mutex must not be poisoned")
+    }
+}
+
+/// Seconds since the unix epoch, as records carry their time
+pub(crate) fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_the_oldest_records_once_full() {
+        let history = History::new(3);
+        let shared = history.clone();
+        for record in 1..=5 {
+            shared.push(record);
+        }
+        assert_eq!(history.records(), [3, 4, 5]);
+    }
+}
@@ -0,0 +1,248 @@
+use std::{process::Stdio, sync::Arc, time::Duration};
+
+use tokio::{
+    io::AsyncWriteExt, process::Command, sync::broadcast::error::RecvError, time::timeout,
+};
+
+use crate::{
+    history::{unix_timestamp, History},
+    rules::snapshot_devices,
+    DeviceList, HookConfig, HookRun, LifecycleEvent, LifecycleTracker,
+};
+
+/// Number of hook runs kept for inspection
+const RUN_HISTORY: usize = 100;
+/// Captured output beyond this many bytes per stream is dropped
+const OUTPUT_LIMIT: usize = 64 * 1024;
+
+/// Runs user executables on lifecycle events
+///
+/// The event's `DeviceInfo` is written to stdin as JSON, a single object for device events
+/// and an array for everything else, and summarized in `VRLH_*` environment variables
+///
+/// Can be cloned and will retain references to the same run history
+#[derive(Clone, Debug)]
+pub struct Hooks {
+    hooks: Arc<Vec<HookConfig>>,
+    runs: History<HookRun>,
+}
+
+impl Hooks {
+    pub fn new(hooks: Vec<HookConfig>) -> Self {
+        Self {
+            hooks: Arc::new(hooks),
+            runs: History::new(RUN_HISTORY),
+        }
+    }
+
+    /// Most recent hook runs, oldest first
+    pub fn runs(&self) -> Vec<HookRun> {
+        self.runs.records()
+    }
+
+    /// Runs hooks for events from the device list until it is dropped
+    pub async fn run(&self, devices: &DeviceList) {
+        let mut events = devices.subscribe();
+        let mut tracker = LifecycleTracker::default();
+        loop {
+            match events.recv().await {
+                Ok(event) => {
+                    for event in tracker.update(&event) {
+                        self.dispatch(&event);
+                    }
+                }
+                Err(RecvError::Lagged(_)) => {
+                    for event in tracker.resync(&snapshot_devices(devices)) {
+                        self.dispatch(&event);
+                    }
+                }
+                Err(RecvError::Closed) => return,
+            }
+        }
+    }
+
+    /// Starts every hook accepting the event in the background
+    pub fn dispatch(&self, event: &LifecycleEvent) {
+        for hook in self.hooks.iter().filter(|hook| hook.accepts(event.kind())) {
+            let hooks = self.clone();
+            let hook = hook.clone();
+            let event = event.clone();
+            tokio::spawn(async move {
+                let run = execute(&hook, &event).await;
+                hooks.runs.push(run);
+            });
+        }
+    }
+}
+
+/// Runs a single hook to completion, killing it once its timeout passes
+pub async fn execute(hook: &HookConfig, event: &LifecycleEvent) -> HookRun {
+    let mut run = HookRun {
+        command: hook.command.clone(),
+        event: event.kind(),
+        exit_code: None,
+        stdout: String::new(),
+        stderr: String::new(),
+        error: None,
+        timestamp: unix_timestamp(),
+    };
+
+    let input = match event.device() {
+        Some(device) => serde_json::to_vec(device),
+        None => serde_json::to_vec(event.devices()),
+    }
+    .unwrap_or_default();
+    let mut command = Command::new(&hook.command);
+    command
+        .args(&hook.args)
+        .envs(environment(event))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(err) => {
+            run.error = Some(format!("Failed to start: {err}"));
+            return run;
+        }
+    };
+    let stdin = child.stdin.take();
+    let write = async move {
+        if let Some(mut stdin) = stdin {
+            // Hooks are free to ignore stdin and exit before reading it
+            let _ = stdin.write_all(&input).await;
+        }
+    };
+
+    // Writing counts against the limit too, a hook that never reads may fill the pipe
+    let limit = Duration::from_secs(hook.timeout_secs);
+    let finished = async { tokio::join!(write, child.wait_with_output()).1 };
+    match timeout(limit, finished).await {
+        Ok(Ok(output)) => {
+            run.exit_code = output.status.code();
+            run.stdout = capture(&output.stdout);
+            run.stderr = capture(&output.stderr);
+            if !output.status.success() {
+                run.error = Some(format!("Exited with {}", output.status));
+            }
+        }
+        Ok(Err(err)) => run.error = Some(err.to_string()),
+        Err(_) => run.error = Some(format!("Timed out after {}s", hook.timeout_secs)),
+    }
+    run
+}
+
+fn environment(event: &LifecycleEvent) -> Vec<(&'static str, String)> {
+    let mut env = vec![
+        ("VRLH_EVENT", event.kind().to_string()),
+        ("VRLH_DEVICE_COUNT", event.devices().len().to_string()),
+    ];
+    if let Some(reason) = event.reason() {
+        env.push(("VRLH_REASON", reason.to_string()));
+    }
+    if let Some(device) = event.device() {
+        env.push(("VRLH_DEVICE_NAME", device.name.clone()));
+        env.push(("VRLH_DEVICE_ADDR", device.addr.clone()));
+        if let Some(local) = &device.local {
+            env.push(("VRLH_DEVICE_LOCAL", local.to_string()));
+        }
+        if let Some(remote) = &device.remote {
+            env.push(("VRLH_DEVICE_REMOTE", remote.to_string()));
+        }
+        env.push((
+            "VRLH_DEVICE",
+            serde_json::to_string(device).unwrap_or_default(),
+        ));
+    }
+    env
+}
+
+fn capture(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(&bytes[..bytes.len().min(OUTPUT_LIMIT)]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::{DeviceInfo, DeviceRemoteStatus};
+
+    fn shell(script: &str) -> HookConfig {
+        HookConfig {
+            args: vec!["-c".into(), script.into()],
+            ..HookConfig::new("sh")
+        }
+    }
+
+    fn changed() -> LifecycleEvent {
+        LifecycleEvent::DeviceChanged {
+            device: DeviceInfo::fake("LHB-A", "AA:AA:AA:AA:AA:AA", &DeviceRemoteStatus::Active),
+        }
+    }
+
+    #[tokio::test]
+    async fn writes_the_event_to_stdin() {
+        let event = changed();
+        let run = execute(&shell("cat"), &event).await;
+        assert!(run.succeeded(), "{run:?}");
+        let device = serde_json::from_str::<serde_json::Value>(&run.stdout).unwrap();
+        assert_eq!(device["name"], "LHB-A");
+
+        let event = LifecycleEvent::AllOff {
+            devices: vec![DeviceInfo::fake(
+                "LHB-B",
+                "BB:00",
+                &DeviceRemoteStatus::Stopped,
+            )],
+        };
+        let run = execute(&shell("cat"), &event).await;
+        let devices = serde_json::from_str::<serde_json::Value>(&run.stdout).unwrap();
+        assert_eq!(devices[0]["addr"], "BB:00");
+    }
+
+    #[tokio::test]
+    async fn summarizes_the_event_in_the_environment() {
+        let script = r#"printf '%s|' "$VRLH_EVENT" "$VRLH_DEVICE_COUNT" "$VRLH_DEVICE_NAME" \
+            "$VRLH_DEVICE_ADDR" "$VRLH_DEVICE_REMOTE" "${VRLH_REASON-unset}""#;
+        let run = execute(&shell(script), &changed()).await;
+        assert_eq!(
+            run.stdout,
+            "DEVICE_CHANGED|1|LHB-A|AA:AA:AA:AA:AA:AA|ACTIVE|unset|"
+        );
+    }
+
+    #[tokio::test]
+    async fn captures_exit_codes_and_output() {
+        let run = execute(&shell("echo out; echo err >&2; exit 3"), &changed()).await;
+        assert_eq!(run.exit_code, Some(3));
+        assert_eq!(
+            (run.stdout.as_str(), run.stderr.as_str()),
+            ("out\n", "err\n")
+        );
+        assert_eq!(run.error.as_deref(), Some("Exited with exit status: 3"));
+
+        let run = execute(&HookConfig::new("/nonexistent/hook"), &changed()).await;
+        assert_eq!(run.exit_code, None);
+        assert!(run.error.unwrap().starts_with("Failed to start"));
+    }
+
+    #[tokio::test]
+    async fn kills_hooks_that_run_too_long() {
+        // More than a pipe holds, so writing blocks as long as the hook does not read
+        let devices = (0..2000)
+            .map(|i| DeviceInfo::fake(&format!("LHB-{i}"), "AA:00", &DeviceRemoteStatus::Active))
+            .collect();
+        let event = LifecycleEvent::ScanFinished { devices };
+        let hook = HookConfig {
+            timeout_secs: 1,
+            ..shell("sleep 30")
+        };
+        let started = Instant::now();
+        let run = execute(&hook, &event).await;
+        assert_eq!(run.error.as_deref(), Some("Timed out after 1s"));
+        assert_eq!(run.exit_code, None);
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+}
@@ -0,0 +1,16 @@
+use std::{path::Path, time::Duration};
+
+use tokio::process::Command;
+
+/// Runs an `xprintidle` compatible executable and parses the milliseconds it prints
+pub async fn command_idle_time(command: &Path, args: &[String]) -> crate::Result<Duration> {
+    let output = Command::new(command).args(args).output().await?;
+    if !output.status.success() {
+        return Err(crate::Error::Vrlh("Idle time command failed!"));
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .map(Duration::from_millis)
+        .map_err(|_| crate::Error::Vrlh("Idle time command printed an invalid duration!"))
+}
@@ -1,20 +1,59 @@
+mod calendar;
+mod config;
 mod constants;
 mod device;
 mod device_list;
 mod dto;
 mod error;
+mod history;
+mod hooks;
+mod idle;
+mod lifecycle;
+mod openxr;
+mod policy;
+mod process;
+mod remote_state;
+mod rpc;
+mod rules;
+mod schedule;
+#[cfg(feature = "scripting")]
+mod scripts;
+mod selector;
+mod steamvr;
 mod traits;
+mod usb;
+#[cfg(feature = "webhooks")]
+mod webhook;
 
 use btleplug::{
     api::Manager as _,
     platform::{Adapter, Manager},
 };
 
+pub use calendar::*;
+pub use config::*;
 pub use device::Device;
 pub use device_list::DeviceList;
 pub use dto::*;
 pub use error::*;
+pub use hooks::*;
+pub use idle::*;
+pub use lifecycle::*;
+pub use openxr::*;
+pub use policy::*;
+pub use process::*;
+pub use remote_state::*;
+pub use rpc::*;
+pub use rules::*;
+pub use schedule::*;
+#[cfg(feature = "scripting")]
+pub use scripts::*;
+pub use selector::*;
+pub use steamvr::*;
 pub use traits::*;
+pub use usb::*;
+#[cfg(feature = "webhooks")]
+pub use webhook::*;
 
 pub async fn get_default_adapter() -> crate::Result<Adapter> {
     Manager::new()
@@ -0,0 +1,250 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    DeviceInfo, DeviceListEvent, DeviceLocalStatus, DeviceRemoteStatus, DeviceWarning,
+    LifecycleEvent,
+};
+
+/// Folds device list events into the latest state of every lighthouse and reports
+/// lifecycle events as they happen, room wide events only fire on the edge into that state
+#[derive(Clone, Debug, Default)]
+pub struct LifecycleTracker {
+    devices: BTreeMap<String, DeviceInfo>,
+    all_active: bool,
+    all_off: bool,
+}
+
+impl LifecycleTracker {
+    /// Latest known state of every lighthouse, sorted by address
+    pub fn devices(&self) -> Vec<DeviceInfo> {
+        self.devices.values().cloned().collect()
+    }
+
+    pub fn update(&mut self, event: &DeviceListEvent) -> Vec<LifecycleEvent> {
+        match event {
+            DeviceListEvent::Device(info) => self.update_device(info),
+            DeviceListEvent::ScanFinished => vec![LifecycleEvent::ScanFinished {
+                devices: self.devices(),
+            }],
+            DeviceListEvent::Status(_) | DeviceListEvent::ScanStarted { .. } => Vec::new(),
+        }
+    }
+
+    /// Catches up with the current state of every lighthouse after events were missed
+    ///
+    /// Failures are states in a snapshot, so they are only reported for lighthouses that changed
+    pub fn resync(&mut self, devices: &[DeviceInfo]) -> Vec<LifecycleEvent> {
+        let mut events = Vec::new();
+        for info in devices {
+            let updates = self.update_device(info);
+            let changed = updates
+                .iter()
+                .any(|event| matches!(event, LifecycleEvent::DeviceChanged { .. }));
+            events.extend(
+                updates.into_iter().filter(|event| {
+                    changed || !matches!(event, LifecycleEvent::DeviceFailed { .. })
+                }),
+            );
+        }
+        events
+    }
+
+    fn update_device(&mut self, info: &DeviceInfo) -> Vec<LifecycleEvent> {
+        if matches!(info.local, Some(DeviceLocalStatus::Ignored)) {
+            return Vec::new();
+        }
+
+        let previous = self
+            .devices
+            .get(&info.addr)
+            .map(|device| (device.local.clone(), device.remote.clone()));
+        let device = self
+            .devices
+            .entry(info.addr.clone())
+            .or_insert_with(|| info.clone());
+        if let Some(local) = &info.local {
+            device.local = Some(local.clone());
+        }
+        if let Some(remote) = &info.remote {
+            device.remote = Some(remote.clone());
+        }
+        device.warnings.clone_from(&info.warnings);
+        let device = device.clone();
+
+        let mut events = Vec::new();
+        if previous.is_none_or(|previous| previous != (device.local.clone(), device.remote.clone()))
+        {
+            events.push(LifecycleEvent::DeviceChanged {
+                device: device.clone(),
+            });
+        }
+        if let Some(reason) = failure(info) {
+            events.push(LifecycleEvent::DeviceFailed { device, reason });
+        }
+
+        let remotes = self.devices.values().map(|device| device.remote.as_ref());
+        let all_active = remotes
+            .clone()
+            .all(|remote| matches!(remote, Some(DeviceRemoteStatus::Active)));
+        let all_off = remotes.clone().all(|remote| {
+            matches!(
+                remote,
+                Some(DeviceRemoteStatus::Standby | DeviceRemoteStatus::Stopped)
+            )
+        });
+        if all_active && !self.all_active {
+            events.push(LifecycleEvent::AllActive {
+                devices: self.devices(),
+            });
+        }
+        if all_off && !self.all_off {
+            events.push(LifecycleEvent::AllOff {
+                devices: self.devices(),
+            });
+        }
+        self.all_active = all_active;
+        self.all_off = all_off;
+        events
+    }
+}
+
+/// Reason the update signals a failure, only looking at what the update itself carries
+/// so a failure is reported once rather than on every following update
+fn failure(info: &DeviceInfo) -> Option<String> {
+    match (&info.local, &info.remote) {
+        (Some(DeviceLocalStatus::FailConnection), _) => Some("Could not connect".into()),
+        (Some(DeviceLocalStatus::FailVerify), _) => Some("Could not verify lighthouse".into()),
+        (Some(DeviceLocalStatus::Error(err)), _) => Some(err.clone()),
+        (_, Some(DeviceRemoteStatus::Unknown(_))) => {
+            Some(format!("Reported unknown status {}", info.remote.as_ref()?))
+        }
+        _ => info
+            .warnings
+            .iter()
+            .find(|warning| matches!(warning, DeviceWarning::Stuck { .. }))
+            .map(|warning| format!("Lighthouse {warning}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DeviceCommand, LifecycleEventKind};
+
+    fn device(addr: &str, remote: &DeviceRemoteStatus) -> DeviceInfo {
+        DeviceInfo::fake(&format!("LHB-{addr}"), addr, remote)
+    }
+
+    fn kinds(events: &[LifecycleEvent]) -> Vec<LifecycleEventKind> {
+        events.iter().map(LifecycleEvent::kind).collect()
+    }
+
+    fn update(tracker: &mut LifecycleTracker, info: DeviceInfo) -> Vec<LifecycleEventKind> {
+        kinds(&tracker.update(&DeviceListEvent::Device(info)))
+    }
+
+    #[test]
+    fn reports_changes_once() {
+        use LifecycleEventKind::{AllOff, DeviceChanged};
+
+        let mut tracker = LifecycleTracker::default();
+        let standby = device("A", &DeviceRemoteStatus::Standby);
+        assert_eq!(
+            update(&mut tracker, standby.clone()),
+            [DeviceChanged, AllOff]
+        );
+        assert_eq!(update(&mut tracker, standby), []);
+
+        // Partial updates keep the other status
+        let mut connected = device("A", &DeviceRemoteStatus::Standby);
+        connected.remote = None;
+        connected.local = Some(DeviceLocalStatus::Connected);
+        assert_eq!(update(&mut tracker, connected), [DeviceChanged]);
+        let [info] = tracker.devices().try_into().unwrap();
+        assert_eq!(info.local, Some(DeviceLocalStatus::Connected));
+        assert_eq!(info.remote, Some(DeviceRemoteStatus::Standby));
+
+        let mut ignored = device("B", &DeviceRemoteStatus::Active);
+        ignored.local = Some(DeviceLocalStatus::Ignored);
+        assert_eq!(update(&mut tracker, ignored), []);
+        assert_eq!(tracker.devices().len(), 1);
+    }
+
+    #[test]
+    fn fires_room_wide_events_on_the_edge() {
+        use LifecycleEventKind::{AllActive, AllOff, DeviceChanged};
+
+        let mut tracker = LifecycleTracker::default();
+        update(&mut tracker, device("A", &DeviceRemoteStatus::Standby));
+        let b = update(&mut tracker, device("B", &DeviceRemoteStatus::Stopped));
+        assert_eq!(b, [DeviceChanged]);
+
+        let a = update(&mut tracker, device("A", &DeviceRemoteStatus::Active));
+        assert_eq!(a, [DeviceChanged]);
+        let b = update(&mut tracker, device("B", &DeviceRemoteStatus::Active));
+        assert_eq!(b, [DeviceChanged, AllActive]);
+        let spinup = update(&mut tracker, device("B", &DeviceRemoteStatus::Spinup));
+        assert_eq!(spinup, [DeviceChanged]);
+        let b = update(&mut tracker, device("B", &DeviceRemoteStatus::Active));
+        assert_eq!(b, [DeviceChanged, AllActive]);
+
+        update(&mut tracker, device("A", &DeviceRemoteStatus::Standby));
+        let b = update(&mut tracker, device("B", &DeviceRemoteStatus::Standby));
+        assert_eq!(b, [DeviceChanged, AllOff]);
+
+        let events = tracker.update(&DeviceListEvent::ScanFinished);
+        assert!(
+            matches!(&events[..], [LifecycleEvent::ScanFinished { devices }] if devices.len() == 2)
+        );
+    }
+
+    #[test]
+    fn reports_failures_carried_by_the_update() {
+        use LifecycleEventKind::{DeviceChanged, DeviceFailed};
+
+        let mut tracker = LifecycleTracker::default();
+        update(&mut tracker, device("A", &DeviceRemoteStatus::Standby));
+
+        let mut failed = device("A", &DeviceRemoteStatus::Standby);
+        failed.local = Some(DeviceLocalStatus::FailConnection);
+        let events = tracker.update(&DeviceListEvent::Device(failed));
+        assert_eq!(kinds(&events), [DeviceChanged, DeviceFailed]);
+        assert_eq!(events[1].reason(), Some("Could not connect"));
+
+        let unknown = device("A", &DeviceRemoteStatus::Unknown(vec![0x7f]));
+        assert_eq!(update(&mut tracker, unknown), [DeviceChanged, DeviceFailed]);
+
+        let mut stuck = device("A", &DeviceRemoteStatus::Spinup);
+        stuck.warnings = vec![DeviceWarning::Stuck {
+            command: Some(DeviceCommand::Activate),
+            status: DeviceRemoteStatus::Spinup,
+            elapsed_secs: 30,
+        }];
+        let events = tracker.update(&DeviceListEvent::Device(stuck));
+        assert_eq!(kinds(&events), [DeviceChanged, DeviceFailed]);
+        assert!(events[1].reason().unwrap().starts_with("Lighthouse "));
+    }
+
+    #[test]
+    fn resyncs_after_missed_events() {
+        use LifecycleEventKind::{AllOff, DeviceChanged};
+
+        let mut tracker = LifecycleTracker::default();
+        update(&mut tracker, device("A", &DeviceRemoteStatus::Active));
+        update(&mut tracker, device("B", &DeviceRemoteStatus::Active));
+        let mut failed = device("C", &DeviceRemoteStatus::Standby);
+        failed.local = Some(DeviceLocalStatus::FailConnection);
+        update(&mut tracker, failed.clone());
+
+        // A and B went to standby while the events were missed
+        let snapshot = [
+            device("A", &DeviceRemoteStatus::Standby),
+            device("B", &DeviceRemoteStatus::Standby),
+            failed,
+        ];
+        let events = tracker.resync(&snapshot);
+        assert_eq!(kinds(&events), [DeviceChanged, DeviceChanged, AllOff]);
+        // The connection failure was already reported and is not repeated
+        assert_eq!(kinds(&tracker.resync(&snapshot)), []);
+    }
+}
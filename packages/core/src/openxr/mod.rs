@@ -0,0 +1,297 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::Deserialize;
+use tokio::sync::watch;
+
+use crate::{poll_presence, OpenXrConfig, OpenXrRuntime, ProcessWatcher};
+
+const ACTIVE_RUNTIME: &str = "openxr/1/active_runtime.json";
+
+/// Where the OpenXR loader looks for the active runtime, resolved below a filesystem root
+#[derive(Clone, Debug)]
+pub struct OpenXrEnvironment {
+    root: PathBuf,
+    /// Value of `XR_RUNTIME_JSON`, which overrides every other location
+    pub runtime_json: Option<PathBuf>,
+    pub config_home: PathBuf,
+    pub config_dirs: Vec<PathBuf>,
+}
+
+impl OpenXrEnvironment {
+    pub fn from_env() -> Self {
+        let config_home = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .unwrap_or_default();
+        let config_dirs = env::var_os("XDG_CONFIG_DIRS").map_or_else(
+            || vec![PathBuf::from("/etc/xdg")],
+            |dirs| env::split_paths(&dirs).collect(),
+        );
+        Self {
+            root: PathBuf::from("/"),
+            runtime_json: env::var_os("XR_RUNTIME_JSON").map(PathBuf::from),
+            config_home,
+            config_dirs,
+        }
+    }
+
+    /// Resolves every path below another root, such as a fake tree
+    #[must_use]
+    pub fn with_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = root.into();
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Candidate manifest locations in the order the loader checks them
+    pub fn manifest_paths(&self) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        paths.extend(self.runtime_json.clone());
+        paths.push(self.config_home.join(ACTIVE_RUNTIME));
+        paths.extend(self.config_dirs.iter().map(|dir| dir.join(ACTIVE_RUNTIME)));
+        paths.push(PathBuf::from("/etc").join(ACTIVE_RUNTIME));
+        paths.into_iter().map(|path| self.resolve(&path)).collect()
+    }
+
+    /// Runtime named by the first manifest found
+    pub fn active_runtime(&self) -> Option<OpenXrRuntime> {
+        self.manifest_paths()
+            .iter()
+            .find_map(|path| read_manifest(path).ok())
+            .flatten()
+    }
+
+    fn resolve(&self, path: &Path) -> PathBuf {
+        self.root.join(path.strip_prefix("/").unwrap_or(path))
+    }
+}
+
+#[derive(Deserialize)]
+struct Manifest {
+    runtime: ManifestRuntime,
+}
+
+#[derive(Deserialize)]
+struct ManifestRuntime {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    library_path: String,
+}
+
+/// Identifies the runtime by its name and library, falling back to the file the manifest links to
+pub fn read_manifest(path: &Path) -> crate::Result<Option<OpenXrRuntime>> {
+    let manifest = serde_json::from_str::<Manifest>(&fs::read_to_string(path)?)?;
+    let target = fs::canonicalize(path).unwrap_or_default();
+    let haystack = format!(
+        "{} {} {}",
+        manifest.runtime.name,
+        manifest.runtime.library_path,
+        target.display()
+    )
+    .to_ascii_lowercase();
+    let runtime = if haystack.contains("monado") {
+        Some(OpenXrRuntime::Monado)
+    } else if haystack.contains("wivrn") {
+        Some(OpenXrRuntime::WiVRn)
+    } else if haystack.contains("alvr") {
+        Some(OpenXrRuntime::Alvr)
+    } else if haystack.contains("steamvr") || haystack.contains("vrclient") {
+        Some(OpenXrRuntime::SteamVr)
+    } else {
+        None
+    };
+    Ok(runtime)
+}
+
+/// Follows the active OpenXR runtime and reports whether a session of an enabled runtime is running
+#[derive(Clone, Debug)]
+pub struct OpenXrWatcher {
+    env: OpenXrEnvironment,
+    config: OpenXrConfig,
+    interval: Duration,
+}
+
+impl OpenXrWatcher {
+    pub fn new(env: OpenXrEnvironment, config: OpenXrConfig) -> Self {
+        Self {
+            env,
+            config,
+            interval: Duration::from_secs(2),
+        }
+    }
+
+    #[must_use]
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Enabled runtimes that should be followed given the active one
+    pub fn followed(&self) -> Vec<OpenXrRuntime> {
+        let followed = match self.env.active_runtime() {
+            Some(OpenXrRuntime::SteamVr) => vec![OpenXrRuntime::SteamVr, OpenXrRuntime::Alvr],
+            Some(runtime) => vec![runtime],
+            None => Vec::new(),
+        };
+        followed
+            .into_iter()
+            .filter(|runtime| self.config.is_enabled(*runtime))
+            .collect()
+    }
+
+    /// Followed runtime with a running session, if any
+    pub fn running(&self) -> Option<OpenXrRuntime> {
+        let proc = self.env.resolve(Path::new("/proc"));
+        self.followed().into_iter().find(|runtime| {
+            ProcessWatcher::new(runtime.processes().iter().copied())
+                .with_root(&proc)
+                .is_running()
+        })
+    }
+
+    /// Polls in the background until every receiver is dropped, the active runtime is
+    /// read again on every poll so switching runtimes needs no restart
+    pub fn watch(self) -> watch::Receiver<bool> {
+        poll_presence(self.interval, move || self.running().is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn write(root: &Path, path: &str, contents: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    /// A tree with the given runtime active for the user and the given processes running
+    fn tree(name: &str, library_path: &str, processes: &[&str]) -> TempDir {
+        let root = tempfile::tempdir().unwrap();
+        let manifest = serde_json::json!({
+            "file_format_version": "1.0.0",
+            "runtime": { "name": name, "library_path": library_path },
+        });
+        write(
+            root.path(),
+            "home/user/.config/openxr/1/active_runtime.json",
+            &manifest.to_string(),
+        );
+        for (pid, process) in processes.iter().enumerate() {
+            write(root.path(), &format!("proc/{}/comm", pid + 100), process);
+        }
+        root
+    }
+
+    fn env(root: &TempDir) -> OpenXrEnvironment {
+        OpenXrEnvironment {
+            root: PathBuf::from("/"),
+            runtime_json: None,
+            config_home: PathBuf::from("/home/user/.config"),
+            config_dirs: vec![PathBuf::from("/etc/xdg")],
+        }
+        .with_root(root.path())
+    }
+
+    fn watcher(root: &TempDir, runtimes: &[OpenXrRuntime]) -> OpenXrWatcher {
+        let config = OpenXrConfig {
+            runtimes: runtimes.to_vec(),
+        };
+        OpenXrWatcher::new(env(root), config)
+    }
+
+    #[test]
+    fn reads_the_active_runtime() {
+        for (name, library_path, runtime) in [
+            (
+                "Monado",
+                "/usr/lib/libopenxr_monado.so",
+                OpenXrRuntime::Monado,
+            ),
+            (
+                "WiVRn",
+                "/usr/lib/wivrn/libopenxr_wivrn.so",
+                OpenXrRuntime::WiVRn,
+            ),
+            (
+                "",
+                "../steamvr/bin/linux64/vrclient.so",
+                OpenXrRuntime::SteamVr,
+            ),
+        ] {
+            let root = tree(name, library_path, &[]);
+            assert_eq!(env(&root).active_runtime(), Some(runtime));
+        }
+        let root = tree("Unknown", "/usr/lib/libopenxr_unknown.so", &[]);
+        assert_eq!(env(&root).active_runtime(), None);
+    }
+
+    #[test]
+    fn runtime_json_overrides_the_config_directories() {
+        let root = tree("Monado", "libopenxr_monado.so", &[]);
+        let manifest = r#"{ "runtime": { "name": "WiVRn" } }"#;
+        write(root.path(), "opt/wivrn.json", manifest);
+        let mut env = env(&root);
+        env.runtime_json = Some(PathBuf::from("/opt/wivrn.json"));
+        assert_eq!(env.active_runtime(), Some(OpenXrRuntime::WiVRn));
+    }
+
+    #[test]
+    fn follows_sessions_of_enabled_runtimes() {
+        let default = OpenXrConfig::default().runtimes;
+        let root = tree("Monado", "libopenxr_monado.so", &["monado-service"]);
+        assert_eq!(
+            watcher(&root, &default).running(),
+            Some(OpenXrRuntime::Monado)
+        );
+        assert_eq!(watcher(&root, &[OpenXrRuntime::WiVRn]).running(), None);
+
+        let root = tree("WiVRn", "libopenxr_wivrn.so", &["wivrn-server"]);
+        assert_eq!(
+            watcher(&root, &default).running(),
+            Some(OpenXrRuntime::WiVRn)
+        );
+        assert_eq!(watcher(&root, &[OpenXrRuntime::Monado]).running(), None);
+    }
+
+    #[test]
+    fn ignores_sessions_of_inactive_runtimes() {
+        let root = tree("Monado", "libopenxr_monado.so", &["wivrn-server"]);
+        let watcher = watcher(&root, &OpenXrConfig::default().runtimes);
+        assert_eq!(watcher.followed(), [OpenXrRuntime::Monado]);
+        assert_eq!(watcher.running(), None);
+    }
+
+    #[test]
+    fn follows_alvr_through_steamvr() {
+        let steamvr = ("SteamVR", "vrclient.so");
+        let default = OpenXrConfig::default().runtimes;
+
+        // The dashboard stays open without a headset
+        let root = tree(steamvr.0, steamvr.1, &["alvr_dashboard"]);
+        assert_eq!(watcher(&root, &default).running(), None);
+
+        let root = tree(steamvr.0, steamvr.1, &["alvr_dashboard", "vrserver"]);
+        assert_eq!(watcher(&root, &default).followed(), [OpenXrRuntime::Alvr]);
+        assert_eq!(
+            watcher(&root, &default).running(),
+            Some(OpenXrRuntime::Alvr)
+        );
+        assert_eq!(watcher(&root, &[OpenXrRuntime::Monado]).running(), None);
+        assert_eq!(
+            watcher(&root, &OpenXrRuntime::ALL).running(),
+            Some(OpenXrRuntime::SteamVr)
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use std::time::Duration;
+
+use tokio::{
+    sync::watch,
+    time::{interval, sleep},
+};
+
+use crate::{Aliases, DeviceCommand, DeviceList, DeviceListEvent, PowerPolicy};
+
+/// Applies the policy to a session presence until its watcher stops
+///
+/// Nothing is sent for the initial state unless a session is already running, and `on_stop`
+/// is held back while `active_elsewhere` reports a session of another source
+pub async fn run_power_policy(
+    devices: DeviceList,
+    aliases: Aliases,
+    policy: PowerPolicy,
+    source: String,
+    mut presence: watch::Receiver<bool>,
+    active_elsewhere: impl Fn() -> bool,
+) {
+    let grace = Duration::from_secs(policy.grace_secs);
+    loop {
+        if presence.wait_for(|present| *present).await.is_err() {
+            return;
+        }
+        let message = format!("{source} became active");
+        apply(&devices, &aliases, &policy, &policy.on_start, message).await;
+
+        loop {
+            if presence.wait_for(|present| !*present).await.is_err() {
+                return;
+            }
+            tokio::select! {
+                () = sleep(grace) => break,
+                resumed = presence.wait_for(|present| *present) => if resumed.is_err() {
+                    return;
+                },
+            }
+        }
+        if active_elsewhere() {
+            devices.publish(DeviceListEvent::Status(format!(
+                "{source} became inactive, keeping lighthouses on for another session"
+            )));
+            continue;
+        }
+        let message = format!("{source} became inactive");
+        apply(&devices, &aliases, &policy, &policy.on_stop, message).await;
+    }
+}
+
+async fn apply(
+    devices: &DeviceList,
+    aliases: &Aliases,
+    policy: &PowerPolicy,
+    command: &DeviceCommand,
+    message: String,
+) {
+    let selected = devices.select(&policy.targets, aliases);
+    if selected.is_empty() {
+        return;
+    }
+    devices.publish(DeviceListEvent::Status(format!(
+        r#"{message}, sending "{command}" to {} lighthouses"#,
+        selected.len()
+    )));
+    let mut rx = devices.start_power(selected, command);
+    while rx.recv().await.is_some() {}
+}
+
+/// Calls `check` on every tick until every receiver is dropped, sending only changes
+pub fn poll_presence<F>(period: Duration, check: F) -> watch::Receiver<bool>
+where
+    F: Fn() -> bool + Send + 'static,
+{
+    let (tx, rx) = watch::channel(check());
+    tokio::spawn(async move {
+        let mut ticks = interval(period);
+        loop {
+            tokio::select! {
+                _ = ticks.tick() => {}
+                () = tx.closed() => return,
+            }
+            let present = check();
+            tx.send_if_modified(|current| {
+                let changed = *current != present;
+                *current = present;
+                changed
+            });
+        }
+    });
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::broadcast;
+
+    use super::*;
+
+    async fn next_status(events: &mut broadcast::Receiver<DeviceListEvent>) -> String {
+        loop {
+            if let DeviceListEvent::Status(status) = events.recv().await.unwrap() {
+                return status;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn holds_back_on_stop_while_another_session_runs() {
+        let devices = DeviceList::detached();
+        let mut events = devices.subscribe();
+        let policy = PowerPolicy {
+            grace_secs: 0,
+            ..PowerPolicy::default()
+        };
+        let (tx, rx) = watch::channel(true);
+        tokio::spawn(run_power_policy(
+            devices,
+            Aliases::new(),
+            policy,
+            "Headset".into(),
+            rx,
+            || true,
+        ));
+
+        // Lets the policy see the session start before it stops
+        tokio::task::yield_now().await;
+        tx.send(false).unwrap();
+        assert_eq!(
+            next_status(&mut events).await,
+            "Headset became inactive, keeping lighthouses on for another session"
+        );
+    }
+}
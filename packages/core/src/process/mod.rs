@@ -0,0 +1,89 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use tokio::sync::watch;
+
+use crate::poll_presence;
+
+/// Polls a procfs tree for processes with any of the given names
+#[derive(Clone, Debug)]
+pub struct ProcessWatcher {
+    root: PathBuf,
+    names: Vec<String>,
+    interval: Duration,
+}
+
+impl ProcessWatcher {
+    pub fn new<I, S>(names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            root: PathBuf::from("/proc"),
+            names: names.into_iter().map(Into::into).collect(),
+            interval: Duration::from_secs(2),
+        }
+    }
+
+    /// Reads processes from another procfs mount or a fake tree instead of `/proc`
+    #[must_use]
+    pub fn with_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = root.into();
+        self
+    }
+
+    #[must_use]
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Watched names with at least one running process
+    pub fn running(&self) -> Vec<String> {
+        let Ok(entries) = fs::read_dir(&self.root) else {
+            return Vec::new();
+        };
+        let mut running = entries
+            .flatten()
+            .filter(|entry| entry.file_name().to_string_lossy().parse::<u32>().is_ok())
+            .flat_map(|entry| process_names(&entry.path()))
+            .filter(|name| self.names.contains(name))
+            .collect::<Vec<_>>();
+        running.sort();
+        running.dedup();
+        running
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.running().is_empty()
+    }
+
+    /// Polls in the background until every receiver is dropped
+    pub fn watch(self) -> watch::Receiver<bool> {
+        poll_presence(self.interval, move || self.is_running())
+    }
+}
+
+/// `comm` is cut off at 15 bytes, so the executable name from `cmdline` is checked as well
+fn process_names(dir: &Path) -> Vec<String> {
+    let mut names = Vec::new();
+    if let Ok(comm) = fs::read_to_string(dir.join("comm")) {
+        names.push(comm.trim_end().to_string());
+    }
+    if let Ok(cmdline) = fs::read(dir.join("cmdline")) {
+        let argv0 = cmdline.split(|byte| *byte == 0).next().unwrap_or_default();
+        let argv0 = String::from_utf8_lossy(argv0);
+        if let Some(name) = Path::new(argv0.as_ref()).file_name() {
+            names.push(name.to_string_lossy().into_owned());
+        }
+    }
+    names
+}
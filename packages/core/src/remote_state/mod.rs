@@ -0,0 +1,298 @@
+use std::time::{Duration, Instant};
+
+use crate::{DeviceCommand, DeviceRemoteStatus, DeviceWarning, TimeoutSettings};
+
+/// Tracks the last known remote status of a device and validates every reported change
+/// against the path the pending command is expected to take
+#[derive(Clone, Debug)]
+pub struct RemoteStateMachine {
+    current: DeviceRemoteStatus,
+    since: Instant,
+    command: Option<DeviceCommand>,
+    /// When the pending command was sent
+    began: Instant,
+    timeouts: TimeoutSettings,
+}
+
+impl Default for RemoteStateMachine {
+    fn default() -> Self {
+        Self {
+            current: DeviceRemoteStatus::Unavailable,
+            since: Instant::now(),
+            command: None,
+            began: Instant::now(),
+            timeouts: TimeoutSettings::default(),
+        }
+    }
+}
+
+impl RemoteStateMachine {
+    pub fn current(&self) -> &DeviceRemoteStatus {
+        &self.current
+    }
+
+    pub fn command(&self) -> Option<&DeviceCommand> {
+        self.command.as_ref()
+    }
+
+    /// Changes how long intermediate statuses may last, applying to the current one as well
+    pub fn set_timeouts(&mut self, timeouts: TimeoutSettings) {
+        self.timeouts = timeouts;
+    }
+
+    /// Marks a command as sent so following transitions are validated against its path,
+    /// giving the device until the command timeout to settle on its target
+    pub fn begin(&mut self, command: DeviceCommand) {
+        self.begin_at(command, Instant::now());
+    }
+
+    pub fn begin_at(&mut self, command: DeviceCommand, now: Instant) {
+        self.command = Some(command);
+        self.began = now;
+        self.since = now;
+    }
+
+    /// Clears the pending command, returning to passive validation
+    pub fn finish(&mut self) {
+        self.command = None;
+    }
+
+    /// Records a newly reported status and returns any warnings caused by it
+    pub fn record(&mut self, status: DeviceRemoteStatus) -> Vec<DeviceWarning> {
+        self.record_at(status, Instant::now())
+    }
+
+    pub fn record_at(&mut self, status: DeviceRemoteStatus, now: Instant) -> Vec<DeviceWarning> {
+        if status == self.current {
+            return self.check_stuck_at(now).into_iter().collect();
+        }
+
+        let mut warnings = Vec::new();
+        if !is_legal(&self.current, &status, self.command.as_ref()) {
+            warnings.push(DeviceWarning::UnexpectedTransition {
+                command: self.command.clone(),
+                from: self.current.clone(),
+                to: status.clone(),
+            });
+        }
+        self.current = status;
+        self.since = now;
+        warnings
+    }
+
+    /// Time left until the current intermediate status or the pending command is considered stuck
+    pub fn remaining(&self) -> Option<Duration> {
+        self.remaining_at(Instant::now())
+    }
+
+    pub fn remaining_at(&self, now: Instant) -> Option<Duration> {
+        let status = expected_duration(&self.current, &self.timeouts)
+            .map(|limit| limit.saturating_sub(now.saturating_duration_since(self.since)));
+        let command = self
+            .deadline()
+            .map(|deadline| deadline.saturating_duration_since(now));
+        status.into_iter().chain(command).min()
+    }
+
+    pub fn check_stuck(&self) -> Option<DeviceWarning> {
+        self.check_stuck_at(Instant::now())
+    }
+
+    pub fn check_stuck_at(&self, now: Instant) -> Option<DeviceWarning> {
+        let in_status = now.saturating_duration_since(self.since);
+        let elapsed = if expected_duration(&self.current, &self.timeouts)
+            .is_some_and(|limit| in_status >= limit)
+        {
+            in_status
+        } else if self.deadline().is_some_and(|deadline| now >= deadline) && !self.settled() {
+            now.saturating_duration_since(self.began)
+        } else {
+            return None;
+        };
+        Some(DeviceWarning::Stuck {
+            command: self.command.clone(),
+            status: self.current.clone(),
+            elapsed_secs: u32::try_from(elapsed.as_secs()).unwrap_or(u32::MAX),
+        })
+    }
+
+    /// When the pending command should have settled by
+    fn deadline(&self) -> Option<Instant> {
+        self.command.as_ref()?;
+        Some(self.began + Duration::from_secs(self.timeouts.command_secs))
+    }
+
+    /// Whether the device reached the target of the pending command
+    fn settled(&self) -> bool {
+        self.command
+            .as_ref()
+            .and_then(|command| expected_path(command).last())
+            .is_some_and(|target| *target == self.current)
+    }
+}
+
+/// Ordered statuses a device is expected to pass through after receiving a command,
+/// with the final entry being the settled target
+pub fn expected_path(command: &DeviceCommand) -> &'static [DeviceRemoteStatus] {
+    match command {
+        DeviceCommand::Activate => &[
+            DeviceRemoteStatus::Initiated,
+            DeviceRemoteStatus::Acknowledged,
+            DeviceRemoteStatus::Spinup,
+            DeviceRemoteStatus::Active,
+        ],
+        DeviceCommand::Standby => &[
+            DeviceRemoteStatus::Initiated,
+            DeviceRemoteStatus::Acknowledged,
+            DeviceRemoteStatus::Spinup,
+            DeviceRemoteStatus::Standby,
+        ],
+        DeviceCommand::Sleep => &[
+            DeviceRemoteStatus::Acknowledged,
+            DeviceRemoteStatus::Stopped,
+        ],
+    }
+}
+
+/// How long a device may stay in a status before it is considered stuck,
+/// or `None` if the status is settled
+pub fn expected_duration(
+    status: &DeviceRemoteStatus,
+    timeouts: &TimeoutSettings,
+) -> Option<Duration> {
+    match status {
+        DeviceRemoteStatus::Initiated | DeviceRemoteStatus::Acknowledged => {
+            Some(Duration::from_secs(timeouts.transition_secs))
+        }
+        DeviceRemoteStatus::Spinup => Some(Duration::from_secs(timeouts.spinup_secs)),
+        _ => None,
+    }
+}
+
+pub fn is_legal(
+    from: &DeviceRemoteStatus,
+    to: &DeviceRemoteStatus,
+    command: Option<&DeviceCommand>,
+) -> bool {
+    use DeviceRemoteStatus as S;
+
+    match (from, to) {
+        (_, S::Unknown(_)) => false,
+        (S::Unavailable, _) | (_, S::Unavailable) => true,
+        _ => match command {
+            // Statuses may be skipped but must never move backwards along the path
+            Some(command) => {
+                let path = expected_path(command);
+                let Some(to_index) = path.iter().position(|status| status == to) else {
+                    return false;
+                };
+                path.iter()
+                    .position(|status| status == from)
+                    .is_none_or(|from_index| from_index <= to_index)
+            }
+            // Without a command another program may be controlling the device,
+            // so only intermediate statuses are constrained
+            None => match from {
+                S::Initiated | S::Acknowledged => !matches!(to, S::Initiated),
+                S::Spinup => matches!(to, S::Active | S::Standby),
+                _ => true,
+            },
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use DeviceRemoteStatus as S;
+
+    fn machine(current: DeviceRemoteStatus, now: Instant) -> RemoteStateMachine {
+        let mut machine = RemoteStateMachine::default();
+        machine.record_at(current, now);
+        machine
+    }
+
+    #[test]
+    fn follows_the_expected_path_without_warnings() {
+        let now = Instant::now();
+        let mut machine = machine(S::Stopped, now);
+        machine.begin_at(DeviceCommand::Activate, now);
+        for status in [S::Initiated, S::Acknowledged, S::Spinup, S::Active] {
+            assert!(machine.record_at(status, now).is_empty());
+        }
+        assert_eq!(machine.current(), &S::Active);
+        assert_eq!(machine.check_stuck_at(now + Duration::from_hours(1)), None);
+    }
+
+    #[test]
+    fn warns_about_illegal_jumps() {
+        let now = Instant::now();
+        let mut machine = machine(S::Stopped, now);
+        machine.begin_at(DeviceCommand::Activate, now);
+        assert!(machine.record_at(S::Spinup, now).is_empty());
+        assert_eq!(
+            machine.record_at(S::Initiated, now),
+            [DeviceWarning::UnexpectedTransition {
+                command: Some(DeviceCommand::Activate),
+                from: S::Spinup,
+                to: S::Initiated,
+            }]
+        );
+        assert_eq!(machine.record_at(S::Stopped, now).len(), 1);
+    }
+
+    #[test]
+    fn reports_intermediate_statuses_that_last_too_long() {
+        let now = Instant::now();
+        let mut machine = machine(S::Stopped, now);
+        machine.begin_at(DeviceCommand::Activate, now);
+        machine.record_at(S::Spinup, now);
+        let limit = Duration::from_secs(TimeoutSettings::default().spinup_secs);
+        assert_eq!(machine.remaining_at(now), Some(limit));
+        assert_eq!(machine.check_stuck_at(now + limit / 2), None);
+        assert_eq!(
+            machine.check_stuck_at(now + limit),
+            Some(DeviceWarning::Stuck {
+                command: Some(DeviceCommand::Activate),
+                status: S::Spinup,
+                elapsed_secs: u32::try_from(limit.as_secs()).unwrap(),
+            })
+        );
+    }
+
+    #[test]
+    fn gives_pending_commands_a_deadline_from_begin() {
+        let start = Instant::now();
+        let mut machine = machine(S::Stopped, start);
+        assert_eq!(machine.remaining_at(start), None);
+
+        // Settled since long before the command was sent
+        let now = start + Duration::from_mins(10);
+        machine.begin_at(DeviceCommand::Activate, now);
+        let limit = Duration::from_secs(TimeoutSettings::default().command_secs);
+        assert_eq!(machine.remaining_at(now), Some(limit));
+        assert_eq!(machine.check_stuck_at(now), None);
+        assert_eq!(
+            machine.check_stuck_at(now + limit),
+            Some(DeviceWarning::Stuck {
+                command: Some(DeviceCommand::Activate),
+                status: S::Stopped,
+                elapsed_secs: u32::try_from(limit.as_secs()).unwrap(),
+            })
+        );
+
+        machine.finish();
+        assert_eq!(machine.remaining_at(now), None);
+        assert_eq!(machine.check_stuck_at(now + limit), None);
+    }
+
+    #[test]
+    fn does_not_report_commands_already_on_target() {
+        let now = Instant::now();
+        let mut machine = machine(S::Active, now);
+        machine.begin_at(DeviceCommand::Activate, now);
+        let limit = Duration::from_secs(TimeoutSettings::default().command_secs);
+        assert_eq!(machine.check_stuck_at(now + limit), None);
+    }
+}
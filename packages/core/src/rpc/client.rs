@@ -0,0 +1,99 @@
+use std::path::Path;
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines},
+    net::{
+        unix::{OwnedReadHalf, OwnedWriteHalf},
+        UnixStream,
+    },
+};
+
+use crate::{
+    DeviceListEvent, RpcMessage, RpcNotification, RpcRequest, JSONRPC_VERSION, NOTIFICATION_EVENT,
+};
+
+/// Connection to a daemon, notifications received while awaiting a call are queued as events
+pub struct RpcClient {
+    writer: OwnedWriteHalf,
+    lines: Lines<BufReader<OwnedReadHalf>>,
+    queued: Vec<RpcNotification>,
+    next_id: u64,
+}
+
+impl RpcClient {
+    pub async fn connect(path: impl AsRef<Path>) -> crate::Result<Self> {
+        let (reader, writer) = UnixStream::connect(path).await?.into_split();
+        Ok(Self {
+            writer,
+            lines: BufReader::new(reader).lines(),
+            queued: Vec::new(),
+            next_id: 1,
+        })
+    }
+
+    pub async fn call<P: Serialize, R: DeserializeOwned>(
+        &mut self,
+        method: &str,
+        params: P,
+    ) -> crate::Result<R> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let request = RpcRequest {
+            jsonrpc: JSONRPC_VERSION.into(),
+            id: Some(id.into()),
+            method: method.into(),
+            params: serde_json::to_value(params)?,
+        };
+        let mut line = serde_json::to_string(&request)?;
+        line.push('\n');
+        self.writer.write_all(line.as_bytes()).await?;
+
+        loop {
+            match self.next_message().await? {
+                RpcMessage::Response(response) if response.id == id => {
+                    if let Some(error) = response.error {
+                        return Err(crate::Error::Rpc(error.code, error.message));
+                    }
+                    let result = response.result.unwrap_or(Value::Null);
+                    return Ok(serde_json::from_value(result)?);
+                }
+                RpcMessage::Response(_) => {}
+                RpcMessage::Notification(notification) => self.queued.push(notification),
+            }
+        }
+    }
+
+    /// Waits for the next event, only sent after subscribing; `None` once the daemon disconnects
+    pub async fn next_event(&mut self) -> crate::Result<Option<DeviceListEvent>> {
+        loop {
+            let notification = if self.queued.is_empty() {
+                match self.next_message().await {
+                    Ok(RpcMessage::Notification(notification)) => notification,
+                    Ok(RpcMessage::Response(_)) => continue,
+                    Err(crate::Error::Io(err))
+                        if err.kind() == std::io::ErrorKind::UnexpectedEof =>
+                    {
+                        return Ok(None)
+                    }
+                    Err(err) => return Err(err),
+                }
+            } else {
+                self.queued.remove(0)
+            };
+            if notification.method == NOTIFICATION_EVENT {
+                return Ok(Some(serde_json::from_value(notification.params)?));
+            }
+        }
+    }
+
+    async fn next_message(&mut self) -> crate::Result<RpcMessage> {
+        let line = self
+            .lines
+            .next_line()
+            .await?
+            .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::UnexpectedEof))?;
+        Ok(serde_json::from_str(&line)?)
+    }
+}
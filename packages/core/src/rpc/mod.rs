@@ -0,0 +1,168 @@
+#[cfg(unix)]
+mod client;
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{DeviceCommand, DeviceSelector, RuleInput};
+
+#[cfg(unix)]
+pub use client::*;
+
+pub const JSONRPC_VERSION: &str = "2.0";
+
+pub const METHOD_SCAN: &str = "scan";
+pub const METHOD_STATUS: &str = "status";
+pub const METHOD_POWER: &str = "power";
+pub const METHOD_IDENTIFY: &str = "identify";
+pub const METHOD_SUBSCRIBE: &str = "subscribe";
+pub const METHOD_CANCEL_IDLE: &str = "cancel_idle";
+pub const METHOD_HOOK_RUNS: &str = "hook_runs";
+pub const METHOD_WEBHOOK_DELIVERIES: &str = "webhook_deliveries";
+pub const METHOD_RULE_EVALUATIONS: &str = "rule_evaluations";
+pub const METHOD_RULES_DRY_RUN: &str = "rules_dry_run";
+pub const METHOD_SCRIPT_RUNS: &str = "script_runs";
+/// Method name of notifications carrying a `DeviceListEvent` to subscribers
+pub const NOTIFICATION_EVENT: &str = "event";
+
+pub const ERROR_PARSE: i64 = -32700;
+pub const ERROR_INVALID_REQUEST: i64 = -32600;
+pub const ERROR_METHOD_NOT_FOUND: i64 = -32601;
+pub const ERROR_INVALID_PARAMS: i64 = -32602;
+pub const ERROR_INTERNAL: i64 = -32603;
+pub const ERROR_NO_DEVICES: i64 = -32001;
+
+/// Requests and responses are exchanged as one JSON object per line
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RpcRequest {
+    pub jsonrpc: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RpcResponse {
+    pub jsonrpc: String,
+    pub id: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RpcNotification {
+    pub jsonrpc: String,
+    pub method: String,
+    pub params: Value,
+}
+
+/// Anything a server may send to a client
+///
+/// Notifications are matched first since a missing `id` would otherwise deserialize as null
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RpcMessage {
+    Notification(RpcNotification),
+    Response(RpcResponse),
+}
+
+impl RpcResponse {
+    pub fn result(id: Value, result: Value) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.into(),
+            id,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    pub fn error(id: Value, code: i64, message: impl Into<String>) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.into(),
+            id,
+            result: None,
+            error: Some(RpcError {
+                code,
+                message: message.into(),
+            }),
+        }
+    }
+}
+
+impl RpcNotification {
+    pub fn new(method: impl Into<String>, params: Value) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.into(),
+            method: method.into(),
+            params,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ScanParams {
+    /// Seconds to scan, the configured duration when missing
+    #[serde(default)]
+    pub duration: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct StatusParams {
+    #[serde(default = "default_targets")]
+    pub targets: Vec<DeviceSelector>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PowerParams {
+    pub command: DeviceCommand,
+    pub targets: Vec<DeviceSelector>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct IdentifyParams {
+    pub targets: Vec<DeviceSelector>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RulesDryRunParams {
+    pub input: RuleInput,
+}
+
+fn default_targets() -> Vec<DeviceSelector> {
+    vec![DeviceSelector::all()]
+}
+
+/// Socket the daemon listens on unless told otherwise
+pub fn default_socket_path() -> PathBuf {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .map_or_else(private_temp_dir, PathBuf::from)
+        .join("vrlh-power-manager.sock")
+}
+
+/// Directory of the user in the shared temporary one, the daemon creates it only they can access
+#[cfg(unix)]
+fn private_temp_dir() -> PathBuf {
+    let uid = rustix::process::getuid().as_raw();
+    std::env::temp_dir().join(format!("vrlh-power-manager-{uid}"))
+}
+
+#[cfg(not(unix))]
+fn private_temp_dir() -> PathBuf {
+    std::env::temp_dir()
+}
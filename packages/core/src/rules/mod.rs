@@ -0,0 +1,870 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, DurationRound, NaiveTime, TimeDelta, Utc};
+use chrono_tz::Tz;
+use croner::Cron;
+use tokio::{sync::broadcast::error::RecvError, task::spawn_blocking, time::interval};
+
+use crate::{
+    expand_groups, history::History, schedule::local_zone, Aliases, DeviceInfo, DeviceList,
+    DeviceListEvent, DeviceRemoteStatus, DeviceSelector, LifecycleTracker, OpenXrRuntime,
+    ProcessWatcher, Rule, RuleAction, RuleCondition, RuleEvaluation, RuleInput, RuleSet,
+    RuleTrigger, UsbId, UsbWatcher,
+};
+
+/// Number of evaluations kept for inspection
+const EVALUATION_HISTORY: usize = 100;
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// State the conditions of rules are checked against
+#[derive(Clone, Debug)]
+pub struct RuleSnapshot {
+    pub devices: Vec<DeviceInfo>,
+    /// Watched process names with at least one running process
+    pub processes: Vec<String>,
+    /// Watched USB devices that are plugged in
+    pub usb: Vec<UsbId>,
+    pub now: DateTime<Utc>,
+}
+
+/// Reads rules from TOML and checks them for mistakes serde cannot catch
+pub fn parse_rules(text: &str) -> crate::Result<RuleSet> {
+    let rules = toml::from_str::<RuleSet>(text)
+        .map_err(|err| crate::Error::InvalidRules(err.to_string()))?;
+    match validate_rules(&rules).as_slice() {
+        [] => Ok(rules),
+        problems => Err(crate::Error::InvalidRules(problems.join("; "))),
+    }
+}
+
+/// Every problem found in the rules, empty if they are fine
+pub fn validate_rules(rules: &RuleSet) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut names = HashSet::new();
+    for rule in &rules.rules {
+        let name = &rule.name;
+        if name.trim().is_empty() {
+            problems.push("Every rule needs a name".to_string());
+        } else if !names.insert(name) {
+            problems.push(format!(r#"Rule "{name}" is defined twice"#));
+        }
+        if rule.actions.is_empty() {
+            problems.push(format!(r#"Rule "{name}" has no actions"#));
+        }
+        match &rule.trigger {
+            RuleTrigger::Time { cron, timezone } => {
+                if let Err(err) = parse_cron(cron, timezone.as_deref()) {
+                    problems.push(format!(r#"Rule "{name}": {err}"#));
+                }
+            }
+            RuleTrigger::Process { names, .. } if names.is_empty() => {
+                problems.push(format!(r#"Rule "{name}" watches no processes"#));
+            }
+            RuleTrigger::Usb { ids, .. } if ids.is_empty() => {
+                problems.push(format!(r#"Rule "{name}" watches no USB devices"#));
+            }
+            _ => {}
+        }
+        for condition in &rule.conditions {
+            if let RuleCondition::TimeBetween {
+                after,
+                before,
+                timezone,
+            } = condition
+            {
+                for time in [after, before] {
+                    if parse_clock(time).is_none() {
+                        problems.push(format!(r#"Rule "{name}": '{time}' is not a HH:MM time"#));
+                    }
+                }
+                if let Err(err) = parse_zone(timezone.as_deref()) {
+                    problems.push(format!(r#"Rule "{name}": {err}"#));
+                }
+            }
+        }
+        let targets_trigger = matches!(rule.trigger, RuleTrigger::Status { .. });
+        let targetless = rule.actions.iter().any(
+            |action| matches!(action, RuleAction::Power { targets, .. } if targets.is_empty()),
+        );
+        if targetless && !targets_trigger {
+            problems.push(format!(
+                r#"Rule "{name}" powers the triggering lighthouse but is not triggered by one"#
+            ));
+        }
+    }
+    problems
+}
+
+/// Fires rules on device events, lifecycle events, time, processes and USB devices
+///
+/// Can be cloned and will retain references to the same evaluation history
+#[derive(Clone, Debug)]
+pub struct RuleEngine {
+    rules: Arc<RuleSet>,
+    aliases: Arc<Aliases>,
+    processes: ProcessWatcher,
+    usb: UsbWatcher,
+    fired: Arc<Mutex<HashMap<String, Instant>>>,
+    evaluations: History<RuleEvaluation>,
+}
+
+impl RuleEngine {
+    pub fn new(rules: RuleSet, aliases: Aliases) -> crate::Result<Self> {
+        if let problems @ [_, ..] = validate_rules(&rules).as_slice() {
+            return Err(crate::Error::InvalidRules(problems.join("; ")));
+        }
+        let mut names = runtime_processes();
+        let mut ids = Vec::new();
+        for rule in &rules.rules {
+            match &rule.trigger {
+                RuleTrigger::Process { names: watched, .. } => names.extend(watched.clone()),
+                RuleTrigger::Usb { ids: watched, .. } => ids.extend(watched),
+                _ => {}
+            }
+            for condition in &rule.conditions {
+                match condition {
+                    RuleCondition::Process { names: watched, .. } => {
+                        names.extend(watched.clone());
+                    }
+                    RuleCondition::Usb { ids: watched, .. } => ids.extend(watched),
+                    _ => {}
+                }
+            }
+        }
+        names.sort();
+        names.dedup();
+        Ok(Self {
+            rules: Arc::new(rules),
+            aliases: Arc::new(aliases),
+            processes: ProcessWatcher::new(names),
+            usb: UsbWatcher::new(ids),
+            fired: Arc::default(),
+            evaluations: History::new(EVALUATION_HISTORY),
+        })
+    }
+
+    pub fn load(path: &Path, aliases: Aliases) -> crate::Result<Self> {
+        Self::new(parse_rules(&std::fs::read_to_string(path)?)?, aliases)
+    }
+
+    /// Reads processes and USB devices from fake trees instead of `/proc` and sysfs
+    #[must_use]
+    pub fn with_roots(
+        mut self,
+        proc: impl Into<std::path::PathBuf>,
+        usb: impl Into<std::path::PathBuf>,
+    ) -> Self {
+        self.processes = self.processes.with_root(proc);
+        self.usb = self.usb.with_root(usb);
+        self
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules.rules
+    }
+
+    /// Most recent evaluations whose trigger matched, oldest first
+    pub fn evaluations(&self) -> Vec<RuleEvaluation> {
+        self.evaluations.records()
+    }
+
+    pub fn snapshot(&self, devices: Vec<DeviceInfo>) -> RuleSnapshot {
+        RuleSnapshot {
+            devices,
+            processes: self.processes.running(),
+            usb: self.usb.present(),
+            now: Utc::now(),
+        }
+    }
+
+    /// Explains what every rule would do for the input without running any actions
+    pub fn dry_run(&self, input: &RuleInput, snapshot: &RuleSnapshot) -> Vec<RuleEvaluation> {
+        self.rules
+            .rules
+            .iter()
+            .map(|rule| self.evaluate(rule, input, snapshot).0)
+            .collect()
+    }
+
+    /// Feeds every input to the rules until the device list is dropped
+    pub async fn run(&self, devices: &DeviceList) {
+        let mut events = devices.subscribe();
+        let mut tracker = LifecycleTracker::default();
+        let mut statuses = HashMap::<String, DeviceRemoteStatus>::new();
+        let mut running = self.processes.running();
+        let mut present = self.usb.present();
+        let mut minute = current_minute();
+        let mut ticks = interval(POLL_INTERVAL);
+        loop {
+            let mut inputs = Vec::new();
+            tokio::select! {
+                event = events.recv() => match event {
+                    Ok(event) => {
+                        if let DeviceListEvent::Device(info) = &event {
+                            inputs.extend(status_change(&mut statuses, info));
+                        }
+                        inputs.extend(tracker.update(&event).iter().map(|event| {
+                            RuleInput::Lifecycle { event: event.kind() }
+                        }));
+                    }
+                    // Changes that were missed are found by comparing against current statuses
+                    Err(RecvError::Lagged(_)) => {
+                        let snapshot = snapshot_devices(devices);
+                        for info in &snapshot {
+                            inputs.extend(status_change(&mut statuses, info));
+                        }
+                        inputs.extend(tracker.resync(&snapshot).iter().map(|event| {
+                            RuleInput::Lifecycle { event: event.kind() }
+                        }));
+                    }
+                    Err(RecvError::Closed) => return,
+                },
+                _ = ticks.tick() => {
+                    let (processes, usb) = (self.processes.clone(), self.usb.clone());
+                    let Ok((now_running, now_present)) =
+                        spawn_blocking(move || (processes.running(), usb.present())).await
+                    else {
+                        continue;
+                    };
+                    inputs.extend(changes(&running, &now_running).map(|(name, running)| {
+                        RuleInput::Process { name, running }
+                    }));
+                    running = now_running;
+
+                    inputs.extend(changes(&present, &now_present).map(|(id, present)| {
+                        RuleInput::Usb { id, present }
+                    }));
+                    present = now_present;
+
+                    let now_minute = current_minute();
+                    if now_minute != minute {
+                        minute = now_minute;
+                        inputs.push(RuleInput::Time { at: minute });
+                    }
+                }
+            }
+            self.handle(devices, &inputs).await;
+        }
+    }
+
+    /// Walking `/proc` and sysfs for the snapshot blocks, so it happens once per batch of inputs
+    /// on the blocking pool
+    async fn handle(&self, devices: &DeviceList, inputs: &[RuleInput]) {
+        if inputs.is_empty() {
+            return;
+        }
+        let (engine, infos) = (self.clone(), snapshot_devices(devices));
+        let Ok(snapshot) = spawn_blocking(move || engine.snapshot(infos)).await else {
+            return;
+        };
+        for input in inputs {
+            self.fire(devices, input, &snapshot);
+        }
+    }
+
+    fn fire(&self, devices: &DeviceList, input: &RuleInput, snapshot: &RuleSnapshot) {
+        for rule in &self.rules.rules {
+            let (evaluation, triggered) = self.evaluate(rule, input, snapshot);
+            if !triggered {
+                continue;
+            }
+            let fired = evaluation.fired;
+            self.evaluations.push(evaluation);
+            if !fired {
+                continue;
+            }
+            self.lock_fired().insert(rule.name.clone(), Instant::now());
+            for action in &rule.actions {
+                self.execute(devices, rule, action, input);
+            }
+        }
+    }
+
+    /// Power commands run in the background so events keep being received meanwhile
+    fn execute(&self, devices: &DeviceList, rule: &Rule, action: &RuleAction, input: &RuleInput) {
+        match action {
+            RuleAction::Power { command, targets } => {
+                let targets = match (targets.is_empty(), input) {
+                    (true, RuleInput::Status { addr, .. }) => vec![DeviceSelector::new(addr)],
+                    _ => expand_groups(targets, &self.rules.groups),
+                };
+                let selected = devices.select(&targets, &self.aliases);
+                if selected.is_empty() {
+                    return;
+                }
+                devices.publish(DeviceListEvent::Status(format!(
+                    r#"Rule "{}" fired, sending "{command}" to {} lighthouses"#,
+                    rule.name,
+                    selected.len()
+                )));
+                let mut rx = devices.start_power(selected, command);
+                tokio::spawn(async move { while rx.recv().await.is_some() {} });
+            }
+            RuleAction::Emit { message } => {
+                devices.publish(DeviceListEvent::Status(message.clone()));
+            }
+        }
+    }
+
+    /// Returns the evaluation and whether the trigger matched
+    fn evaluate(
+        &self,
+        rule: &Rule,
+        input: &RuleInput,
+        snapshot: &RuleSnapshot,
+    ) -> (RuleEvaluation, bool) {
+        let mut evaluation = RuleEvaluation {
+            rule: rule.name.clone(),
+            at: snapshot.now,
+            input: input.clone(),
+            fired: false,
+            explanation: Vec::new(),
+        };
+        let lines = &mut evaluation.explanation;
+        if !rule.enabled {
+            lines.push("Rule is disabled".into());
+            return (evaluation, false);
+        }
+        match self.trigger_matches(&rule.trigger, input) {
+            Ok(line) => lines.push(format!("Triggered: {line}")),
+            Err(line) => {
+                lines.push(format!("Not triggered: {line}"));
+                return (evaluation, false);
+            }
+        }
+
+        let mut passed = true;
+        for condition in &rule.conditions {
+            let (met, line) = self.check_condition(condition, snapshot);
+            passed &= met;
+            lines.push(match met {
+                true => format!("Condition met: {line}"),
+                false => format!("Condition not met: {line}"),
+            });
+        }
+
+        let cooldown = Duration::from_secs(rule.cooldown_secs);
+        if let Some(elapsed) = self
+            .lock_fired()
+            .get(&rule.name)
+            .map(Instant::elapsed)
+            .filter(|elapsed| *elapsed < cooldown)
+        {
+            passed = false;
+            lines.push(format!(
+                "Cooling down: fired {}s ago, cooldown is {}s",
+                elapsed.as_secs(),
+                rule.cooldown_secs
+            ));
+        }
+        evaluation.fired = passed;
+        (evaluation, true)
+    }
+
+    fn trigger_matches(&self, trigger: &RuleTrigger, input: &RuleInput) -> Result<String, String> {
+        match (trigger, input) {
+            (
+                RuleTrigger::Status { targets, status },
+                RuleInput::Status {
+                    name,
+                    addr,
+                    status: changed,
+                },
+            ) => {
+                let selected = self.matches_any(targets, name, addr);
+                match selected && status == changed {
+                    true => Ok(format!("{name} changed to {changed}")),
+                    false => Err(format!(
+                        "waiting for {status} from {}, {name} changed to {changed}",
+                        list(targets)
+                    )),
+                }
+            }
+            (RuleTrigger::Lifecycle { event }, RuleInput::Lifecycle { event: happened }) => {
+                match event == happened {
+                    true => Ok(format!("{happened} happened")),
+                    false => Err(format!("waiting for {event}, {happened} happened")),
+                }
+            }
+            (RuleTrigger::Time { cron, timezone }, RuleInput::Time { at }) => {
+                let (cron, zone) = parse_cron(cron, timezone.as_deref())?;
+                let minute = at.duration_trunc(TimeDelta::minutes(1)).unwrap_or(*at);
+                let local = minute.with_timezone(&zone);
+                match cron.is_time_matching(&local).unwrap_or(false) {
+                    true => Ok(format!("{} matches the schedule", local.format("%a %H:%M"))),
+                    false => Err(format!(
+                        "{} does not match the schedule",
+                        local.format("%a %H:%M")
+                    )),
+                }
+            }
+            (
+                RuleTrigger::Process { names, running },
+                RuleInput::Process {
+                    name,
+                    running: changed,
+                },
+            ) => match names.contains(name) && running == changed {
+                true => Ok(format!("{name} {}", started(*changed))),
+                false => Err(format!(
+                    "waiting for {} to {}, {name} {}",
+                    names.join(", "),
+                    if *running { "start" } else { "stop" },
+                    started(*changed)
+                )),
+            },
+            (
+                RuleTrigger::Usb { ids, present },
+                RuleInput::Usb {
+                    id,
+                    present: changed,
+                },
+            ) => match ids.contains(id) && present == changed {
+                true => Ok(format!("{id} {}", plugged(*changed))),
+                false => Err(format!(
+                    "waiting for {} to be {}, {id} {}",
+                    list(ids),
+                    if *present { "plugged in" } else { "removed" },
+                    plugged(*changed)
+                )),
+            },
+            _ => Err(format!("{} does not trigger this rule", input_kind(input))),
+        }
+    }
+
+    fn check_condition(
+        &self,
+        condition: &RuleCondition,
+        snapshot: &RuleSnapshot,
+    ) -> (bool, String) {
+        match condition {
+            RuleCondition::AllStatus { targets, status }
+            | RuleCondition::AnyStatus { targets, status } => {
+                let all = matches!(condition, RuleCondition::AllStatus { .. });
+                let selected = self.select(targets, &snapshot.devices);
+                let wanted = if all { "all" } else { "any" };
+                let description = format!("{wanted} of {} {status}", list(targets));
+                if selected.is_empty() {
+                    return (false, format!("{description}, but no lighthouses matched"));
+                }
+                let (matching, others): (Vec<&DeviceInfo>, Vec<&DeviceInfo>) = selected
+                    .into_iter()
+                    .partition(|info| info.remote.as_ref() == Some(status));
+                let met = if all {
+                    others.is_empty()
+                } else {
+                    !matching.is_empty()
+                };
+                let details = others
+                    .iter()
+                    .map(|info| match &info.remote {
+                        Some(remote) => format!("{} is {remote}", info.name),
+                        None => format!("{} is unknown", info.name),
+                    })
+                    .collect::<Vec<_>>();
+                match details.is_empty() || (met && !all) {
+                    true => (met, description),
+                    false => (met, format!("{description}, but {}", details.join(", "))),
+                }
+            }
+            RuleCondition::Process { names, running } => {
+                let found = names
+                    .iter()
+                    .filter(|name| snapshot.processes.contains(name))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                let description = match found.is_empty() {
+                    true => format!("none of {} are running", names.join(", ")),
+                    false => format!("{} running", found.join(", ")),
+                };
+                (found.is_empty() != *running, description)
+            }
+            RuleCondition::Usb { ids, present } => {
+                let found = ids
+                    .iter()
+                    .filter(|id| snapshot.usb.contains(id))
+                    .collect::<Vec<_>>();
+                let description = match found.is_empty() {
+                    true => format!("none of {} are plugged in", list(ids)),
+                    false => format!("{} plugged in", list(&found)),
+                };
+                (found.is_empty() != *present, description)
+            }
+            RuleCondition::VrRuntime { running } => {
+                let runtimes = runtime_processes();
+                let found = snapshot
+                    .processes
+                    .iter()
+                    .filter(|name| runtimes.contains(name))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                let description = match found.is_empty() {
+                    true => "no VR runtime is running".to_string(),
+                    false => format!("VR runtime {} running", found.join(", ")),
+                };
+                (found.is_empty() != *running, description)
+            }
+            RuleCondition::TimeBetween {
+                after,
+                before,
+                timezone,
+            } => {
+                let (Some(start), Some(end), Ok(zone)) = (
+                    parse_clock(after),
+                    parse_clock(before),
+                    parse_zone(timezone.as_deref()),
+                ) else {
+                    return (false, format!("invalid time range {after} to {before}"));
+                };
+                let now = snapshot.now.with_timezone(&zone).time();
+                let met = match start <= end {
+                    true => start <= now && now < end,
+                    false => start <= now || now < end,
+                };
+                (
+                    met,
+                    format!("{} is between {after} and {before}", now.format("%H:%M")),
+                )
+            }
+        }
+    }
+
+    fn select<'a>(
+        &self,
+        targets: &[DeviceSelector],
+        devices: &'a [DeviceInfo],
+    ) -> Vec<&'a DeviceInfo> {
+        devices
+            .iter()
+            .filter(|info| self.matches_any(targets, &info.name, &info.addr))
+            .collect()
+    }
+
+    fn matches_any(&self, targets: &[DeviceSelector], name: &str, addr: &str) -> bool {
+        expand_groups(targets, &self.rules.groups)
+            .iter()
+            .any(|selector| selector.matches(name, addr, &self.aliases))
+    }
+
+    fn lock_fired(&self) -> std::sync::MutexGuard<'_, HashMap<String, Instant>> {
+        self.fired.lock().expect("Rule mutex must not be poisoned")
+    }
+}
+
+/// Reports a status only when it differs from the last one seen for the device
+fn status_change(
+    statuses: &mut HashMap<String, DeviceRemoteStatus>,
+    info: &DeviceInfo,
+) -> Option<RuleInput> {
+    let status = info.remote.clone()?;
+    let previous = statuses.insert(info.addr.clone(), status.clone());
+    (previous.as_ref() != Some(&status)).then(|| RuleInput::Status {
+        name: info.name.clone(),
+        addr: info.addr.clone(),
+        status,
+    })
+}
+
+/// Items that appeared, paired with `true`, and disappeared, paired with `false`
+fn changes<'a, T: PartialEq + Clone>(
+    before: &'a [T],
+    after: &'a [T],
+) -> impl Iterator<Item = (T, bool)> + 'a {
+    let added = after
+        .iter()
+        .filter(|item| !before.contains(item))
+        .map(|item| (item.clone(), true));
+    let removed = before
+        .iter()
+        .filter(|item| !after.contains(item))
+        .map(|item| (item.clone(), false));
+    added.chain(removed)
+}
+
+pub(crate) fn snapshot_devices(devices: &DeviceList) -> Vec<DeviceInfo> {
+    devices
+        .get_devices()
+        .iter()
+        .map(|device| {
+            let (local, remote) = device.get_last_statuses();
+            DeviceInfo::from_device_statuses(device, local, remote)
+        })
+        .collect()
+}
+
+fn runtime_processes() -> Vec<String> {
+    OpenXrRuntime::ALL
+        .into_iter()
+        .flat_map(OpenXrRuntime::processes)
+        .map(ToString::to_string)
+        .collect()
+}
+
+fn current_minute() -> DateTime<Utc> {
+    let now = Utc::now();
+    now.duration_trunc(TimeDelta::minutes(1)).unwrap_or(now)
+}
+
+fn parse_cron(cron: &str, timezone: Option<&str>) -> Result<(Cron, Tz), String> {
+    let parsed = Cron::from_str(cron).map_err(|err| format!("'{cron}': {err}"))?;
+    Ok((parsed, parse_zone(timezone)?))
+}
+
+fn parse_zone(timezone: Option<&str>) -> Result<Tz, String> {
+    match timezone {
+        Some(name) => name
+            .parse()
+            .map_err(|_| format!("Unknown timezone '{name}'")),
+        None => Ok(local_zone()),
+    }
+}
+
+fn parse_clock(time: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(time, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M:%S"))
+        .ok()
+}
+
+fn list<T: std::fmt::Display>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn started(running: bool) -> &'static str {
+    match running {
+        true => "started",
+        false => "stopped",
+    }
+}
+
+fn plugged(present: bool) -> &'static str {
+    match present {
+        true => "was plugged in",
+        false => "was removed",
+    }
+}
+
+fn input_kind(input: &RuleInput) -> &'static str {
+    match input {
+        RuleInput::Status { .. } => "A status change",
+        RuleInput::Lifecycle { .. } => "A lifecycle event",
+        RuleInput::Time { .. } => "The time",
+        RuleInput::Process { .. } => "A process",
+        RuleInput::Usb { .. } => "A USB device",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LifecycleEventKind;
+
+    const READY: &str = r#"
+        [groups]
+        room = ["LHB-A", "LHB-B"]
+
+        [[rule]]
+        name = "ready"
+        trigger = { type = "Status", targets = ["room"], status = "Active" }
+        conditions = [{ type = "AllStatus", targets = ["room"], status = "Active" }]
+        actions = [{ type = "Emit", message = "Room is ready" }]
+    "#;
+
+    fn engine(text: &str) -> RuleEngine {
+        RuleEngine::new(toml::from_str(text).unwrap(), Aliases::new()).unwrap()
+    }
+
+    fn snapshot(remotes: &[DeviceRemoteStatus]) -> RuleSnapshot {
+        let devices = remotes
+            .iter()
+            .zip(["A", "B"])
+            .map(|(remote, id)| {
+                DeviceInfo::fake(&format!("LHB-{id}"), &format!("{id}{id}:00"), remote)
+            })
+            .collect();
+        RuleSnapshot {
+            devices,
+            processes: Vec::new(),
+            usb: Vec::new(),
+            now: "2026-03-02T12:00:00Z".parse().unwrap(),
+        }
+    }
+
+    fn status(name: &str, status: DeviceRemoteStatus) -> RuleInput {
+        let id = name.trim_start_matches("LHB-");
+        RuleInput::Status {
+            name: name.into(),
+            addr: format!("{id}{id}:00"),
+            status,
+        }
+    }
+
+    #[test]
+    fn reports_invalid_rules() {
+        let rules = toml::from_str(
+            r#"
+            [[rule]]
+            name = "twice"
+            trigger = { type = "Lifecycle", event = "AllOff" }
+            actions = [{ type = "Emit", message = "first" }]
+
+            [[rule]]
+            name = "twice"
+            trigger = { type = "Lifecycle", event = "AllOff" }
+            actions = [{ type = "Power", command = "Sleep" }]
+
+            [[rule]]
+            name = "times"
+            trigger = { type = "Time", cron = "every day", timezone = "Mars/Olympus" }
+            conditions = [{ type = "TimeBetween", after = "25:00", before = "06:00", timezone = "Moon/Base" }]
+            actions = [{ type = "Emit", message = "late" }]
+            "#,
+        )
+        .unwrap();
+        let problems = validate_rules(&rules);
+        assert_eq!(problems.len(), 5, "{problems:#?}");
+        assert_eq!(problems[0], r#"Rule "twice" is defined twice"#);
+        assert_eq!(
+            problems[1],
+            r#"Rule "twice" powers the triggering lighthouse but is not triggered by one"#
+        );
+        assert!(problems[2].starts_with(r#"Rule "times": 'every day': "#));
+        assert_eq!(problems[3], r#"Rule "times": '25:00' is not a HH:MM time"#);
+        assert_eq!(problems[4], r#"Rule "times": Unknown timezone 'Moon/Base'"#);
+
+        assert!(RuleEngine::new(rules, Aliases::new()).is_err());
+        assert!(validate_rules(&toml::from_str(READY).unwrap()).is_empty());
+    }
+
+    #[test]
+    fn explains_mismatched_triggers() {
+        let engine = engine(READY);
+        let snapshot = snapshot(&[DeviceRemoteStatus::Active, DeviceRemoteStatus::Active]);
+
+        let input = status("LHB-A", DeviceRemoteStatus::Standby);
+        let [evaluation] = engine.dry_run(&input, &snapshot).try_into().unwrap();
+        assert!(!evaluation.fired);
+        assert_eq!(
+            evaluation.explanation,
+            ["Not triggered: waiting for ACTIVE from room, LHB-A changed to STANDBY"]
+        );
+
+        let input = RuleInput::Lifecycle {
+            event: LifecycleEventKind::AllActive,
+        };
+        let [evaluation] = engine.dry_run(&input, &snapshot).try_into().unwrap();
+        assert_eq!(
+            evaluation.explanation,
+            ["Not triggered: A lifecycle event does not trigger this rule"]
+        );
+    }
+
+    #[test]
+    fn checks_all_and_any_status_conditions() {
+        let input = status("LHB-B", DeviceRemoteStatus::Active);
+        let waiting = snapshot(&[DeviceRemoteStatus::Standby, DeviceRemoteStatus::Active]);
+        let ready = snapshot(&[DeviceRemoteStatus::Active, DeviceRemoteStatus::Active]);
+
+        let all = engine(READY);
+        let [evaluation] = all.dry_run(&input, &waiting).try_into().unwrap();
+        assert!(!evaluation.fired);
+        assert_eq!(
+            evaluation.explanation,
+            [
+                "Triggered: LHB-B changed to ACTIVE",
+                "Condition not met: all of room ACTIVE, but LHB-A is STANDBY",
+            ]
+        );
+        let [evaluation] = all.dry_run(&input, &ready).try_into().unwrap();
+        assert!(evaluation.fired);
+        assert_eq!(
+            evaluation.explanation[1],
+            "Condition met: all of room ACTIVE"
+        );
+
+        let any = engine(&READY.replace("AllStatus", "AnyStatus"));
+        let [evaluation] = any.dry_run(&input, &waiting).try_into().unwrap();
+        assert!(evaluation.fired);
+        assert_eq!(
+            evaluation.explanation[1],
+            "Condition met: any of room ACTIVE"
+        );
+        let stopped = snapshot(&[DeviceRemoteStatus::Stopped, DeviceRemoteStatus::Standby]);
+        let [evaluation] = any.dry_run(&input, &stopped).try_into().unwrap();
+        assert!(!evaluation.fired);
+        assert_eq!(
+            evaluation.explanation[1],
+            "Condition not met: any of room ACTIVE, but LHB-A is STOPPED, LHB-B is STANDBY"
+        );
+
+        let nobody = engine(&READY.replace(r#"["LHB-A", "LHB-B"]"#, r#"["LHB-C"]"#));
+        let [evaluation] = nobody
+            .dry_run(&status("LHB-C", DeviceRemoteStatus::Active), &ready)
+            .try_into()
+            .unwrap();
+        assert_eq!(
+            evaluation.explanation[1],
+            "Condition not met: all of room ACTIVE, but no lighthouses matched"
+        );
+    }
+
+    #[test]
+    fn cools_down_after_firing() {
+        let engine = engine(&format!("{READY}\ncooldown_secs = 60"));
+        let input = status("LHB-A", DeviceRemoteStatus::Active);
+        let snapshot = snapshot(&[DeviceRemoteStatus::Active, DeviceRemoteStatus::Active]);
+        let [evaluation] = engine.dry_run(&input, &snapshot).try_into().unwrap();
+        assert!(evaluation.fired);
+
+        engine.lock_fired().insert("ready".into(), Instant::now());
+        let [evaluation] = engine.dry_run(&input, &snapshot).try_into().unwrap();
+        assert!(!evaluation.fired);
+        assert_eq!(
+            evaluation.explanation.last().unwrap(),
+            "Cooling down: fired 0s ago, cooldown is 60s"
+        );
+    }
+
+    #[test]
+    fn checks_processes_and_usb_devices_of_the_system() {
+        let proc = tempfile::tempdir().unwrap();
+        let usb = tempfile::tempdir().unwrap();
+        std::fs::create_dir(proc.path().join("100")).unwrap();
+        std::fs::write(proc.path().join("100/comm"), "vrserver\n").unwrap();
+        let engine = engine(
+            r#"
+            [[rule]]
+            name = "vr"
+            trigger = { type = "Lifecycle", event = "AllOff" }
+            conditions = [{ type = "VrRuntime" }, { type = "Usb", ids = ["28de:2300"] }]
+            actions = [{ type = "Emit", message = "VR without a headset" }]
+            "#,
+        )
+        .with_roots(proc.path(), usb.path());
+
+        let input = RuleInput::Lifecycle {
+            event: LifecycleEventKind::AllOff,
+        };
+        let [evaluation] = engine
+            .dry_run(&input, &engine.snapshot(Vec::new()))
+            .try_into()
+            .unwrap();
+        assert!(!evaluation.fired);
+        assert_eq!(
+            evaluation.explanation[1..],
+            [
+                "Condition met: VR runtime vrserver running",
+                "Condition not met: none of 28de:2300 are plugged in",
+            ]
+        );
+    }
+}
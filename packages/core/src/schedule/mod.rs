@@ -0,0 +1,655 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, SystemTime},
+};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use chrono_tz::Tz;
+use croner::Cron;
+use serde::{Deserialize, Serialize};
+use tokio::{sync::Notify, time::sleep};
+
+use crate::{
+    calendar_modified, expand_groups, load_calendar, Aliases, CalendarConfig, CalendarEvent,
+    DeviceCommand, DeviceList, DeviceListEvent, DeviceSelector, Groups, Schedule, ScheduleNextRun,
+};
+
+/// Longest sleep between checks, so clock changes and suspends are noticed
+const MAX_SLEEP: Duration = Duration::from_mins(1);
+/// Runs found later than this are treated as missed
+const MISSED_AFTER: TimeDelta = TimeDelta::minutes(2);
+/// Consecutive exception dates searched past before giving up on a schedule
+const SEARCH_LIMIT: usize = 1000;
+
+impl Schedule {
+    pub fn validate(&self) -> crate::Result<()> {
+        if self.id.trim().is_empty() {
+            return Err(crate::Error::InvalidSchedule(
+                "The id must not be empty".into(),
+            ));
+        }
+        self.parse()?;
+        Ok(())
+    }
+
+    /// First run strictly after the given time, skipping exception dates
+    pub fn next_run(&self, after: DateTime<Utc>) -> crate::Result<Option<DateTime<Utc>>> {
+        if !self.enabled {
+            return Ok(None);
+        }
+        let (cron, zone) = self.parse()?;
+        let mut time = after.with_timezone(&zone);
+        for _ in 0..SEARCH_LIMIT {
+            let Ok(next) = cron.find_next_occurrence(&time, false) else {
+                return Ok(None);
+            };
+            if !self.exceptions.contains(&next.date_naive()) {
+                return Ok(Some(next.with_timezone(&Utc)));
+            }
+            time = next;
+        }
+        Ok(None)
+    }
+
+    /// Latest run within `(since, until]`, skipping exception dates
+    pub fn latest_run(
+        &self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> crate::Result<Option<DateTime<Utc>>> {
+        let (cron, zone) = self.parse()?;
+        let mut time = until.with_timezone(&zone);
+        let mut inclusive = true;
+        for _ in 0..SEARCH_LIMIT {
+            let Ok(previous) = cron.find_previous_occurrence(&time, inclusive) else {
+                return Ok(None);
+            };
+            if previous <= since {
+                return Ok(None);
+            }
+            if !self.exceptions.contains(&previous.date_naive()) {
+                return Ok(Some(previous.with_timezone(&Utc)));
+            }
+            time = previous;
+            inclusive = false;
+        }
+        Ok(None)
+    }
+
+    fn parse(&self) -> crate::Result<(Cron, Tz)> {
+        let cron = Cron::from_str(&self.cron)
+            .map_err(|err| crate::Error::InvalidSchedule(format!("'{}': {err}", self.cron)))?;
+        let zone = match &self.timezone {
+            Some(name) => name
+                .parse()
+                .map_err(|_| crate::Error::InvalidSchedule(format!("Unknown timezone '{name}'")))?,
+            None => local_zone(),
+        };
+        Ok((cron, zone))
+    }
+}
+
+pub(crate) fn local_zone() -> Tz {
+    iana_time_zone::get_timezone()
+        .ok()
+        .and_then(|name| name.parse().ok())
+        .unwrap_or(Tz::UTC)
+}
+
+/// Schedules and calendars along with the groups they target and their progress
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct ScheduleBook {
+    #[serde(default)]
+    schedules: Vec<Schedule>,
+    #[serde(default)]
+    calendars: Vec<CalendarConfig>,
+    #[serde(default)]
+    groups: Groups,
+    #[serde(default)]
+    last_runs: HashMap<String, DateTime<Utc>>,
+    /// Calendars whose lighthouses were woken for a session that has not ended yet
+    #[serde(default)]
+    active_calendars: HashSet<String>,
+    #[serde(skip)]
+    loaded: HashMap<String, LoadedCalendar>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct LoadedCalendar {
+    modified: Option<SystemTime>,
+    events: Vec<CalendarEvent>,
+}
+
+/// Command due to be sent to lighthouses
+struct ScheduledAction {
+    targets: Vec<DeviceSelector>,
+    command: DeviceCommand,
+    reason: String,
+}
+
+/// Runs schedules against a device list, optionally persisting them as JSON
+///
+/// Can be cloned and will retain references to the same schedules
+#[derive(Clone, Debug, Default)]
+pub struct Scheduler {
+    book: Arc<Mutex<ScheduleBook>>,
+    path: Option<Arc<PathBuf>>,
+    changed: Arc<Notify>,
+    writing: Arc<Mutex<()>>,
+}
+
+impl Scheduler {
+    pub fn new(schedules: Vec<Schedule>, groups: Groups) -> crate::Result<Self> {
+        for schedule in &schedules {
+            schedule.validate()?;
+        }
+        Ok(Self {
+            book: Arc::new(Mutex::new(ScheduleBook {
+                schedules,
+                groups,
+                ..ScheduleBook::default()
+            })),
+            ..Self::default()
+        })
+    }
+
+    /// Reads schedules from the file if it exists and writes every change back to it
+    pub fn load(path: impl Into<PathBuf>) -> crate::Result<Self> {
+        let path = path.into();
+        let book = match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice::<ScheduleBook>(&bytes)?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => ScheduleBook::default(),
+            Err(err) => return Err(err.into()),
+        };
+        for schedule in &book.schedules {
+            schedule.validate()?;
+        }
+        for calendar in &book.calendars {
+            validate_calendar(calendar)?;
+        }
+        Ok(Self {
+            book: Arc::new(Mutex::new(book)),
+            path: Some(Arc::new(path)),
+            ..Self::default()
+        })
+    }
+
+    pub fn schedules(&self) -> Vec<Schedule> {
+        self.lock_book().schedules.clone()
+    }
+
+    pub fn groups(&self) -> Groups {
+        self.lock_book().groups.clone()
+    }
+
+    /// Adds the schedule or replaces the one with the same id
+    pub fn save_schedule(&self, schedule: Schedule) -> crate::Result<()> {
+        schedule.validate()?;
+        self.update(|book| {
+            // Runs before the change must not count as missed
+            book.last_runs.insert(schedule.id.clone(), Utc::now());
+            match book
+                .schedules
+                .iter_mut()
+                .find(|existing| existing.id == schedule.id)
+            {
+                Some(existing) => *existing = schedule,
+                None => book.schedules.push(schedule),
+            }
+            true
+        })?;
+        Ok(())
+    }
+
+    /// Returns whether a schedule with the id existed
+    pub fn delete_schedule(&self, id: &str) -> crate::Result<bool> {
+        self.update(|book| {
+            book.last_runs.remove(id);
+            let before = book.schedules.len();
+            book.schedules.retain(|schedule| schedule.id != id);
+            book.schedules.len() != before
+        })
+    }
+
+    pub fn calendars(&self) -> Vec<CalendarConfig> {
+        self.lock_book().calendars.clone()
+    }
+
+    /// Adds the calendar or replaces the one with the same id, reading it again
+    pub fn save_calendar(&self, calendar: CalendarConfig) -> crate::Result<()> {
+        validate_calendar(&calendar)?;
+        self.update(|book| {
+            book.loaded.remove(&calendar.id);
+            match book
+                .calendars
+                .iter_mut()
+                .find(|existing| existing.id == calendar.id)
+            {
+                Some(existing) => *existing = calendar,
+                None => book.calendars.push(calendar),
+            }
+            true
+        })?;
+        Ok(())
+    }
+
+    /// Returns whether a calendar with the id existed
+    pub fn delete_calendar(&self, id: &str) -> crate::Result<bool> {
+        self.update(|book| {
+            book.loaded.remove(id);
+            book.active_calendars.remove(id);
+            let before = book.calendars.len();
+            book.calendars.retain(|calendar| calendar.id != id);
+            book.calendars.len() != before
+        })
+    }
+
+    /// Sessions of the calendar that have not ended yet, read from disk, earliest first
+    pub fn calendar_sessions(&self, id: &str) -> crate::Result<Vec<CalendarEvent>> {
+        let calendar = self
+            .lock_book()
+            .calendars
+            .iter()
+            .find(|calendar| calendar.id == id)
+            .cloned()
+            .ok_or(crate::Error::Vrlh("Calendar not found!"))?;
+        let now = Utc::now();
+        let mut sessions = load_calendar(&calendar.path)?
+            .into_iter()
+            .filter(|event| event.end > now && calendar.accepts(event))
+            .collect::<Vec<_>>();
+        sessions.sort_by_key(|event| event.start);
+        Ok(sessions)
+    }
+
+    /// Adds the group or replaces its members
+    pub fn save_group(&self, name: String, members: Vec<DeviceSelector>) -> crate::Result<()> {
+        self.update(|book| {
+            book.groups.insert(name, members);
+            true
+        })?;
+        Ok(())
+    }
+
+    /// Returns whether a group with the name existed
+    pub fn delete_group(&self, name: &str) -> crate::Result<bool> {
+        self.update(|book| book.groups.remove(name).is_some())
+    }
+
+    pub fn next_runs(&self, after: DateTime<Utc>) -> Vec<ScheduleNextRun> {
+        self.lock_book()
+            .schedules
+            .iter()
+            .map(|schedule| ScheduleNextRun {
+                id: schedule.id.clone(),
+                next: schedule.next_run(after).ok().flatten(),
+            })
+            .collect()
+    }
+
+    /// Sends the commands of due schedules and calendar sessions through the device list forever
+    pub async fn run(&self, devices: &DeviceList, aliases: &Aliases) {
+        loop {
+            let now = Utc::now();
+            let groups = self.groups();
+            let mut actions = self.take_due(now).await;
+            actions.extend(self.take_calendar_changes(now, devices).await);
+            for action in actions {
+                let targets = expand_groups(&action.targets, &groups);
+                let mut selected = devices.select(&targets, aliases);
+                // Nothing was scanned for yet, as when started at login
+                if selected.is_empty() && devices.get_devices().is_empty() {
+                    scan(devices).await;
+                    selected = devices.select(&targets, aliases);
+                }
+                if selected.is_empty() {
+                    devices.publish(DeviceListEvent::Status(format!(
+                        r#"{}, but no lighthouses match "{}""#,
+                        action.reason,
+                        format_targets(&action.targets)
+                    )));
+                    continue;
+                }
+                devices.publish(DeviceListEvent::Status(format!(
+                    r#"{}, sending "{}" to {} lighthouses"#,
+                    action.reason,
+                    action.command,
+                    selected.len()
+                )));
+                let mut rx = devices.start_power(selected, &action.command);
+                while rx.recv().await.is_some() {}
+            }
+
+            let wait = self
+                .next_wake(Utc::now())
+                .and_then(|next| (next - Utc::now()).to_std().ok())
+                .map_or(MAX_SLEEP, |wait| wait.min(MAX_SLEEP));
+            tokio::select! {
+                () = sleep(wait) => {}
+                () = self.changed.notified() => {}
+            }
+        }
+    }
+
+    fn next_wake(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let book = self.lock_book();
+        let schedules = book
+            .schedules
+            .iter()
+            .filter_map(|schedule| schedule.next_run(after).ok().flatten());
+        let calendars = book.calendars.iter().filter_map(|calendar| {
+            let loaded = book.loaded.get(&calendar.id)?;
+            calendar.next_change(&loaded.events, after)
+        });
+        schedules.chain(calendars).min()
+    }
+
+    /// Schedules with a run since they were last checked, marking them as run
+    async fn take_due(&self, now: DateTime<Utc>) -> Vec<ScheduledAction> {
+        let mut due = Vec::new();
+        let mut changed = false;
+        {
+            let mut book = self.lock_book();
+            let ScheduleBook {
+                schedules,
+                last_runs,
+                ..
+            } = &mut *book;
+            for schedule in schedules.iter().filter(|schedule| schedule.enabled) {
+                let Some(since) = last_runs.get(&schedule.id).copied() else {
+                    // Schedules loaded without a history start counting now
+                    last_runs.insert(schedule.id.clone(), now);
+                    changed = true;
+                    continue;
+                };
+                let Ok(Some(run)) = schedule.latest_run(since, now) else {
+                    continue;
+                };
+                last_runs.insert(schedule.id.clone(), now);
+                changed = true;
+                let missed = now - run > MISSED_AFTER;
+                if missed && schedule.missed == crate::MissedRuns::Skip {
+                    continue;
+                }
+                let name = match schedule.name.is_empty() {
+                    true => &schedule.id,
+                    false => &schedule.name,
+                };
+                let reason = match missed {
+                    true => "missed a run",
+                    false => "is due",
+                };
+                due.push(ScheduledAction {
+                    targets: schedule.targets.clone(),
+                    command: schedule.command.clone(),
+                    reason: format!(r#"Schedule "{name}" {reason}"#),
+                });
+            }
+        }
+        if changed {
+            self.persist().await;
+        }
+        due
+    }
+
+    /// Calendars whose sessions started or ended since they were last checked,
+    /// reading every calendar that changed on disk first
+    async fn take_calendar_changes(
+        &self,
+        now: DateTime<Utc>,
+        devices: &DeviceList,
+    ) -> Vec<ScheduledAction> {
+        self.reload_calendars(devices).await;
+        let mut due = Vec::new();
+        {
+            let mut book = self.lock_book();
+            let ScheduleBook {
+                calendars,
+                active_calendars,
+                loaded,
+                ..
+            } = &mut *book;
+            for calendar in calendars.iter().filter(|calendar| calendar.enabled) {
+                let Some(loaded) = loaded.get(&calendar.id) else {
+                    continue;
+                };
+                let session = calendar.active_session(&loaded.events, now);
+                let (command, reason) = match (session, active_calendars.contains(&calendar.id)) {
+                    (Some(session), false) => {
+                        active_calendars.insert(calendar.id.clone());
+                        let start = session.start.with_timezone(&chrono::Local).format("%H:%M");
+                        (
+                            &calendar.on_start,
+                            format!(r#"Session "{}" starts at {start}"#, session.summary),
+                        )
+                    }
+                    (None, true) => {
+                        active_calendars.remove(&calendar.id);
+                        (
+                            &calendar.on_end,
+                            format!(r#"Sessions of calendar "{}" are over"#, calendar.id),
+                        )
+                    }
+                    _ => continue,
+                };
+                due.push(ScheduledAction {
+                    targets: calendar.targets.clone(),
+                    command: command.clone(),
+                    reason,
+                });
+            }
+        }
+        if !due.is_empty() {
+            self.persist().await;
+        }
+        due
+    }
+
+    /// Reads every enabled calendar that changed on disk since it was last read,
+    /// keeping the previous events of calendars that can no longer be read
+    async fn reload_calendars(&self, devices: &DeviceList) {
+        let calendars = {
+            let book = self.lock_book();
+            book.calendars
+                .iter()
+                .filter(|calendar| calendar.enabled)
+                .map(|calendar| {
+                    let modified = book.loaded.get(&calendar.id).map(|loaded| loaded.modified);
+                    (calendar.clone(), modified)
+                })
+                .collect::<Vec<_>>()
+        };
+        let read = tokio::task::spawn_blocking(move || {
+            calendars
+                .into_iter()
+                .filter_map(|(calendar, previous)| {
+                    let modified = calendar_modified(&calendar.path);
+                    (previous != Some(modified))
+                        .then(|| (calendar.id, modified, load_calendar(&calendar.path)))
+                })
+                .collect::<Vec<_>>()
+        })
+        .await
+        .unwrap_or_default();
+
+        let mut book = self.lock_book();
+        for (id, modified, events) in read {
+            let events = events.unwrap_or_else(|err| {
+                devices.publish(DeviceListEvent::Status(format!(
+                    r#"Could not read calendar "{id}": {err}"#
+                )));
+                book.loaded
+                    .get(&id)
+                    .map(|previous| previous.events.clone())
+                    .unwrap_or_default()
+            });
+            book.loaded.insert(id, LoadedCalendar { modified, events });
+        }
+    }
+
+    fn update(&self, change: impl FnOnce(&mut ScheduleBook) -> bool) -> crate::Result<bool> {
+        let changed = change(&mut self.lock_book());
+        if changed {
+            self.write()?;
+            self.changed.notify_waiters();
+        }
+        Ok(changed)
+    }
+
+    /// Failing to record progress only risks repeating or missing a command after a restart
+    async fn persist(&self) {
+        let scheduler = self.clone();
+        let _ = tokio::task::spawn_blocking(move || scheduler.write()).await;
+    }
+
+    /// Writes the schedules to the file, outside of the schedule lock
+    fn write(&self) -> crate::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        // Writes happen in the order of their snapshots so an older one never wins
+        let _writing = self.writing.lock().unwrap_or_else(PoisonError::into_inner);
+        let bytes = serde_json::to_vec_pretty(&*self.lock_book())?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&**path, bytes)?;
+        Ok(())
+    }
+
+    fn lock_book(&self) -> std::sync::MutexGuard<'_, ScheduleBook> {
+        self.book
+            .lock()
+            .expect("Schedule mutex must not be poisoned")
+    }
+}
+
+async fn scan(devices: &DeviceList) {
+    devices.publish(DeviceListEvent::Status(
+        "Scanning for lighthouses...".into(),
+    ));
+    match devices.start_scan(devices.config().scan.duration_secs) {
+        Ok(mut rx) => while rx.recv().await.is_some() {},
+        Err(err) => devices.publish(DeviceListEvent::Status(format!(
+            "Could not scan for lighthouses: {err}"
+        ))),
+    }
+}
+
+fn format_targets(targets: &[DeviceSelector]) -> String {
+    targets
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn validate_calendar(calendar: &CalendarConfig) -> crate::Result<()> {
+    match calendar.id.trim().is_empty() {
+        true => Err(crate::Error::InvalidCalendar(
+            "The id must not be empty".into(),
+        )),
+        false => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn schedule(cron: &str, exceptions: &[&str]) -> Schedule {
+        serde_json::from_value(json!({
+            "id": "test",
+            "cron": cron,
+            "timezone": "UTC",
+            "command": "Activate",
+            "exceptions": exceptions,
+        }))
+        .unwrap()
+    }
+
+    fn utc(time: &str) -> DateTime<Utc> {
+        time.parse().unwrap()
+    }
+
+    #[test]
+    fn next_run_skips_exceptions() {
+        let schedule = schedule("0 9 * * *", &["2026-03-02"]);
+        let next = schedule.next_run(utc("2026-03-01T10:00:00Z")).unwrap();
+        assert_eq!(next, Some(utc("2026-03-03T09:00:00Z")));
+    }
+
+    #[test]
+    fn latest_run_stays_within_the_window() {
+        let schedule = schedule("0 9 * * *", &[]);
+        let run = schedule.latest_run(utc("2026-03-01T10:00:00Z"), utc("2026-03-02T09:00:00Z"));
+        assert_eq!(run.unwrap(), Some(utc("2026-03-02T09:00:00Z")));
+        let run = schedule.latest_run(utc("2026-03-02T09:00:00Z"), utc("2026-03-02T12:00:00Z"));
+        assert_eq!(run.unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_invalid_schedules() {
+        assert!(schedule("not cron", &[]).validate().is_err());
+        let mut unnamed = schedule("0 9 * * *", &[]);
+        unnamed.id = " ".into();
+        assert!(unnamed.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn takes_due_and_missed_runs_once() {
+        let mut skipped = schedule("0 9 * * *", &[]);
+        skipped.id = "skipped".into();
+        let mut latest = schedule("0 9 * * *", &[]);
+        latest.id = "latest".into();
+        latest.missed = crate::MissedRuns::RunLatest;
+        let scheduler = Scheduler::new(vec![skipped, latest], Groups::new()).unwrap();
+        for id in ["skipped", "latest"] {
+            scheduler
+                .lock_book()
+                .last_runs
+                .insert(id.into(), utc("2026-03-01T10:00:00Z"));
+        }
+
+        let due = scheduler.take_due(utc("2026-03-02T12:00:00Z")).await;
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].reason, r#"Schedule "latest" missed a run"#);
+        assert!(scheduler
+            .take_due(utc("2026-03-02T12:00:00Z"))
+            .await
+            .is_empty());
+    }
+
+    #[test]
+    fn writes_changes_back_to_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("schedules.json");
+        let scheduler = Scheduler::load(&path).unwrap();
+        assert!(scheduler.schedules().is_empty());
+
+        scheduler.save_schedule(schedule("0 9 * * *", &[])).unwrap();
+        let loaded = Scheduler::load(&path).unwrap();
+        assert_eq!(loaded.schedules().len(), 1);
+        assert!(loaded.delete_schedule("test").unwrap());
+        assert!(Scheduler::load(&path).unwrap().schedules().is_empty());
+    }
+
+    #[test]
+    fn fails_to_load_invalid_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("schedules.json");
+        std::fs::write(&path, "{ not json").unwrap();
+        assert!(Scheduler::load(&path).is_err());
+        std::fs::write(
+            &path,
+            r#"{ "schedules": [{ "id": "a", "cron": "nope", "command": "Sleep" }] }"#,
+        )
+        .unwrap();
+        assert!(Scheduler::load(&path).is_err());
+    }
+}
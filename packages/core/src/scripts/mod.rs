@@ -0,0 +1,537 @@
+use std::{
+    fs,
+    str::FromStr,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use rhai::{
+    module_resolvers::DummyModuleResolver, Array, CallFnOptions, Dynamic, Engine, EvalAltResult,
+    Map, Scope, AST,
+};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::{
+    history::{unix_timestamp, History},
+    rules::snapshot_devices,
+    Aliases, DeviceCommand, DeviceInfo, DeviceList, DeviceListEvent, DeviceSelector,
+    LifecycleTracker, ScriptConfig, ScriptRun,
+};
+
+/// Number of script runs kept for inspection
+const RUN_HISTORY: usize = 100;
+/// Operations between checks of the deadline
+const PROGRESS_INTERVAL: u64 = 1024;
+const HANDLER_DEVICE: &str = "on_device";
+const HANDLER_EVENT: &str = "on_event";
+
+/// Runs Rhai scripts from a directory, calling their `on_device(device)` and `on_event(event)`
+/// functions for every device update and lifecycle event
+///
+/// Scripts cannot reach files, processes or the network. Besides the Rhai standard library they
+/// may call `devices()`, `power(command, targets)`, `identify(targets)` and `log(message)`,
+/// where targets are a name, address or alias or an array of them. Handlers keep state in
+/// `this`, a map that starts out empty. A failing or slow script does not affect the others.
+///
+/// Can be cloned and will retain references to the same scripts and run history
+#[derive(Clone, Debug)]
+pub struct Scripts {
+    engine: Arc<Engine>,
+    host: Arc<Mutex<Host>>,
+    loaded: Arc<Mutex<Vec<Script>>>,
+    aliases: Arc<Aliases>,
+    timeout: Duration,
+    runs: History<ScriptRun>,
+}
+
+#[derive(Debug)]
+struct Script {
+    name: String,
+    ast: AST,
+    scope: Scope<'static>,
+    /// Map bound to `this` in handlers, kept between calls
+    state: Dynamic,
+}
+
+/// State shared with the functions scripts call
+#[derive(Debug)]
+struct Host {
+    script: String,
+    deadline: Instant,
+    devices: Vec<DeviceInfo>,
+    commands: Vec<HostCommand>,
+}
+
+#[derive(Debug)]
+enum HostCommand {
+    Power {
+        script: String,
+        command: DeviceCommand,
+        targets: Vec<DeviceSelector>,
+    },
+    Identify {
+        script: String,
+        targets: Vec<DeviceSelector>,
+    },
+    Log {
+        script: String,
+        message: String,
+    },
+}
+
+impl Scripts {
+    /// Loads every `.rhai` file of the directory, scripts that fail to load are recorded and skipped
+    pub fn load(config: &ScriptConfig, aliases: Aliases) -> crate::Result<Self> {
+        let host = Arc::new(Mutex::new(Host {
+            script: String::new(),
+            deadline: Instant::now(),
+            devices: Vec::new(),
+            commands: Vec::new(),
+        }));
+        let scripts = Self {
+            engine: Arc::new(sandboxed_engine(&host)),
+            host,
+            loaded: Arc::default(),
+            aliases: Arc::new(aliases),
+            timeout: Duration::from_millis(config.timeout_ms),
+            runs: History::new(RUN_HISTORY),
+        };
+
+        let mut files = fs::read_dir(&config.dir)?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "rhai")
+            })
+            .collect::<Vec<_>>();
+        files.sort();
+        for file in files {
+            let name = file
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let started = scripts.start(&name);
+            let loaded = fs::read_to_string(&file)
+                .map_err(|err| err.to_string())
+                .and_then(|source| {
+                    scripts
+                        .engine
+                        .compile(source)
+                        .map_err(|err| err.to_string())
+                })
+                .and_then(|ast| {
+                    let mut scope = Scope::new();
+                    scripts
+                        .engine
+                        .run_ast_with_scope(&mut scope, &ast)
+                        .map_err(|err| scripts.describe(&err))?;
+                    Ok(Script {
+                        name: name.clone(),
+                        ast,
+                        scope,
+                        state: Dynamic::from_map(Map::new()),
+                    })
+                });
+            scripts.record(&name, "load", started, loaded.as_ref().err().cloned());
+            if let Ok(script) = loaded {
+                scripts.lock_scripts().push(script);
+            }
+        }
+        Ok(scripts)
+    }
+
+    /// Names of the scripts that loaded successfully
+    pub fn names(&self) -> Vec<String> {
+        self.lock_scripts()
+            .iter()
+            .map(|script| script.name.clone())
+            .collect()
+    }
+
+    /// Most recent loads and handler calls, oldest first
+    pub fn runs(&self) -> Vec<ScriptRun> {
+        self.runs.records()
+    }
+
+    /// Calls handlers for events of the device list until it is dropped
+    pub async fn run(&self, devices: &DeviceList) {
+        // Commands issued while loading
+        self.execute(devices);
+        let mut events = devices.subscribe();
+        let mut tracker = LifecycleTracker::default();
+        loop {
+            let mut calls = Vec::new();
+            let lifecycle = match events.recv().await {
+                Ok(event) => {
+                    if let DeviceListEvent::Device(info) = &event {
+                        calls
+                            .extend(rhai::serde::to_dynamic(info).map(|arg| (HANDLER_DEVICE, arg)));
+                    }
+                    tracker.update(&event)
+                }
+                // Missed device updates are gone, but lifecycle events can be caught up on
+                Err(RecvError::Lagged(_)) => tracker.resync(&snapshot_devices(devices)),
+                Err(RecvError::Closed) => return,
+            };
+            for lifecycle in lifecycle {
+                calls.extend(rhai::serde::to_dynamic(&lifecycle).map(|arg| (HANDLER_EVENT, arg)));
+            }
+            if calls.is_empty() {
+                continue;
+            }
+
+            lock(&self.host).devices = snapshot_devices(devices);
+            let scripts = self.clone();
+            let _ = tokio::task::spawn_blocking(move || scripts.call_all(&calls)).await;
+            self.execute(devices);
+        }
+    }
+
+    fn call_all(&self, calls: &[(&str, Dynamic)]) {
+        let mut scripts = self.lock_scripts();
+        for script in scripts.iter_mut() {
+            for (handler, arg) in calls {
+                let defined = script
+                    .ast
+                    .iter_functions()
+                    .any(|function| function.name == *handler && function.params.len() == 1);
+                if !defined {
+                    continue;
+                }
+                let started = self.start(&script.name);
+                let result = self.engine.call_fn_with_options::<Dynamic>(
+                    CallFnOptions::new()
+                        .eval_ast(false)
+                        .bind_this_ptr(&mut script.state),
+                    &mut script.scope,
+                    &script.ast,
+                    handler,
+                    (arg.clone(),),
+                );
+                let error = result.err().map(|err| self.describe(&err));
+                self.record(&script.name, handler, started, error);
+            }
+        }
+    }
+
+    /// Sends the commands scripts issued since the last call
+    fn execute(&self, devices: &DeviceList) {
+        let commands = std::mem::take(&mut lock(&self.host).commands);
+        for command in commands {
+            match command {
+                HostCommand::Power {
+                    script,
+                    command,
+                    targets,
+                } => {
+                    let selected = devices.select(&targets, &self.aliases);
+                    if selected.is_empty() {
+                        continue;
+                    }
+                    devices.publish(DeviceListEvent::Status(format!(
+                        r#"Script "{script}" sending "{command}" to {} lighthouses"#,
+                        selected.len()
+                    )));
+                    let mut rx = devices.start_power(selected, &command);
+                    tokio::spawn(async move { while rx.recv().await.is_some() {} });
+                }
+                HostCommand::Identify { script, targets } => {
+                    let selected = devices.select(&targets, &self.aliases);
+                    if selected.is_empty() {
+                        continue;
+                    }
+                    devices.publish(DeviceListEvent::Status(format!(
+                        r#"Script "{script}" identifying {} lighthouses"#,
+                        selected.len()
+                    )));
+                    let mut rx = devices.start_identify(selected);
+                    tokio::spawn(async move { while rx.recv().await.is_some() {} });
+                }
+                HostCommand::Log { script, message } => {
+                    devices.publish(DeviceListEvent::Status(format!("[{script}] {message}")));
+                }
+            }
+        }
+    }
+
+    /// Points host functions at the script and starts its time limit
+    fn start(&self, script: &str) -> Instant {
+        let started = Instant::now();
+        let mut host = lock(&self.host);
+        script.clone_into(&mut host.script);
+        host.deadline = started + self.timeout;
+        started
+    }
+
+    fn describe(&self, err: &EvalAltResult) -> String {
+        match err {
+            EvalAltResult::ErrorTerminated(..) => {
+                format!("Stopped after {}ms", self.timeout.as_millis())
+            }
+            err => err.to_string(),
+        }
+    }
+
+    fn record(&self, script: &str, handler: &str, started: Instant, error: Option<String>) {
+        let run = ScriptRun {
+            script: script.to_string(),
+            handler: handler.to_string(),
+            error,
+            duration_ms: u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX),
+            timestamp: unix_timestamp(),
+        };
+        self.runs.push(run);
+    }
+
+    fn lock_scripts(&self) -> MutexGuard<'_, Vec<Script>> {
+        lock(&self.loaded)
+    }
+}
+
+/// Engine without module imports or `eval`, with limits against runaway scripts and the host API
+fn sandboxed_engine(host: &Arc<Mutex<Host>>) -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_module_resolver(DummyModuleResolver::new())
+        .set_max_call_levels(32)
+        .set_max_expr_depths(64, 32)
+        .set_max_string_size(64 * 1024)
+        .set_max_array_size(10_000)
+        .set_max_map_size(10_000)
+        .disable_symbol("eval");
+
+    let progress = host.clone();
+    engine.on_progress(move |operations| {
+        let expired =
+            operations % PROGRESS_INTERVAL == 0 && Instant::now() > lock(&progress).deadline;
+        expired.then_some(Dynamic::UNIT)
+    });
+
+    let devices = host.clone();
+    engine.register_fn("devices", move || -> Array {
+        lock(&devices)
+            .devices
+            .iter()
+            .filter_map(|info| rhai::serde::to_dynamic(info).ok())
+            .collect()
+    });
+
+    let power = host.clone();
+    engine.register_fn(
+        "power",
+        move |command: &str, targets: Dynamic| -> Result<(), Box<EvalAltResult>> {
+            let command = DeviceCommand::from_str(command)
+                .map_err(|_| format!("Unknown power command '{command}'"))?;
+            let targets = selectors(targets)?;
+            let mut host = lock(&power);
+            let script = host.script.clone();
+            host.commands.push(HostCommand::Power {
+                script,
+                command,
+                targets,
+            });
+            Ok(())
+        },
+    );
+
+    let identify = host.clone();
+    engine.register_fn(
+        "identify",
+        move |targets: Dynamic| -> Result<(), Box<EvalAltResult>> {
+            let targets = selectors(targets)?;
+            let mut host = lock(&identify);
+            let script = host.script.clone();
+            host.commands
+                .push(HostCommand::Identify { script, targets });
+            Ok(())
+        },
+    );
+
+    let log = host.clone();
+    engine.register_fn("log", move |message: &str| push_log(&log, message));
+    let print = host.clone();
+    engine.on_print(move |message| push_log(&print, message));
+    let debug = host.clone();
+    engine.on_debug(move |message, _, _| push_log(&debug, message));
+    engine
+}
+
+fn push_log(host: &Mutex<Host>, message: &str) {
+    let mut host = lock(host);
+    let script = host.script.clone();
+    host.commands.push(HostCommand::Log {
+        script,
+        message: message.to_string(),
+    });
+}
+
+/// Accepts a single selector or an array of them
+fn selectors(targets: Dynamic) -> Result<Vec<DeviceSelector>, Box<EvalAltResult>> {
+    let targets = match targets.is_string() {
+        true => vec![targets],
+        false => targets
+            .into_array()
+            .map_err(|_| "Targets must be a string or an array of strings")?,
+    };
+    targets
+        .into_iter()
+        .map(|target| {
+            target
+                .into_string()
+                .map(DeviceSelector::new)
+                .map_err(|_| "Targets must be strings".into())
+        })
+        .collect()
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().expect("Script mutex must not be poisoned")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    fn load(dir: &Path, scripts: &[(&str, &str)], timeout_ms: u64) -> Scripts {
+        for (name, source) in scripts {
+            fs::write(dir.join(format!("{name}.rhai")), source).unwrap();
+        }
+        let config = ScriptConfig {
+            dir: dir.to_path_buf(),
+            timeout_ms,
+        };
+        Scripts::load(&config, Aliases::new()).unwrap()
+    }
+
+    fn runs(scripts: &Scripts, script: &str, handler: &str) -> Vec<ScriptRun> {
+        scripts
+            .runs()
+            .into_iter()
+            .filter(|run| run.script == script && run.handler == handler)
+            .collect()
+    }
+
+    fn logs(scripts: &Scripts) -> Vec<(String, String)> {
+        lock(&scripts.host)
+            .commands
+            .iter()
+            .filter_map(|command| match command {
+                HostCommand::Log { script, message } => Some((script.clone(), message.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn stops_slow_scripts_without_affecting_others() {
+        let dir = tempfile::tempdir().unwrap();
+        let scripts = load(
+            dir.path(),
+            &[
+                ("a_forever", "fn on_event(event) { loop {} }"),
+                ("b_quick", r#"fn on_event(event) { log("done"); }"#),
+            ],
+            20,
+        );
+        scripts.call_all(&[(HANDLER_EVENT, Dynamic::UNIT)]);
+
+        let [slow] = runs(&scripts, "a_forever", HANDLER_EVENT)
+            .try_into()
+            .unwrap();
+        assert_eq!(slow.error.as_deref(), Some("Stopped after 20ms"));
+        let [quick] = runs(&scripts, "b_quick", HANDLER_EVENT).try_into().unwrap();
+        assert_eq!(quick.error, None);
+        assert_eq!(logs(&scripts), [("b_quick".into(), "done".into())]);
+    }
+
+    #[test]
+    fn skips_scripts_that_fail_to_load() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("notes.txt"), "not a script").unwrap();
+        let scripts = load(
+            dir.path(),
+            &[
+                ("broken", "fn on_event(event) {"),
+                ("failing", "let x = 1 / 0;"),
+                ("working", "fn on_event(event) {}"),
+            ],
+            250,
+        );
+        assert_eq!(scripts.names(), ["working"]);
+        for name in ["broken", "failing"] {
+            let [load] = runs(&scripts, name, "load").try_into().unwrap();
+            assert!(load.error.is_some(), "{name} loaded");
+        }
+        let [load] = runs(&scripts, "working", "load").try_into().unwrap();
+        assert_eq!(load.error, None);
+    }
+
+    #[test]
+    fn queues_host_commands() {
+        let dir = tempfile::tempdir().unwrap();
+        let scripts = load(
+            dir.path(),
+            &[(
+                "host",
+                r#"
+                power("on", ["LHB-A", "LHB-B"]);
+                identify("LHB-A");
+                log("logged");
+                print("printed");
+                debug("debugged");
+                "#,
+            )],
+            250,
+        );
+        let host = lock(&scripts.host);
+        let [power, identify, logs @ ..] = host.commands.as_slice() else {
+            panic!("Unexpected commands {:?}", host.commands);
+        };
+        assert!(matches!(
+            power,
+            HostCommand::Power { script, command: DeviceCommand::Activate, targets }
+                if script == "host"
+                    && *targets == [DeviceSelector::new("LHB-A"), DeviceSelector::new("LHB-B")]
+        ));
+        assert!(matches!(
+            identify,
+            HostCommand::Identify { script, targets }
+                if script == "host" && *targets == [DeviceSelector::new("LHB-A")]
+        ));
+        let messages = logs
+            .iter()
+            .map(|command| match command {
+                HostCommand::Log { message, .. } => message.as_str(),
+                command => panic!("Unexpected command {command:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(messages, ["logged", "printed", r#""debugged""#]);
+    }
+
+    #[test]
+    fn rejects_targets_that_are_not_strings() {
+        assert_eq!(
+            selectors(Dynamic::from("LHB-A")).unwrap(),
+            [DeviceSelector::new("LHB-A")]
+        );
+        for targets in [
+            Dynamic::from(42_i64),
+            Dynamic::from_array(vec!["LHB-A".into(), Dynamic::from(1_i64)]),
+        ] {
+            assert!(selectors(targets).is_err());
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let scripts = load(dir.path(), &[("numbers", r#"power("on", 42);"#)], 250);
+        let [load] = runs(&scripts, "numbers", "load").try_into().unwrap();
+        let error = load.error.unwrap();
+        assert!(
+            error.contains("Targets must be a string or an array of strings"),
+            "{error}"
+        );
+        assert!(lock(&scripts.host).commands.is_empty());
+    }
+}
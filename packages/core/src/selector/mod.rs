@@ -0,0 +1,96 @@
+use std::{collections::HashMap, convert::Infallible, fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Device;
+
+/// Maps user defined aliases to a device name or address
+pub type Aliases = HashMap<String, String>;
+
+/// Maps user defined group names to the selectors of their members
+pub type Groups = HashMap<String, Vec<DeviceSelector>>;
+
+/// Matches devices by name, bluetooth address or alias, ignoring case
+///
+/// The special selector `*` matches every device
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(transparent)]
+pub struct DeviceSelector(String);
+
+impl DeviceSelector {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    pub fn all() -> Self {
+        Self("*".into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_all(&self) -> bool {
+        self.0 == "*"
+    }
+
+    /// Replaces an alias with the name or address it stands for
+    #[must_use]
+    pub fn resolve(&self, aliases: &Aliases) -> Self {
+        aliases
+            .iter()
+            .find(|(alias, _)| alias.eq_ignore_ascii_case(&self.0))
+            .map_or_else(|| self.clone(), |(_, target)| Self::new(target.as_str()))
+    }
+
+    pub fn matches(&self, name: &str, addr: &str, aliases: &Aliases) -> bool {
+        if self.is_all() {
+            return true;
+        }
+        let target = self.resolve(aliases);
+        target.0.eq_ignore_ascii_case(name) || target.0.eq_ignore_ascii_case(addr)
+    }
+
+    pub fn matches_device(&self, device: &Device, aliases: &Aliases) -> bool {
+        self.matches(device.name(), &device.address(), aliases)
+    }
+}
+
+impl FromStr for DeviceSelector {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(s))
+    }
+}
+
+impl Display for DeviceSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Replaces every selector naming a group with the group's members, ignoring case
+///
+/// Members are not expanded again, so groups cannot contain other groups
+pub fn expand_groups(selectors: &[DeviceSelector], groups: &Groups) -> Vec<DeviceSelector> {
+    selectors
+        .iter()
+        .flat_map(|selector| {
+            groups
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(selector.as_str()))
+                .map_or_else(|| vec![selector.clone()], |(_, members)| members.clone())
+        })
+        .collect()
+}
+
+/// Parses an `ALIAS=TARGET` pair as accepted on command lines
+pub fn parse_alias(value: &str) -> Result<(String, String), String> {
+    value
+        .split_once('=')
+        .map(|(alias, target)| (alias.trim().to_string(), target.trim().to_string()))
+        .filter(|(alias, target)| !alias.is_empty() && !target.is_empty())
+        .ok_or_else(|| format!("Expected ALIAS=TARGET but found '{value}'"))
+}
@@ -0,0 +1,253 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde_json::{json, Map, Value};
+
+/// Prefix of the keys SteamVR stores per application settings under
+pub const STEAMVR_APP_KEY: &str = "dev.reyma.vrlh-power-manager";
+
+const APP_CONFIG: &str = "appconfig.json";
+const MANIFEST_PATHS: &str = "manifest_paths";
+
+#[cfg(target_os = "linux")]
+const BINARY_PATH_KEY: &str = "binary_path_linux";
+#[cfg(target_os = "windows")]
+const BINARY_PATH_KEY: &str = "binary_path_windows";
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+const BINARY_PATH_KEY: &str = "binary_path_osx";
+
+/// Application SteamVR can launch on startup, running the binary with these arguments
+#[derive(Clone, Debug)]
+pub struct ManifestApp {
+    /// Appended to `STEAMVR_APP_KEY`
+    pub key: String,
+    pub name: String,
+    pub description: String,
+    pub arguments: Vec<String>,
+}
+
+pub fn generate_manifest(binary: &Path, apps: &[ManifestApp]) -> Value {
+    let applications = apps
+        .iter()
+        .map(|app| {
+            let mut application = json!({
+                "app_key": format!("{STEAMVR_APP_KEY}.{}", app.key),
+                "launch_type": "binary",
+                "arguments": join_arguments(&app.arguments),
+                // Only overlays are offered as startup applications
+                "is_dashboard_overlay": true,
+                "strings": {
+                    "en_us": {
+                        "name": app.name,
+                        "description": app.description,
+                    }
+                }
+            });
+            application[BINARY_PATH_KEY] = json!(binary.to_string_lossy());
+            application
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "source": "user",
+        "applications": applications,
+    })
+}
+
+pub fn write_manifest(path: &Path, binary: &Path, apps: &[ManifestApp]) -> crate::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let manifest = serde_json::to_string_pretty(&generate_manifest(binary, apps))?;
+    fs::write(path, manifest)?;
+    Ok(())
+}
+
+/// Adds the manifest to SteamVR's `appconfig.json`, returning whether it was missing
+///
+/// SteamVR must not be running as it rewrites the file on exit
+pub fn register_manifest(steam_config: &Path, manifest: &Path) -> crate::Result<bool> {
+    let manifest = manifest.to_string_lossy();
+    update_manifest_paths(steam_config, |paths| {
+        if paths.iter().any(|path| path.as_str() == Some(&manifest)) {
+            return false;
+        }
+        paths.push(json!(manifest));
+        true
+    })
+}
+
+/// Removes the manifest from SteamVR's `appconfig.json`, returning whether it was present
+pub fn unregister_manifest(steam_config: &Path, manifest: &Path) -> crate::Result<bool> {
+    let manifest = manifest.to_string_lossy();
+    update_manifest_paths(steam_config, |paths| {
+        let len = paths.len();
+        paths.retain(|path| path.as_str() != Some(&manifest));
+        paths.len() != len
+    })
+}
+
+fn update_manifest_paths(
+    steam_config: &Path,
+    update: impl FnOnce(&mut Vec<Value>) -> bool,
+) -> crate::Result<bool> {
+    let path = steam_config.join(APP_CONFIG);
+    let mut config = match fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str::<Map<String, Value>>(&content)?,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Map::new(),
+        Err(err) => return Err(err.into()),
+    };
+    let paths = config
+        .entry(MANIFEST_PATHS)
+        .or_insert_with(|| Value::Array(Vec::new()));
+    let Value::Array(paths) = paths else {
+        return Err(crate::Error::Vrlh("Invalid SteamVR app config!"));
+    };
+    if !update(paths) {
+        return Ok(false);
+    }
+    fs::create_dir_all(steam_config)?;
+    fs::write(&path, serde_json::to_string_pretty(&config)?)?;
+    Ok(true)
+}
+
+/// First Steam `config` directory found in the usual install locations
+pub fn default_steam_config_dir() -> Option<PathBuf> {
+    let home = dirs::home_dir()?;
+    let candidates = [
+        home.join(".steam/steam/config"),
+        home.join(".local/share/Steam/config"),
+        home.join(".var/app/com.valvesoftware.Steam/.local/share/Steam/config"),
+        home.join("Library/Application Support/Steam/config"),
+        PathBuf::from(r"C:\Program Files (x86)\Steam\config"),
+    ];
+    candidates.into_iter().find(|candidate| candidate.is_dir())
+}
+
+pub fn default_manifest_path() -> Option<PathBuf> {
+    Some(dirs::data_dir()?.join("vrlh-power-manager/vrlh-power-manager.vrmanifest"))
+}
+
+/// SteamVR takes a single argument string, so arguments containing spaces, quotes or
+/// backslashes are quoted with the quotes and backslashes escaped
+fn join_arguments(arguments: &[String]) -> String {
+    arguments
+        .iter()
+        .map(|argument| {
+            if !argument.contains(|c: char| c.is_whitespace() || c == '"' || c == '\\') {
+                return argument.clone();
+            }
+            let escaped = argument.replace('\\', r"\\").replace('"', r#"\""#);
+            format!(r#""{escaped}""#)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(arguments: &[&str]) -> ManifestApp {
+        ManifestApp {
+            key: "poweron".into(),
+            name: "Lighthouse Power On".into(),
+            description: "Powers on lighthouses".into(),
+            arguments: arguments.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    fn manifest_paths(steam_config: &Path) -> Value {
+        let content = fs::read_to_string(steam_config.join(APP_CONFIG)).unwrap();
+        serde_json::from_str::<Value>(&content).unwrap()[MANIFEST_PATHS].clone()
+    }
+
+    #[test]
+    fn generates_an_application_per_app() {
+        let manifest = generate_manifest(
+            Path::new("/opt/vrlh"),
+            &[app(&["on", "--all"]), app(&["steamvr", "companion"])],
+        );
+        let applications = manifest["applications"].as_array().unwrap();
+        assert_eq!(applications.len(), 2);
+        let application = &applications[0];
+        assert_eq!(
+            application["app_key"],
+            "dev.reyma.vrlh-power-manager.poweron"
+        );
+        assert_eq!(application["arguments"], "on --all");
+        assert_eq!(application[BINARY_PATH_KEY], "/opt/vrlh");
+        assert_eq!(
+            application["strings"]["en_us"]["name"],
+            "Lighthouse Power On"
+        );
+        assert_eq!(applications[1]["arguments"], "steamvr companion");
+    }
+
+    #[test]
+    fn quotes_arguments_with_whitespace() {
+        let arguments =
+            ["--config=/home/me/VR Stuff/config.toml", "on", "Left\tBase"].map(String::from);
+        assert_eq!(
+            join_arguments(&arguments),
+            "\"--config=/home/me/VR Stuff/config.toml\" on \"Left\tBase\""
+        );
+        assert_eq!(join_arguments(&[]), "");
+    }
+
+    #[test]
+    fn escapes_quotes_and_backslashes() {
+        let arguments = [r#"--name=Say "hi""#, r"C:\VR", "plain"].map(String::from);
+        assert_eq!(
+            join_arguments(&arguments),
+            r#""--name=Say \"hi\"" "C:\\VR" plain"#
+        );
+    }
+
+    #[test]
+    fn registers_manifests_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let steam_config = dir.path().join("config");
+        let manifest = dir.path().join("vrlh.vrmanifest");
+
+        // A missing appconfig.json is created
+        assert!(register_manifest(&steam_config, &manifest).unwrap());
+        assert!(!register_manifest(&steam_config, &manifest).unwrap());
+        assert_eq!(
+            manifest_paths(&steam_config),
+            json!([manifest.to_string_lossy()])
+        );
+
+        assert!(unregister_manifest(&steam_config, &manifest).unwrap());
+        assert!(!unregister_manifest(&steam_config, &manifest).unwrap());
+        assert_eq!(manifest_paths(&steam_config), json!([]));
+    }
+
+    #[test]
+    fn keeps_other_settings_and_manifests() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = dir.path().join("vrlh.vrmanifest");
+        let config = json!({
+            "manifest_paths": ["/other.vrmanifest"],
+            "last_launch_time": "1700000000",
+        });
+        fs::write(dir.path().join(APP_CONFIG), config.to_string()).unwrap();
+
+        register_manifest(dir.path(), &manifest).unwrap();
+        unregister_manifest(dir.path(), &manifest).unwrap();
+        let content = fs::read_to_string(dir.path().join(APP_CONFIG)).unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&content).unwrap(), config);
+    }
+
+    #[test]
+    fn leaves_missing_app_configs_alone_when_unregistering() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = dir.path().join("vrlh.vrmanifest");
+        assert!(!unregister_manifest(dir.path(), &manifest).unwrap());
+        assert!(!dir.path().join(APP_CONFIG).exists());
+
+        fs::write(dir.path().join(APP_CONFIG), r#"{"manifest_paths": {}}"#).unwrap();
+        assert!(register_manifest(dir.path(), &manifest).is_err());
+    }
+}
@@ -0,0 +1,12 @@
+mod manifest;
+
+use crate::ProcessWatcher;
+
+pub use manifest::*;
+
+/// Processes running for as long as SteamVR is
+pub const STEAMVR_PROCESSES: &[&str] = &["vrserver", "vrmonitor"];
+
+pub fn steamvr_watcher() -> ProcessWatcher {
+    ProcessWatcher::new(STEAMVR_PROCESSES.iter().copied())
+}
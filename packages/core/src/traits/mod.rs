@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 use tokio::sync::mpsc::{error::SendError, Sender};
 
-use crate::{Device, DeviceInfo, DeviceLocalStatus, DeviceRemoteStatus};
+use crate::{Device, DeviceInfo, DeviceLocalStatus, DeviceRemoteStatus, DeviceWarning};
 
 pub trait FromDeviceStatus<T> {
     fn from_device_status(device: &Device, status: T) -> Self;
@@ -15,6 +15,7 @@ impl FromDeviceStatus<DeviceLocalStatus> for DeviceInfo {
             name: device.name().to_string(),
             local: Some(status),
             remote: None,
+            warnings: Vec::new(),
         }
     }
 }
@@ -27,6 +28,20 @@ impl FromDeviceStatus<DeviceRemoteStatus> for DeviceInfo {
             name: device.name().to_string(),
             local: None,
             remote: Some(status),
+            warnings: Vec::new(),
+        }
+    }
+}
+
+impl FromDeviceStatus<DeviceWarning> for DeviceInfo {
+    fn from_device_status(device: &Device, status: DeviceWarning) -> Self {
+        Self {
+            id: device.id(),
+            addr: device.address(),
+            name: device.name().to_string(),
+            local: None,
+            remote: None,
+            warnings: vec![status],
         }
     }
 }
@@ -47,6 +62,7 @@ impl SendDeviceStatus<DeviceLocalStatus> for Sender<DeviceInfo> {
         device: &Device,
         status: DeviceLocalStatus,
     ) -> Result<(), SendError<DeviceInfo>> {
+        device.record_local(status.clone());
         let info = DeviceInfo::from_device_status(device, status);
         self.send(info).await
     }
@@ -58,6 +74,20 @@ impl SendDeviceStatus<DeviceRemoteStatus> for Sender<DeviceInfo> {
         &self,
         device: &Device,
         status: DeviceRemoteStatus,
+    ) -> Result<(), SendError<DeviceInfo>> {
+        let warnings = device.record_remote(status.clone());
+        let mut info = DeviceInfo::from_device_status(device, status);
+        info.warnings = warnings;
+        self.send(info).await
+    }
+}
+
+#[async_trait]
+impl SendDeviceStatus<DeviceWarning> for Sender<DeviceInfo> {
+    async fn send_device_status(
+        &self,
+        device: &Device,
+        status: DeviceWarning,
     ) -> Result<(), SendError<DeviceInfo>> {
         let info = DeviceInfo::from_device_status(device, status);
         self.send(info).await
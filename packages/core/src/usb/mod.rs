@@ -0,0 +1,131 @@
+use std::{fs, path::PathBuf, time::Duration};
+
+use tokio::sync::watch;
+
+use crate::{poll_presence, UsbId};
+
+/// Polls a sysfs USB device tree for any of the given devices
+#[derive(Clone, Debug)]
+pub struct UsbWatcher {
+    root: PathBuf,
+    ids: Vec<UsbId>,
+    interval: Duration,
+}
+
+impl UsbWatcher {
+    pub fn new(ids: Vec<UsbId>) -> Self {
+        Self {
+            root: PathBuf::from("/sys/bus/usb/devices"),
+            ids,
+            interval: Duration::from_secs(2),
+        }
+    }
+
+    /// Reads devices from a fake tree instead of `/sys/bus/usb/devices`
+    #[must_use]
+    pub fn with_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = root.into();
+        self
+    }
+
+    #[must_use]
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Watched IDs with at least one device connected
+    pub fn present(&self) -> Vec<UsbId> {
+        let Ok(entries) = fs::read_dir(&self.root) else {
+            return Vec::new();
+        };
+        let mut present = entries
+            .flatten()
+            .filter_map(|entry| {
+                let read = |name| fs::read_to_string(entry.path().join(name)).ok();
+                let vendor = u16::from_str_radix(read("idVendor")?.trim(), 16).ok()?;
+                let product = u16::from_str_radix(read("idProduct")?.trim(), 16).ok()?;
+                Some(UsbId::new(vendor, product))
+            })
+            .filter(|id| self.ids.contains(id))
+            .collect::<Vec<_>>();
+        present.sort_by_key(|id| (id.vendor, id.product));
+        present.dedup();
+        present
+    }
+
+    pub fn is_present(&self) -> bool {
+        !self.present().is_empty()
+    }
+
+    /// Polls in the background until every receiver is dropped
+    pub fn watch(self) -> watch::Receiver<bool> {
+        poll_presence(self.interval, move || self.is_present())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    const INDEX: UsbId = UsbId::new(0x28de, 0x2300);
+
+    fn plug(root: &Path, port: &str, vendor: &str, product: &str) {
+        let dir = root.join(port);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("idVendor"), format!("{vendor}\n")).unwrap();
+        fs::write(dir.join("idProduct"), format!("{product}\n")).unwrap();
+    }
+
+    fn unplug(root: &Path, port: &str) {
+        fs::remove_dir_all(root.join(port)).unwrap();
+    }
+
+    #[test]
+    fn finds_watched_devices() {
+        let root = tempfile::tempdir().unwrap();
+        let watcher = UsbWatcher::new(vec![INDEX]).with_root(root.path());
+        assert!(!watcher.is_present());
+
+        plug(root.path(), "1-2", "28de", "2300");
+        plug(root.path(), "3-1", "28de", "2300");
+        // Interfaces have no IDs of their own
+        fs::create_dir_all(root.path().join("1-2:1.0")).unwrap();
+        assert_eq!(watcher.present(), [INDEX]);
+
+        unplug(root.path(), "1-2");
+        assert!(watcher.is_present());
+        unplug(root.path(), "3-1");
+        assert!(!watcher.is_present());
+    }
+
+    #[test]
+    fn ignores_other_devices() {
+        let root = tempfile::tempdir().unwrap();
+        plug(root.path(), "1-1", "046d", "c52b");
+        plug(root.path(), "1-2", "28de", "2101");
+        plug(root.path(), "1-3", "not hex", "2300");
+        let watcher = UsbWatcher::new(vec![INDEX]).with_root(root.path());
+        assert!(watcher.present().is_empty());
+
+        let watcher = UsbWatcher::new(vec![UsbId::new(0x046d, 0xc52b)]).with_root(root.path());
+        assert_eq!(watcher.present(), [UsbId::new(0x046d, 0xc52b)]);
+    }
+
+    #[tokio::test]
+    async fn reports_plugging_and_unplugging() {
+        let root = tempfile::tempdir().unwrap();
+        let mut presence = UsbWatcher::new(vec![INDEX])
+            .with_root(root.path())
+            .with_interval(Duration::from_millis(10))
+            .watch();
+        assert!(!*presence.borrow());
+
+        plug(root.path(), "1-2", "28de", "2300");
+        presence.wait_for(|present| *present).await.unwrap();
+        unplug(root.path(), "1-2");
+        presence.wait_for(|present| !*present).await.unwrap();
+    }
+}
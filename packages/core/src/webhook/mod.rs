@@ -0,0 +1,420 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::{sync::broadcast::error::RecvError, time::sleep};
+
+use crate::{
+    history::{unix_timestamp, History},
+    rules::snapshot_devices,
+    DeviceList, LifecycleEvent, LifecycleTracker, WebhookConfig, WebhookDelivery,
+};
+
+/// Number of delivery attempts kept for inspection
+const DELIVERY_HISTORY: usize = 100;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_BACKOFF: Duration = Duration::from_mins(5);
+
+/// Posts lifecycle events to the configured URLs, retrying failed deliveries with backoff
+///
+/// Can be cloned and will retain references to the same delivery history
+#[derive(Clone, Debug)]
+pub struct Webhooks {
+    client: reqwest::Client,
+    hooks: Arc<Vec<WebhookConfig>>,
+    deliveries: History<WebhookDelivery>,
+}
+
+impl Webhooks {
+    pub fn new(hooks: Vec<WebhookConfig>) -> crate::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|_| crate::Error::Vrlh("Failed to create HTTP client!"))?;
+        Ok(Self {
+            client,
+            hooks: Arc::new(hooks),
+            deliveries: History::new(DELIVERY_HISTORY),
+        })
+    }
+
+    /// Most recent delivery attempts, oldest first
+    pub fn deliveries(&self) -> Vec<WebhookDelivery> {
+        self.deliveries.records()
+    }
+
+    /// Dispatches events from the device list until it is dropped
+    pub async fn run(&self, devices: &DeviceList) {
+        let mut events = devices.subscribe();
+        let mut tracker = LifecycleTracker::default();
+        loop {
+            match events.recv().await {
+                Ok(event) => {
+                    for event in tracker.update(&event) {
+                        self.dispatch(&event);
+                    }
+                }
+                Err(RecvError::Lagged(_)) => {
+                    for event in tracker.resync(&snapshot_devices(devices)) {
+                        self.dispatch(&event);
+                    }
+                }
+                Err(RecvError::Closed) => return,
+            }
+        }
+    }
+
+    /// Delivers the event to every webhook accepting it in the background
+    pub fn dispatch(&self, event: &LifecycleEvent) {
+        for hook in self.hooks.iter().filter(|hook| hook.accepts(event.kind())) {
+            let body = match &hook.template {
+                Some(template) => render(template, event),
+                None => serde_json::to_string(event).unwrap_or_default(),
+            };
+            let webhooks = self.clone();
+            let hook = hook.clone();
+            let kind = event.kind();
+            tokio::spawn(async move { webhooks.deliver(&hook, kind, body).await });
+        }
+    }
+
+    async fn deliver(&self, hook: &WebhookConfig, event: crate::LifecycleEventKind, body: String) {
+        let mut backoff = Duration::from_secs(hook.backoff_secs).min(MAX_BACKOFF);
+        for attempt in 1..=hook.max_attempts.max(1) {
+            let response = self
+                .client
+                .post(&hook.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.clone())
+                .send()
+                .await;
+            let (status, error) = match response {
+                Ok(response) if response.status().is_success() => {
+                    (Some(response.status().as_u16()), None)
+                }
+                Ok(response) => (
+                    Some(response.status().as_u16()),
+                    Some(format!("Received status {}", response.status())),
+                ),
+                Err(err) => (None, Some(err.to_string())),
+            };
+            let succeeded = error.is_none();
+            self.deliveries.push(WebhookDelivery {
+                url: hook.url.clone(),
+                event,
+                attempt,
+                status,
+                error,
+                timestamp: unix_timestamp(),
+            });
+            if succeeded {
+                return;
+            }
+            if attempt < hook.max_attempts {
+                sleep(backoff).await;
+                backoff = next_backoff(backoff);
+            }
+        }
+    }
+}
+
+/// Doubles the delay between attempts up to `MAX_BACKOFF`
+fn next_backoff(backoff: Duration) -> Duration {
+    backoff.saturating_mul(2).min(MAX_BACKOFF)
+}
+
+/// Replaces `{{placeholder}}`s in a JSON template, values are escaped to be placed inside
+/// a JSON string except for `{{json}}` which inserts the whole event
+///
+/// Available placeholders are `event`, `name`, `addr`, `local`, `remote`, `reason`, `count` and `json`
+pub fn render(template: &str, event: &LifecycleEvent) -> String {
+    // A single pass, so placeholders inside substituted values are left alone
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let placeholder = after
+            .split_once("}}")
+            .and_then(|(key, _)| Some((key, placeholder(key, event)?)));
+        if let Some((key, value)) = placeholder {
+            rendered.push_str(&value);
+            rest = &after[key.len() + 2..];
+        } else {
+            // Anything else is kept, looking for a placeholder again from the next brace
+            rendered.push('{');
+            rest = &rest[start + 1..];
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+fn placeholder(key: &str, event: &LifecycleEvent) -> Option<String> {
+    let device = event.device();
+    let value = match key {
+        "json" => return Some(serde_json::to_string(event).unwrap_or_default()),
+        "event" => event.kind().to_string(),
+        "name" => device.map(|device| device.name.clone()).unwrap_or_default(),
+        "addr" => device.map(|device| device.addr.clone()).unwrap_or_default(),
+        "local" => device
+            .and_then(|device| device.local.as_ref())
+            .map(ToString::to_string)
+            .unwrap_or_default(),
+        "remote" => device
+            .and_then(|device| device.remote.as_ref())
+            .map(ToString::to_string)
+            .unwrap_or_default(),
+        "reason" => event.reason().unwrap_or_default().to_string(),
+        "count" => event.devices().len().to_string(),
+        _ => return None,
+    };
+    Some(escape(&value))
+}
+
+fn escape(value: &str) -> String {
+    let quoted = serde_json::to_string(value).unwrap_or_default();
+    quoted
+        .strip_prefix('"')
+        .and_then(|quoted| quoted.strip_suffix('"'))
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+        net::TcpListener,
+        time::timeout,
+    };
+
+    use super::*;
+    use crate::{DeviceInfo, DeviceRemoteStatus, LifecycleEventKind};
+
+    /// Answers requests with the given statuses in order, repeating the last one,
+    /// and collects the request bodies
+    async fn serve(statuses: Vec<u16>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let bodies = Arc::new(Mutex::new(Vec::new()));
+        let received = Arc::clone(&bodies);
+        tokio::spawn(async move {
+            for index in 0.. {
+                let Ok((stream, _)) = listener.accept().await else {
+                    return;
+                };
+                let mut stream = BufReader::new(stream);
+                let mut length = 0;
+                let mut line = String::new();
+                while stream.read_line(&mut line).await.unwrap() > 2 {
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            length = value.trim().parse().unwrap();
+                        }
+                    }
+                    line.clear();
+                }
+                let mut body = vec![0; length];
+                stream.read_exact(&mut body).await.unwrap();
+                received
+                    .lock()
+                    .unwrap()
+                    .push(String::from_utf8(body).unwrap());
+
+                let status = statuses[index.min(statuses.len() - 1)];
+                let response = format!(
+                    "HTTP/1.1 {status} X\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                );
+                let _ = stream.get_mut().write_all(response.as_bytes()).await;
+            }
+        });
+        (url, bodies)
+    }
+
+    fn hook(url: &str, max_attempts: u32) -> WebhookConfig {
+        WebhookConfig {
+            max_attempts,
+            backoff_secs: 0,
+            ..WebhookConfig::new(url)
+        }
+    }
+
+    /// Waits for the given number of deliveries, then a little longer to catch extra ones
+    async fn wait_for_deliveries(webhooks: &Webhooks, count: usize) -> Vec<WebhookDelivery> {
+        timeout(Duration::from_secs(10), async {
+            while webhooks.deliveries().len() < count {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        sleep(Duration::from_millis(100)).await;
+        webhooks.deliveries()
+    }
+
+    fn failed(name: &str, reason: &str) -> LifecycleEvent {
+        LifecycleEvent::DeviceFailed {
+            device: DeviceInfo::fake(name, "AA:BB:CC:DD:EE:FF", &DeviceRemoteStatus::Standby),
+            reason: reason.into(),
+        }
+    }
+
+    #[test]
+    fn renders_placeholders() {
+        let template = r#"{"text": "{{event}} {{name}} {{addr}} [{{local}}] {{remote}}: {{reason}} ({{count}})"}"#;
+        let rendered = render(template, &failed("LHB-1", "timed out"));
+        assert_eq!(
+            rendered,
+            r#"{"text": "DEVICE_FAILED LHB-1 AA:BB:CC:DD:EE:FF [] STANDBY: timed out (1)"}"#
+        );
+
+        let event = LifecycleEvent::AllOff {
+            devices: Vec::new(),
+        };
+        assert_eq!(
+            render(r#""{{event}}/{{name}}/{{count}}""#, &event),
+            r#""ALL_OFF//0""#
+        );
+    }
+
+    #[test]
+    fn escapes_values_inside_json_strings() {
+        let event = failed(r#"Left "Base" \ 1"#, "line one\nline two\ttabbed");
+        let rendered = render(r#"{"name": "{{name}}", "reason": "{{reason}}"}"#, &event);
+        assert_eq!(
+            rendered,
+            r#"{"name": "Left \"Base\" \\ 1", "reason": "line one\nline two\ttabbed"}"#
+        );
+        let parsed = serde_json::from_str::<serde_json::Value>(&rendered).unwrap();
+        assert_eq!(parsed["name"], r#"Left "Base" \ 1"#);
+        assert_eq!(parsed["reason"], "line one\nline two\ttabbed");
+    }
+
+    #[test]
+    fn inserts_the_whole_event_as_json() {
+        let event = failed("LHB-1", "timed out");
+        let rendered = render(r#"{"event": {{json}}}"#, &event);
+        let parsed = serde_json::from_str::<serde_json::Value>(&rendered).unwrap();
+        assert_eq!(parsed["event"], serde_json::to_value(&event).unwrap());
+    }
+
+    #[test]
+    fn leaves_placeholders_inside_values_alone() {
+        let event = failed("{{reason}} {{json}}", "{{name}}");
+        let rendered = render(r#"{"name": "{{name}}", "reason": "{{reason}}"}"#, &event);
+        assert_eq!(
+            rendered,
+            r#"{"name": "{{reason}} {{json}}", "reason": "{{name}}"}"#
+        );
+        assert_eq!(render("{{{name}}}", &failed("LHB-1", "")), "{LHB-1}");
+    }
+
+    #[test]
+    fn keeps_unknown_placeholders() {
+        let rendered = render("{{nmae}} {{ name }} {name} {{name}}", &failed("LHB-1", ""));
+        assert_eq!(rendered, "{{nmae}} {{ name }} {name} LHB-1");
+    }
+
+    #[tokio::test]
+    async fn retries_until_delivered() {
+        let (url, bodies) = serve(vec![500, 503, 204]).await;
+        let webhooks = Webhooks::new(vec![hook(&url, 5)]).unwrap();
+        webhooks.dispatch(&failed("LHB-1", "timed out"));
+
+        let deliveries = wait_for_deliveries(&webhooks, 3).await;
+        let attempts = deliveries
+            .iter()
+            .map(|delivery| (delivery.attempt, delivery.status, delivery.succeeded()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            attempts,
+            [
+                (1, Some(500), false),
+                (2, Some(503), false),
+                (3, Some(204), true)
+            ]
+        );
+        assert_eq!(
+            deliveries[0].error.as_deref(),
+            Some("Received status 500 Internal Server Error")
+        );
+        assert!(deliveries
+            .iter()
+            .all(|delivery| delivery.event == LifecycleEventKind::DeviceFailed));
+
+        let bodies = bodies.lock().unwrap();
+        assert_eq!(bodies.len(), 3);
+        let body = serde_json::from_str::<serde_json::Value>(&bodies[0]).unwrap();
+        assert_eq!(body["type"], "DeviceFailed");
+        assert_eq!(body["reason"], "timed out");
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_the_last_attempt() {
+        let (url, bodies) = serve(vec![500]).await;
+        let webhooks = Webhooks::new(vec![hook(&url, 3)]).unwrap();
+        webhooks.dispatch(&failed("LHB-1", ""));
+
+        let deliveries = wait_for_deliveries(&webhooks, 3).await;
+        assert_eq!(deliveries.len(), 3);
+        assert!(deliveries.iter().all(|delivery| !delivery.succeeded()));
+        assert_eq!(bodies.lock().unwrap().len(), 3);
+
+        // Unreachable URLs fail without a status, at least one attempt is always made
+        let closed = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", closed.local_addr().unwrap());
+        drop(closed);
+        let webhooks = Webhooks::new(vec![hook(&url, 0)]).unwrap();
+        webhooks.dispatch(&failed("LHB-1", ""));
+        let [delivery] = wait_for_deliveries(&webhooks, 1).await.try_into().unwrap();
+        assert_eq!((delivery.attempt, delivery.status), (1, None));
+        assert!(delivery.error.is_some());
+    }
+
+    #[tokio::test]
+    async fn sends_templates_to_matching_hooks_only() {
+        let (url, bodies) = serve(vec![200]).await;
+        let templated = WebhookConfig {
+            events: vec![LifecycleEventKind::DeviceFailed],
+            template: Some(r#"{"text": "{{name}}: {{reason}}"}"#.into()),
+            ..hook(&url, 1)
+        };
+        let ignoring = WebhookConfig {
+            events: vec![LifecycleEventKind::AllOff],
+            ..hook(&url, 1)
+        };
+        let webhooks = Webhooks::new(vec![templated, ignoring]).unwrap();
+        webhooks.dispatch(&failed("LHB-1", "timed out"));
+
+        wait_for_deliveries(&webhooks, 1).await;
+        assert_eq!(*bodies.lock().unwrap(), [r#"{"text": "LHB-1: timed out"}"#]);
+    }
+
+    #[tokio::test]
+    async fn keeps_the_latest_deliveries() {
+        let (url, bodies) = serve(vec![200]).await;
+        let webhooks = Webhooks::new(vec![hook(&url, 1)]).unwrap();
+        for _ in 0..=DELIVERY_HISTORY {
+            webhooks.dispatch(&failed("LHB-1", ""));
+        }
+        timeout(Duration::from_secs(10), async {
+            while bodies.lock().unwrap().len() <= DELIVERY_HISTORY {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        // Every request arrived, so the last delivery is about to be recorded
+        let deliveries = wait_for_deliveries(&webhooks, DELIVERY_HISTORY).await;
+        assert_eq!(deliveries.len(), DELIVERY_HISTORY);
+    }
+
+    #[test]
+    fn doubles_the_backoff_up_to_the_limit() {
+        assert_eq!(next_backoff(Duration::ZERO), Duration::ZERO);
+        assert_eq!(next_backoff(Duration::from_secs(2)), Duration::from_secs(4));
+        assert_eq!(next_backoff(Duration::from_secs(200)), MAX_BACKOFF);
+        assert_eq!(next_backoff(Duration::MAX), MAX_BACKOFF);
+    }
+}
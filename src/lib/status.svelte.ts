@@ -1,3 +1,4 @@
+import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
 
 class Status {
@@ -5,6 +6,9 @@ class Status {
 
   public constructor() {
     void listen<string>("status", ({ payload }) => void status.push(payload));
+    void invoke<string[]>("startup_notices").then((notices) => {
+      for (const notice of notices) this.push(notice);
+    });
   }
 
   public get current(): string {